@@ -0,0 +1,508 @@
+use std::{collections::HashMap, hash::BuildHasherDefault};
+
+use wgpu::util::DeviceExt;
+
+use crate::{mipmap, texture::Texture};
+
+/// How bright parts of the scene bleed into their surroundings, see
+/// [`crate::Context::set_bloom`].
+///
+/// Everything above `threshold` is blurred over a chain of `levels` ever smaller textures,
+/// starting at half the surface size, and the levels are added back up and onto the scene.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BloomSettings {
+    /// Scene brightness, in linear HDR units, above which pixels start to bloom.
+    pub threshold: f32,
+    /// Width of the soft transition below the threshold, 0 for a hard cut.
+    pub knee: f32,
+    /// Scales the bloom added to the scene.
+    pub intensity: f32,
+    /// Number of blurred levels, fewer when the surface is too small for them. More levels
+    /// spread the glow further.
+    pub levels: u32,
+    /// Spread of the upsampling tent filter in texels of each level.
+    pub radius: f32,
+}
+
+impl Default for BloomSettings {
+    fn default() -> Self {
+        Self {
+            threshold: 1.0,
+            knee: 0.5,
+            intensity: 0.5,
+            levels: 6,
+            radius: 1.0,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+struct BloomData {
+    threshold: f32,
+    knee: f32,
+    /// Already divided by the level count, since the upsampling adds every level up.
+    intensity: f32,
+    radius: f32,
+}
+
+unsafe impl bytemuck::Pod for BloomData {}
+unsafe impl bytemuck::Zeroable for BloomData {}
+
+impl BloomData {
+    fn new(settings: &BloomSettings, config: &wgpu::SurfaceConfiguration) -> Self {
+        Self {
+            threshold: settings.threshold,
+            knee: settings.knee,
+            intensity: settings.intensity / level_count(settings, config) as f32,
+            radius: settings.radius,
+        }
+    }
+}
+
+/// Size of the largest level, half that of the surface.
+fn chain_size(config: &wgpu::SurfaceConfiguration) -> (u32, u32) {
+    ((config.width / 2).max(1), (config.height / 2).max(1))
+}
+
+/// [`BloomSettings::levels`], limited to what fits the surface.
+fn level_count(settings: &BloomSettings, config: &wgpu::SurfaceConfiguration) -> u32 {
+    let (width, height) = chain_size(config);
+
+    settings.levels.clamp(1, mipmap::level_count(width, height))
+}
+
+/// The bloom passes and the mip chain they blur in. Reads the scene through the post-processing
+/// input layout, so it slots into the post stack's ping-pong.
+pub(crate) struct Bloom {
+    settings: BloomSettings,
+    prefilter: wgpu::RenderPipeline,
+    downsample: wgpu::RenderPipeline,
+    upsample: wgpu::RenderPipeline,
+    composite: wgpu::RenderPipeline,
+    composite_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    buffer: wgpu::Buffer,
+    parameters: wgpu::BindGroup,
+    chain: Chain,
+}
+
+/// The size dependent part of [`Bloom`], recreated on resize.
+struct Chain {
+    /// One view per level, level 0 being half the surface size.
+    levels: Vec<wgpu::TextureView>,
+    /// Reads each level, for the pass writing the next or previous one.
+    inputs: Vec<wgpu::BindGroup>,
+    /// Parameters plus level 0, for compositing.
+    composite: wgpu::BindGroup,
+}
+
+impl Bloom {
+    pub fn new(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        input_layout: &wgpu::BindGroupLayout,
+        settings: BloomSettings,
+    ) -> Self {
+        let parameters_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Bloom Parameters Layout"),
+            entries: &[parameters_entry()],
+        });
+
+        let composite_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Bloom Composite Layout"),
+            entries: &[
+                parameters_entry(),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        let pass_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Bloom Pipeline Layout"),
+            bind_group_layouts: &[input_layout, &parameters_layout],
+            push_constant_ranges: &[],
+        });
+
+        let composite_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Bloom Composite Pipeline Layout"),
+                bind_group_layouts: &[input_layout, &composite_layout],
+                push_constant_ranges: &[],
+            });
+
+        let downsample = include_str!("resources/shaders/bloom_downsample.frag");
+        let mut prefilter_defines = HashMap::with_hasher(BuildHasherDefault::default());
+        prefilter_defines.insert("PREFILTER".to_owned(), "1".to_owned());
+
+        // Levels are summed up by adding each one onto the next larger one
+        let additive = wgpu::BlendState {
+            color: wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::One,
+                dst_factor: wgpu::BlendFactor::One,
+                operation: wgpu::BlendOperation::Add,
+            },
+            alpha: wgpu::BlendComponent::REPLACE,
+        };
+
+        let prefilter = bloom_pipeline(
+            device,
+            &pass_layout,
+            "Bloom Prefilter",
+            downsample,
+            prefilter_defines,
+            None,
+        );
+        let downsample = bloom_pipeline(
+            device,
+            &pass_layout,
+            "Bloom Downsample",
+            downsample,
+            HashMap::with_hasher(BuildHasherDefault::default()),
+            None,
+        );
+        let upsample = bloom_pipeline(
+            device,
+            &pass_layout,
+            "Bloom Upsample",
+            include_str!("resources/shaders/bloom_upsample.frag"),
+            HashMap::with_hasher(BuildHasherDefault::default()),
+            Some(additive),
+        );
+        let composite = bloom_pipeline(
+            device,
+            &composite_pipeline_layout,
+            "Bloom Composite",
+            include_str!("resources/shaders/bloom_composite.frag"),
+            HashMap::with_hasher(BuildHasherDefault::default()),
+            None,
+        );
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Bloom Sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Bloom Buffer"),
+            contents: bytemuck::cast_slice(&[BloomData::new(&settings, config)]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let parameters = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Bloom Parameters Bind Group"),
+            layout: &parameters_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            }],
+        });
+
+        let chain = Chain::new(
+            device,
+            config,
+            &settings,
+            input_layout,
+            &composite_layout,
+            &sampler,
+            &buffer,
+        );
+
+        Self {
+            settings,
+            prefilter,
+            downsample,
+            upsample,
+            composite,
+            composite_layout,
+            sampler,
+            buffer,
+            parameters,
+            chain,
+        }
+    }
+
+    pub fn settings(&self) -> &BloomSettings {
+        &self.settings
+    }
+
+    /// Follows the surface to a new size. `input_layout` is the one the bloom was created with.
+    pub fn resize(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        config: &wgpu::SurfaceConfiguration,
+        input_layout: &wgpu::BindGroupLayout,
+    ) {
+        self.chain = Chain::new(
+            device,
+            config,
+            &self.settings,
+            input_layout,
+            &self.composite_layout,
+            &self.sampler,
+            &self.buffer,
+        );
+
+        // The level count, and with it the intensity, may have changed
+        queue.write_buffer(
+            &self.buffer,
+            0,
+            bytemuck::cast_slice(&[BloomData::new(&self.settings, config)]),
+        );
+    }
+
+    /// Blurs the bright parts of `input` and writes them, added onto `input`, into `view`.
+    pub fn render(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        input: &wgpu::BindGroup,
+        view: &wgpu::TextureView,
+    ) {
+        let levels = &self.chain.levels;
+        let inputs = &self.chain.inputs;
+
+        self.pass(encoder, &self.prefilter, input, &levels[0], true);
+
+        for level in 1..levels.len() {
+            self.pass(
+                encoder,
+                &self.downsample,
+                &inputs[level - 1],
+                &levels[level],
+                true,
+            );
+        }
+
+        for level in (0..levels.len() - 1).rev() {
+            self.pass(
+                encoder,
+                &self.upsample,
+                &inputs[level + 1],
+                &levels[level],
+                false,
+            );
+        }
+
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Bloom Composite Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+
+        pass.set_pipeline(&self.composite);
+        pass.set_bind_group(0, input, &[]);
+        pass.set_bind_group(1, &self.chain.composite, &[]);
+        pass.draw(0..3, 0..1);
+    }
+
+    fn pass(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        pipeline: &wgpu::RenderPipeline,
+        input: &wgpu::BindGroup,
+        view: &wgpu::TextureView,
+        clear: bool,
+    ) {
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Bloom Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: if clear {
+                        wgpu::LoadOp::Clear(wgpu::Color::BLACK)
+                    } else {
+                        wgpu::LoadOp::Load
+                    },
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+
+        pass.set_pipeline(pipeline);
+        pass.set_bind_group(0, input, &[]);
+        pass.set_bind_group(1, &self.parameters, &[]);
+        pass.draw(0..3, 0..1);
+    }
+}
+
+impl Chain {
+    fn new(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        settings: &BloomSettings,
+        input_layout: &wgpu::BindGroupLayout,
+        composite_layout: &wgpu::BindGroupLayout,
+        sampler: &wgpu::Sampler,
+        buffer: &wgpu::Buffer,
+    ) -> Self {
+        let (width, height) = chain_size(config);
+        let level_count = level_count(settings, config);
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("bloom_chain"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: level_count,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: Texture::HDR_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+
+        let levels = (0..level_count)
+            .map(|level| {
+                texture.create_view(&wgpu::TextureViewDescriptor {
+                    label: Some("Bloom Level View"),
+                    base_mip_level: level,
+                    mip_level_count: Some(1),
+                    ..Default::default()
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let inputs = levels
+            .iter()
+            .map(|level| {
+                device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: Some("Bloom Level Bind Group"),
+                    layout: input_layout,
+                    entries: &[
+                        wgpu::BindGroupEntry {
+                            binding: 0,
+                            resource: wgpu::BindingResource::TextureView(level),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 1,
+                            resource: wgpu::BindingResource::Sampler(sampler),
+                        },
+                    ],
+                })
+            })
+            .collect();
+
+        let composite = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Bloom Composite Bind Group"),
+            layout: composite_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&levels[0]),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+            ],
+        });
+
+        Self {
+            levels,
+            inputs,
+            composite,
+        }
+    }
+}
+
+fn parameters_entry() -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding: 0,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }
+}
+
+fn bloom_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    label: &str,
+    shader: &str,
+    defines: wgpu::naga::FastHashMap<String, String>,
+    blend: Option<wgpu::BlendState>,
+) -> wgpu::RenderPipeline {
+    let vertex_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("Fullscreen Shader"),
+        source: wgpu::ShaderSource::Glsl {
+            shader: include_str!("resources/shaders/fullscreen.vert").into(),
+            stage: wgpu::naga::ShaderStage::Vertex,
+            defines: HashMap::with_hasher(BuildHasherDefault::default()),
+        },
+    });
+
+    let fragment_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some(label),
+        source: wgpu::ShaderSource::Glsl {
+            shader: shader.into(),
+            stage: wgpu::naga::ShaderStage::Fragment,
+            defines,
+        },
+    });
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some(label),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: &vertex_shader,
+            entry_point: "main",
+            buffers: &[],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &fragment_shader,
+            entry_point: "main",
+            targets: &[Some(wgpu::ColorTargetState {
+                format: Texture::HDR_FORMAT,
+                blend,
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+    })
+}
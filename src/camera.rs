@@ -1,3 +1,4 @@
+#[derive(Clone, Copy, Debug)]
 pub struct Camera {
     pub eye: glam::Vec3,
     pub target: glam::Vec3,
@@ -9,16 +10,41 @@ pub struct Camera {
 }
 
 impl Camera {
-    pub fn build_view_projection(&self) -> glam::Mat4 {
-        let view = glam::Mat4::look_at_rh(self.eye, self.target, self.up);
-        let proj = glam::Mat4::perspective_rh(
+    pub fn build_view(&self) -> glam::Mat4 {
+        glam::Mat4::look_at_rh(self.eye, self.target, self.up)
+    }
+
+    pub fn build_projection(&self) -> glam::Mat4 {
+        glam::Mat4::perspective_rh(
             f32::to_radians(self.fov_y),
             self.aspect,
             self.z_near,
             self.z_far,
-        );
+        )
+    }
+
+    pub fn build_view_projection(&self) -> glam::Mat4 {
+        self.build_projection() * self.build_view()
+    }
+
+    /// The planes bounding what the camera sees, for culling.
+    pub fn frustum(&self) -> crate::culling::Frustum {
+        crate::culling::Frustum::from_view_projection(self.build_view_projection())
+    }
+
+    pub fn to_raw(&self) -> CameraData {
+        CameraData {
+            view_projection: self.build_view_projection(),
+            position: self.eye.extend(1.0),
+        }
+    }
 
-        proj * view
+    /// View-projection without the translation, so distant backgrounds follow the camera's
+    /// rotation only.
+    pub fn build_rotation_projection(&self) -> glam::Mat4 {
+        let rotation = glam::Mat4::from_mat3(glam::Mat3::from_mat4(self.build_view()));
+
+        self.build_projection() * rotation
     }
 
     pub fn input_move_camera(&mut self, event: &winit::event::WindowEvent, speed: f32) -> bool {
@@ -35,14 +61,14 @@ impl Camera {
                 winit::keyboard::PhysicalKey::Code(winit::keyboard::KeyCode::KeyW) => {
                     self.eye += self.forward().normalize_or_zero() * speed;
 
-                    std::println!("W: {}", self.eye);
+                    log::debug!("W: {}", self.eye);
 
                     true
                 }
                 winit::keyboard::PhysicalKey::Code(winit::keyboard::KeyCode::KeyS) => {
                     self.eye -= self.forward().normalize_or_zero() * speed;
 
-                    std::println!("S: {}", self.eye);
+                    log::debug!("S: {}", self.eye);
 
                     true
                 }
@@ -51,7 +77,7 @@ impl Camera {
                         - (self.forward() + self.right() * speed).normalize_or_zero()
                             * self.forward().length();
 
-                    std::println!("A: {}", self.eye);
+                    log::debug!("A: {}", self.eye);
 
                     true
                 }
@@ -60,7 +86,7 @@ impl Camera {
                         - (self.forward() - self.right() * speed).normalize_or_zero()
                             * self.forward().length();
 
-                    std::println!("D: {}", self.eye);
+                    log::debug!("D: {}", self.eye);
 
                     true
                 }
@@ -78,3 +104,15 @@ impl Camera {
         self.forward().normalize_or_zero().cross(self.up)
     }
 }
+
+/// The camera uniform shared by the vertex and fragment shaders.
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct CameraData {
+    pub view_projection: glam::Mat4,
+    /// World space eye position, for view dependent shading.
+    pub position: glam::Vec4,
+}
+
+unsafe impl bytemuck::Pod for CameraData {}
+unsafe impl bytemuck::Zeroable for CameraData {}
@@ -0,0 +1,243 @@
+use std::{collections::HashMap, hash::BuildHasherDefault};
+
+use crate::{camera::Camera, include_shader_snippets};
+
+/// Invocations per workgroup of cluster.comp.
+const WORKGROUP_SIZE: u32 = 64;
+
+/// How lights are binned for clustered (Forward+) lighting, see
+/// [`crate::Context::set_clustered_lighting`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ClusterSettings {
+    /// Clusters across and down the screen, and depth slices between the camera's near and
+    /// far planes.
+    pub grid: glam::UVec3,
+    /// Lights kept per cluster, any more reaching it are dropped.
+    pub max_lights: u32,
+    /// Light, in linear RGB, below which a point or spot light no longer counts as reaching
+    /// a cluster. Lower values bin lights into more clusters but hide their cut-off better.
+    pub threshold: f32,
+}
+
+impl Default for ClusterSettings {
+    fn default() -> Self {
+        Self {
+            grid: glam::uvec3(16, 9, 24),
+            max_lights: 128,
+            threshold: 1.0 / 256.0,
+        }
+    }
+}
+
+impl ClusterSettings {
+    fn cluster_count(&self) -> u32 {
+        self.grid.x * self.grid.y * self.grid.z
+    }
+}
+
+/// The froxel grid as the binning compute shader and the fragment shaders see it.
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct ClusterData {
+    pub inverse_projection: glam::Mat4,
+    pub view: glam::Mat4,
+    /// `w` holds the most lights per cluster.
+    pub grid: [u32; 4],
+    /// Surface size and tile size, in pixels.
+    pub screen: glam::Vec4,
+    /// Near and far plane, then the scale and bias from log view depth to slice.
+    pub depth: glam::Vec4,
+    pub light_count: u32,
+    pub threshold: f32,
+    pub _padding: [u32; 2],
+}
+
+unsafe impl bytemuck::Pod for ClusterData {}
+unsafe impl bytemuck::Zeroable for ClusterData {}
+
+impl ClusterData {
+    /// Bound while clustering is off, the shaders never read it then.
+    pub fn disabled() -> Self {
+        bytemuck::Zeroable::zeroed()
+    }
+}
+
+/// Bins lights into a froxel grid on the GPU before the main pass, so each fragment only
+/// walks the lights that can reach it.
+pub(crate) struct LightClusters {
+    settings: ClusterSettings,
+    pipeline: wgpu::ComputePipeline,
+    /// Lights binned into each cluster.
+    counts: wgpu::Buffer,
+    /// `max_lights` slots of light indices per cluster.
+    indices: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+}
+
+impl LightClusters {
+    pub fn new(
+        device: &wgpu::Device,
+        settings: ClusterSettings,
+        cluster_buffer: &wgpu::Buffer,
+        light_buffer: &wgpu::Buffer,
+    ) -> Self {
+        let settings = ClusterSettings {
+            grid: settings.grid.max(glam::UVec3::ONE),
+            max_lights: settings.max_lights.max(1),
+            ..settings
+        };
+
+        let counts = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Cluster Counts"),
+            size: settings.cluster_count() as u64 * 4,
+            usage: wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+        let indices = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Cluster Indices"),
+            size: settings.cluster_count() as u64 * settings.max_lights as u64 * 4,
+            usage: wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Cluster Shader"),
+            source: wgpu::ShaderSource::Glsl {
+                shader: include_shader_snippets(include_str!("resources/shaders/cluster.comp"))
+                    .into(),
+                stage: wgpu::naga::ShaderStage::Compute,
+                defines: HashMap::with_hasher(BuildHasherDefault::default()),
+            },
+        });
+
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Cluster Pipeline"),
+            layout: None,
+            module: &shader,
+            entry_point: "main",
+        });
+
+        let bind_group = Self::bind_group(
+            device,
+            &pipeline,
+            cluster_buffer,
+            light_buffer,
+            &counts,
+            &indices,
+        );
+
+        Self {
+            settings,
+            pipeline,
+            counts,
+            indices,
+            bind_group,
+        }
+    }
+
+    fn bind_group(
+        device: &wgpu::Device,
+        pipeline: &wgpu::ComputePipeline,
+        cluster_buffer: &wgpu::Buffer,
+        light_buffer: &wgpu::Buffer,
+        counts: &wgpu::Buffer,
+        indices: &wgpu::Buffer,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Cluster Bind Group"),
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: cluster_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: light_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: counts.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: indices.as_entire_binding(),
+                },
+            ],
+        })
+    }
+
+    pub fn settings(&self) -> &ClusterSettings {
+        &self.settings
+    }
+
+    pub fn counts(&self) -> &wgpu::Buffer {
+        &self.counts
+    }
+
+    pub fn indices(&self) -> &wgpu::Buffer {
+        &self.indices
+    }
+
+    /// Rebinds the light buffer after it was replaced by a bigger one.
+    pub fn set_light_buffer(
+        &mut self,
+        device: &wgpu::Device,
+        cluster_buffer: &wgpu::Buffer,
+        light_buffer: &wgpu::Buffer,
+    ) {
+        self.bind_group = Self::bind_group(
+            device,
+            &self.pipeline,
+            cluster_buffer,
+            light_buffer,
+            &self.counts,
+            &self.indices,
+        );
+    }
+
+    /// The grid for `camera` looking at a `width` by `height` surface, to upload into the
+    /// cluster buffer.
+    pub fn update(
+        &self,
+        camera: &Camera,
+        width: u32,
+        height: u32,
+        light_count: usize,
+    ) -> ClusterData {
+        let grid = self.settings.grid;
+        let tile = glam::vec2(
+            width.div_ceil(grid.x) as f32,
+            height.div_ceil(grid.y) as f32,
+        );
+        let log_ratio = (camera.z_far / camera.z_near).ln();
+
+        ClusterData {
+            inverse_projection: camera.build_projection().inverse(),
+            view: camera.build_view(),
+            grid: [grid.x, grid.y, grid.z, self.settings.max_lights],
+            screen: glam::vec4(width as f32, height as f32, tile.x, tile.y),
+            depth: glam::vec4(
+                camera.z_near,
+                camera.z_far,
+                grid.z as f32 / log_ratio,
+                -(grid.z as f32) * camera.z_near.ln() / log_ratio,
+            ),
+            light_count: light_count as u32,
+            threshold: self.settings.threshold,
+            _padding: [0; 2],
+        }
+    }
+
+    /// Records the binning pass, before anything reads the clusters.
+    pub fn dispatch(&self, encoder: &mut wgpu::CommandEncoder) {
+        let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("Cluster Pass"),
+            timestamp_writes: None,
+        });
+
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &self.bind_group, &[]);
+        pass.dispatch_workgroups(self.settings.cluster_count().div_ceil(WORKGROUP_SIZE), 1, 1);
+    }
+}
@@ -0,0 +1,1160 @@
+//! Pre-compressed texture containers (KTX2 and DDS) and a CPU fallback decoder for the BC
+//! formats.
+
+/// Texture data read from a KTX2 or DDS container, ready to be uploaded as is.
+pub struct TextureData {
+    pub format: wgpu::TextureFormat,
+    pub width: u32,
+    pub height: u32,
+    /// Array layers, with every cube face counted as its own layer.
+    pub layers: u32,
+    /// One entry per mip level, largest first, each holding every layer back to back.
+    pub levels: Vec<Vec<u8>>,
+}
+
+impl TextureData {
+    pub fn from_ktx2(bytes: &[u8]) -> anyhow::Result<Self> {
+        let reader =
+            ktx2::Reader::new(bytes).map_err(|e| anyhow::anyhow!("invalid KTX2 file: {e:?}"))?;
+        let header = reader.header();
+
+        if let Some(scheme) = header.supercompression_scheme {
+            anyhow::bail!("KTX2 supercompression {scheme:?} is not supported");
+        }
+
+        anyhow::ensure!(
+            header.pixel_depth <= 1,
+            "3D KTX2 textures are not supported"
+        );
+
+        let format = header
+            .format
+            .and_then(ktx2_format)
+            .ok_or_else(|| anyhow::anyhow!("unsupported KTX2 format {:?}", header.format))?;
+
+        let data = Self {
+            format,
+            width: header.pixel_width,
+            height: header.pixel_height.max(1),
+            layers: header.layer_count.max(1) * header.face_count.max(1),
+            levels: reader.levels().map(|level| level.data.to_vec()).collect(),
+        };
+
+        data.validate()?;
+
+        Ok(data)
+    }
+
+    pub fn from_dds(bytes: &[u8]) -> anyhow::Result<Self> {
+        let dds = ddsfile::Dds::read(bytes)?;
+
+        let format = match (dds.get_dxgi_format(), dds.get_d3d_format()) {
+            (Some(format), _) => dxgi_format(format),
+            (None, Some(format)) => d3d_format(format),
+            (None, None) => None,
+        }
+        .ok_or_else(|| anyhow::anyhow!("unsupported DDS format"))?;
+
+        anyhow::ensure!(dds.get_depth() <= 1, "3D DDS textures are not supported");
+
+        let mut data = Self {
+            format,
+            width: dds.get_width(),
+            height: dds.get_height(),
+            layers: 1,
+            levels: Vec::new(),
+        };
+
+        // DDS stores each layer with its whole mip chain, KTX2 (and wgpu) want levels first
+        let level_sizes = (0..dds.get_num_mipmap_levels().max(1))
+            .map(|level| data.level_byte_size(level) as usize)
+            .collect::<Vec<_>>();
+        let layer_size = level_sizes.iter().sum::<usize>();
+
+        anyhow::ensure!(
+            layer_size > 0 && dds.data.len() % layer_size == 0,
+            "DDS data does not match its header"
+        );
+
+        data.layers = (dds.data.len() / layer_size) as u32;
+        data.levels = level_sizes.iter().map(|_| Vec::new()).collect();
+
+        for layer in dds.data.chunks_exact(layer_size) {
+            let mut offset = 0;
+
+            for (level, size) in data.levels.iter_mut().zip(&level_sizes) {
+                level.extend_from_slice(&layer[offset..offset + size]);
+                offset += size;
+            }
+        }
+
+        Ok(data)
+    }
+
+    /// Size of a mip level in texels, not rounded up to whole blocks.
+    pub fn level_size(&self, level: u32) -> wgpu::Extent3d {
+        wgpu::Extent3d {
+            width: (self.width >> level).max(1),
+            height: (self.height >> level).max(1),
+            depth_or_array_layers: self.layers,
+        }
+    }
+
+    /// Bytes per row of blocks and rows of blocks per layer of a mip level.
+    pub fn level_layout(&self, level: u32) -> (u32, u32) {
+        let (block_width, block_height) = self.format.block_dimensions();
+        let block_size = self.format.block_copy_size(None).unwrap_or(4);
+        let size = self.level_size(level);
+
+        (
+            size.width.div_ceil(block_width) * block_size,
+            size.height.div_ceil(block_height),
+        )
+    }
+
+    fn level_byte_size(&self, level: u32) -> u32 {
+        let (bytes_per_row, rows) = self.level_layout(level);
+        bytes_per_row * rows * self.layers
+    }
+
+    fn validate(&self) -> anyhow::Result<()> {
+        anyhow::ensure!(!self.levels.is_empty(), "texture has no mip levels");
+
+        for (level, data) in self.levels.iter().enumerate() {
+            let expected = self.level_byte_size(level as u32) as usize;
+
+            anyhow::ensure!(
+                data.len() == expected,
+                "mip level {level} has {} bytes, expected {expected}",
+                data.len()
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Decodes BC data to an uncompressed format, keeping the stored mip chain and layers.
+    ///
+    /// Used when the device lacks `Features::TEXTURE_COMPRESSION_BC`. BC1-BC3 and BC7 decode to
+    /// RGBA8, BC6H to RGBA16 float. Single and two channel formats decode to red and red/green
+    /// like they sample on the GPU, the signed variants to RGBA8 SNORM.
+    pub fn decompress(&self) -> anyhow::Result<Self> {
+        use wgpu::TextureFormat as F;
+
+        let rgba8 = if self.format.is_srgb() {
+            F::Rgba8UnormSrgb
+        } else {
+            F::Rgba8Unorm
+        };
+
+        let (format, levels) = match self.format {
+            F::Bc1RgbaUnorm | F::Bc1RgbaUnormSrgb => (rgba8, self.decode_levels(decode_bc1, 8)),
+            F::Bc2RgbaUnorm | F::Bc2RgbaUnormSrgb => (rgba8, self.decode_levels(decode_bc2, 16)),
+            F::Bc3RgbaUnorm | F::Bc3RgbaUnormSrgb => (rgba8, self.decode_levels(decode_bc3, 16)),
+            F::Bc4RUnorm => (rgba8, self.decode_levels(decode_bc4, 8)),
+            F::Bc4RSnorm => (F::Rgba8Snorm, self.decode_levels(decode_bc4_snorm, 8)),
+            F::Bc5RgUnorm => (rgba8, self.decode_levels(decode_bc5, 16)),
+            F::Bc5RgSnorm => (F::Rgba8Snorm, self.decode_levels(decode_bc5_snorm, 16)),
+            F::Bc6hRgbUfloat => (F::Rgba16Float, self.decode_levels(decode_bc6h_ufloat, 16)),
+            F::Bc6hRgbFloat => (F::Rgba16Float, self.decode_levels(decode_bc6h_sfloat, 16)),
+            F::Bc7RgbaUnorm | F::Bc7RgbaUnormSrgb => (rgba8, self.decode_levels(decode_bc7, 16)),
+            format => anyhow::bail!("no CPU decoder for {format:?}"),
+        };
+
+        Ok(Self {
+            format,
+            width: self.width,
+            height: self.height,
+            layers: self.layers,
+            levels,
+        })
+    }
+
+    /// Runs `decode_block` over every `block_bytes` sized block of every level and layer,
+    /// giving `N` bytes per texel.
+    fn decode_levels<const N: usize>(
+        &self,
+        decode_block: BlockDecoder<N>,
+        block_bytes: usize,
+    ) -> Vec<Vec<u8>> {
+        self.levels
+            .iter()
+            .enumerate()
+            .map(|(level, data)| {
+                let size = self.level_size(level as u32);
+                let (width, height) = (size.width as usize, size.height as usize);
+                let blocks_wide = width.div_ceil(4);
+                let layer_bytes = data.len() / self.layers as usize;
+                let mut pixels = vec![0; width * height * N * self.layers as usize];
+
+                for (blocks, out) in data
+                    .chunks_exact(layer_bytes)
+                    .zip(pixels.chunks_exact_mut(width * height * N))
+                {
+                    for (index, block) in blocks.chunks_exact(block_bytes).enumerate() {
+                        let (block_x, block_y) = (index % blocks_wide * 4, index / blocks_wide * 4);
+
+                        for (texel, bytes) in decode_block(block).iter().enumerate() {
+                            let (x, y) = (block_x + texel % 4, block_y + texel / 4);
+
+                            // Blocks overhang levels that are not a multiple of 4 in size
+                            if x < width && y < height {
+                                let offset = (y * width + x) * N;
+                                out[offset..offset + N].copy_from_slice(bytes);
+                            }
+                        }
+                    }
+                }
+
+                pixels
+            })
+            .collect()
+    }
+}
+
+fn ktx2_format(format: ktx2::Format) -> Option<wgpu::TextureFormat> {
+    use ktx2::Format as K;
+    use wgpu::{AstcBlock as B, AstcChannel as C, TextureFormat as F};
+
+    let astc = |block, channel| Some(F::Astc { block, channel });
+
+    match format {
+        K::R8G8B8A8_UNORM => Some(F::Rgba8Unorm),
+        K::R8G8B8A8_SRGB => Some(F::Rgba8UnormSrgb),
+        K::B8G8R8A8_UNORM => Some(F::Bgra8Unorm),
+        K::B8G8R8A8_SRGB => Some(F::Bgra8UnormSrgb),
+        K::R16G16B16A16_SFLOAT => Some(F::Rgba16Float),
+        K::R32G32B32A32_SFLOAT => Some(F::Rgba32Float),
+        // BC1 has no opaque-only variant in wgpu, the punch-through alpha decodes the same
+        K::BC1_RGB_UNORM_BLOCK | K::BC1_RGBA_UNORM_BLOCK => Some(F::Bc1RgbaUnorm),
+        K::BC1_RGB_SRGB_BLOCK | K::BC1_RGBA_SRGB_BLOCK => Some(F::Bc1RgbaUnormSrgb),
+        K::BC2_UNORM_BLOCK => Some(F::Bc2RgbaUnorm),
+        K::BC2_SRGB_BLOCK => Some(F::Bc2RgbaUnormSrgb),
+        K::BC3_UNORM_BLOCK => Some(F::Bc3RgbaUnorm),
+        K::BC3_SRGB_BLOCK => Some(F::Bc3RgbaUnormSrgb),
+        K::BC4_UNORM_BLOCK => Some(F::Bc4RUnorm),
+        K::BC4_SNORM_BLOCK => Some(F::Bc4RSnorm),
+        K::BC5_UNORM_BLOCK => Some(F::Bc5RgUnorm),
+        K::BC5_SNORM_BLOCK => Some(F::Bc5RgSnorm),
+        K::BC6H_UFLOAT_BLOCK => Some(F::Bc6hRgbUfloat),
+        K::BC6H_SFLOAT_BLOCK => Some(F::Bc6hRgbFloat),
+        K::BC7_UNORM_BLOCK => Some(F::Bc7RgbaUnorm),
+        K::BC7_SRGB_BLOCK => Some(F::Bc7RgbaUnormSrgb),
+        K::ETC2_R8G8B8_UNORM_BLOCK => Some(F::Etc2Rgb8Unorm),
+        K::ETC2_R8G8B8_SRGB_BLOCK => Some(F::Etc2Rgb8UnormSrgb),
+        K::ETC2_R8G8B8A1_UNORM_BLOCK => Some(F::Etc2Rgb8A1Unorm),
+        K::ETC2_R8G8B8A1_SRGB_BLOCK => Some(F::Etc2Rgb8A1UnormSrgb),
+        K::ETC2_R8G8B8A8_UNORM_BLOCK => Some(F::Etc2Rgba8Unorm),
+        K::ETC2_R8G8B8A8_SRGB_BLOCK => Some(F::Etc2Rgba8UnormSrgb),
+        K::EAC_R11_UNORM_BLOCK => Some(F::EacR11Unorm),
+        K::EAC_R11_SNORM_BLOCK => Some(F::EacR11Snorm),
+        K::EAC_R11G11_UNORM_BLOCK => Some(F::EacRg11Unorm),
+        K::EAC_R11G11_SNORM_BLOCK => Some(F::EacRg11Snorm),
+        K::ASTC_4x4_UNORM_BLOCK => astc(B::B4x4, C::Unorm),
+        K::ASTC_4x4_SRGB_BLOCK => astc(B::B4x4, C::UnormSrgb),
+        K::ASTC_5x4_UNORM_BLOCK => astc(B::B5x4, C::Unorm),
+        K::ASTC_5x4_SRGB_BLOCK => astc(B::B5x4, C::UnormSrgb),
+        K::ASTC_5x5_UNORM_BLOCK => astc(B::B5x5, C::Unorm),
+        K::ASTC_5x5_SRGB_BLOCK => astc(B::B5x5, C::UnormSrgb),
+        K::ASTC_6x5_UNORM_BLOCK => astc(B::B6x5, C::Unorm),
+        K::ASTC_6x5_SRGB_BLOCK => astc(B::B6x5, C::UnormSrgb),
+        K::ASTC_6x6_UNORM_BLOCK => astc(B::B6x6, C::Unorm),
+        K::ASTC_6x6_SRGB_BLOCK => astc(B::B6x6, C::UnormSrgb),
+        K::ASTC_8x5_UNORM_BLOCK => astc(B::B8x5, C::Unorm),
+        K::ASTC_8x5_SRGB_BLOCK => astc(B::B8x5, C::UnormSrgb),
+        K::ASTC_8x6_UNORM_BLOCK => astc(B::B8x6, C::Unorm),
+        K::ASTC_8x6_SRGB_BLOCK => astc(B::B8x6, C::UnormSrgb),
+        K::ASTC_8x8_UNORM_BLOCK => astc(B::B8x8, C::Unorm),
+        K::ASTC_8x8_SRGB_BLOCK => astc(B::B8x8, C::UnormSrgb),
+        K::ASTC_10x5_UNORM_BLOCK => astc(B::B10x5, C::Unorm),
+        K::ASTC_10x5_SRGB_BLOCK => astc(B::B10x5, C::UnormSrgb),
+        K::ASTC_10x6_UNORM_BLOCK => astc(B::B10x6, C::Unorm),
+        K::ASTC_10x6_SRGB_BLOCK => astc(B::B10x6, C::UnormSrgb),
+        K::ASTC_10x8_UNORM_BLOCK => astc(B::B10x8, C::Unorm),
+        K::ASTC_10x8_SRGB_BLOCK => astc(B::B10x8, C::UnormSrgb),
+        K::ASTC_10x10_UNORM_BLOCK => astc(B::B10x10, C::Unorm),
+        K::ASTC_10x10_SRGB_BLOCK => astc(B::B10x10, C::UnormSrgb),
+        K::ASTC_12x10_UNORM_BLOCK => astc(B::B12x10, C::Unorm),
+        K::ASTC_12x10_SRGB_BLOCK => astc(B::B12x10, C::UnormSrgb),
+        K::ASTC_12x12_UNORM_BLOCK => astc(B::B12x12, C::Unorm),
+        K::ASTC_12x12_SRGB_BLOCK => astc(B::B12x12, C::UnormSrgb),
+        _ => None,
+    }
+}
+
+fn dxgi_format(format: ddsfile::DxgiFormat) -> Option<wgpu::TextureFormat> {
+    use ddsfile::DxgiFormat as D;
+    use wgpu::TextureFormat as F;
+
+    match format {
+        D::R8G8B8A8_UNorm => Some(F::Rgba8Unorm),
+        D::R8G8B8A8_UNorm_sRGB => Some(F::Rgba8UnormSrgb),
+        D::B8G8R8A8_UNorm => Some(F::Bgra8Unorm),
+        D::B8G8R8A8_UNorm_sRGB => Some(F::Bgra8UnormSrgb),
+        D::R16G16B16A16_Float => Some(F::Rgba16Float),
+        D::R32G32B32A32_Float => Some(F::Rgba32Float),
+        D::BC1_UNorm => Some(F::Bc1RgbaUnorm),
+        D::BC1_UNorm_sRGB => Some(F::Bc1RgbaUnormSrgb),
+        D::BC2_UNorm => Some(F::Bc2RgbaUnorm),
+        D::BC2_UNorm_sRGB => Some(F::Bc2RgbaUnormSrgb),
+        D::BC3_UNorm => Some(F::Bc3RgbaUnorm),
+        D::BC3_UNorm_sRGB => Some(F::Bc3RgbaUnormSrgb),
+        D::BC4_UNorm => Some(F::Bc4RUnorm),
+        D::BC4_SNorm => Some(F::Bc4RSnorm),
+        D::BC5_UNorm => Some(F::Bc5RgUnorm),
+        D::BC5_SNorm => Some(F::Bc5RgSnorm),
+        D::BC6H_UF16 => Some(F::Bc6hRgbUfloat),
+        D::BC6H_SF16 => Some(F::Bc6hRgbFloat),
+        D::BC7_UNorm => Some(F::Bc7RgbaUnorm),
+        D::BC7_UNorm_sRGB => Some(F::Bc7RgbaUnormSrgb),
+        _ => None,
+    }
+}
+
+fn d3d_format(format: ddsfile::D3DFormat) -> Option<wgpu::TextureFormat> {
+    use ddsfile::D3DFormat as D;
+    use wgpu::TextureFormat as F;
+
+    match format {
+        // D3D names list channels from the most significant bit down
+        D::A8B8G8R8 => Some(F::Rgba8Unorm),
+        D::A8R8G8B8 => Some(F::Bgra8Unorm),
+        D::A16B16G16R16F => Some(F::Rgba16Float),
+        D::A32B32G32R32F => Some(F::Rgba32Float),
+        D::DXT1 => Some(F::Bc1RgbaUnorm),
+        D::DXT3 => Some(F::Bc2RgbaUnorm),
+        D::DXT5 => Some(F::Bc3RgbaUnorm),
+        _ => None,
+    }
+}
+
+/// Decodes one 4x4 block into texels of `N` bytes in row order.
+type BlockDecoder<const N: usize> = fn(&[u8]) -> [[u8; N]; 16];
+
+fn expand_565(colour: u16) -> [u8; 3] {
+    let r = (colour >> 11) as u8 & 0x1f;
+    let g = (colour >> 5) as u8 & 0x3f;
+    let b = colour as u8 & 0x1f;
+
+    [
+        (r << 3) | (r >> 2),
+        (g << 2) | (g >> 4),
+        (b << 3) | (b >> 2),
+    ]
+}
+
+/// Decodes the 8 byte colour part shared by BC1-BC3. Only BC1 has the 3 colour + transparent
+/// mode, BC2 and BC3 always interpolate four colours.
+fn decode_colour_block(block: &[u8], allow_punch_through: bool) -> [[u8; 4]; 16] {
+    let c0 = u16::from_le_bytes([block[0], block[1]]);
+    let c1 = u16::from_le_bytes([block[2], block[3]]);
+    let indices = u32::from_le_bytes([block[4], block[5], block[6], block[7]]);
+
+    let [e0, e1] = [expand_565(c0), expand_565(c1)];
+    let mix = |a: u32, b: u32, div: u32| -> [u8; 4] {
+        let channel = |i: usize| ((e0[i] as u32 * a + e1[i] as u32 * b) / div) as u8;
+        [channel(0), channel(1), channel(2), 255]
+    };
+
+    let palette = if c0 > c1 || !allow_punch_through {
+        [mix(1, 0, 1), mix(0, 1, 1), mix(2, 1, 3), mix(1, 2, 3)]
+    } else {
+        [mix(1, 0, 1), mix(0, 1, 1), mix(1, 1, 2), [0, 0, 0, 0]]
+    };
+
+    std::array::from_fn(|texel| palette[(indices >> (texel * 2)) as usize & 3])
+}
+
+/// Decodes the 8 byte interpolated channel block of BC3 alpha, BC4 and BC5.
+fn decode_channel_block(block: &[u8]) -> [u8; 16] {
+    let (a0, a1) = (block[0] as u32, block[1] as u32);
+    let indices = block[2..8]
+        .iter()
+        .rev()
+        .fold(0u64, |bits, &byte| (bits << 8) | byte as u64);
+
+    let palette: [u8; 8] = if a0 > a1 {
+        std::array::from_fn(|i| match i {
+            0 => a0 as u8,
+            1 => a1 as u8,
+            i => ((a0 * (8 - i as u32) + a1 * (i as u32 - 1)) / 7) as u8,
+        })
+    } else {
+        std::array::from_fn(|i| match i {
+            0 => a0 as u8,
+            1 => a1 as u8,
+            6 => 0,
+            7 => 255,
+            i => ((a0 * (6 - i as u32) + a1 * (i as u32 - 1)) / 5) as u8,
+        })
+    };
+
+    std::array::from_fn(|texel| palette[(indices >> (texel * 3)) as usize & 7])
+}
+
+/// Decodes the 8 byte signed channel block of BC4 and BC5 SNORM.
+fn decode_channel_block_snorm(block: &[u8]) -> [i8; 16] {
+    let (a0, a1) = (block[0] as i8 as i32, block[1] as i8 as i32);
+    let indices = block[2..8]
+        .iter()
+        .rev()
+        .fold(0u64, |bits, &byte| (bits << 8) | byte as u64);
+
+    let palette: [i8; 8] = if a0 > a1 {
+        std::array::from_fn(|i| match i {
+            0 => a0 as i8,
+            1 => a1 as i8,
+            i => ((a0 * (8 - i as i32) + a1 * (i as i32 - 1)) / 7) as i8,
+        })
+    } else {
+        std::array::from_fn(|i| match i {
+            0 => a0 as i8,
+            1 => a1 as i8,
+            6 => -127,
+            7 => 127,
+            i => ((a0 * (6 - i as i32) + a1 * (i as i32 - 1)) / 5) as i8,
+        })
+    };
+
+    std::array::from_fn(|texel| palette[(indices >> (texel * 3)) as usize & 7])
+}
+
+fn decode_bc1(block: &[u8]) -> [[u8; 4]; 16] {
+    decode_colour_block(block, true)
+}
+
+fn decode_bc2(block: &[u8]) -> [[u8; 4]; 16] {
+    let mut texels = decode_colour_block(&block[8..], false);
+
+    for (texel, rgba) in texels.iter_mut().enumerate() {
+        let alpha = (block[texel / 2] >> (texel % 2 * 4)) & 0xf;
+        rgba[3] = alpha * 17;
+    }
+
+    texels
+}
+
+fn decode_bc3(block: &[u8]) -> [[u8; 4]; 16] {
+    let mut texels = decode_colour_block(&block[8..], false);
+
+    for (rgba, alpha) in texels.iter_mut().zip(decode_channel_block(&block[..8])) {
+        rgba[3] = alpha;
+    }
+
+    texels
+}
+
+fn decode_bc4(block: &[u8]) -> [[u8; 4]; 16] {
+    decode_channel_block(block).map(|red| [red, 0, 0, 255])
+}
+
+fn decode_bc5(block: &[u8]) -> [[u8; 4]; 16] {
+    let red = decode_channel_block(&block[..8]);
+    let green = decode_channel_block(&block[8..]);
+
+    std::array::from_fn(|texel| [red[texel], green[texel], 0, 255])
+}
+
+fn decode_bc4_snorm(block: &[u8]) -> [[u8; 4]; 16] {
+    decode_channel_block_snorm(block).map(|red| [red as u8, 0, 0, 127])
+}
+
+fn decode_bc5_snorm(block: &[u8]) -> [[u8; 4]; 16] {
+    let red = decode_channel_block_snorm(&block[..8]);
+    let green = decode_channel_block_snorm(&block[8..]);
+
+    std::array::from_fn(|texel| [red[texel] as u8, green[texel] as u8, 0, 127])
+}
+
+/// Reads the fields of a 16 byte BC6H or BC7 block, least significant bit first.
+struct BitReader(u128);
+
+impl BitReader {
+    fn new(block: &[u8]) -> Self {
+        Self(u128::from_le_bytes(block.try_into().unwrap()))
+    }
+
+    fn read(&mut self, count: u32) -> u32 {
+        let value = (self.0 & ((1 << count) - 1)) as u32;
+        self.0 >>= count;
+        value
+    }
+
+    /// Reads an index for every texel. The `anchors` store theirs with one bit less, the
+    /// implicit top bit being 0.
+    fn read_indices(&mut self, bits: u32, anchors: &[usize]) -> [u32; 16] {
+        std::array::from_fn(|texel| {
+            let bits = if anchors.contains(&texel) {
+                bits - 1
+            } else {
+                bits
+            };
+
+            self.read(bits)
+        })
+    }
+}
+
+/// Interpolation weights out of 64 for 2, 3 and 4 bit indices.
+const WEIGHTS: [&[u32]; 3] = [
+    &[0, 21, 43, 64],
+    &[0, 9, 18, 27, 37, 46, 55, 64],
+    &[0, 4, 9, 13, 17, 21, 26, 30, 34, 38, 43, 47, 51, 55, 60, 64],
+];
+
+fn interpolate(e0: i32, e1: i32, index_bits: u32, index: u32) -> i32 {
+    let weight = WEIGHTS[index_bits as usize - 2][index as usize] as i32;
+    (e0 * (64 - weight) + e1 * weight + 32) >> 6
+}
+
+/// Subset of every texel in the two subset partitions, one bit per texel in row order.
+const PARTITIONS_2: [u16; 64] = [
+    0xCCCC, 0x8888, 0xEEEE, 0xECC8, 0xC880, 0xFEEC, 0xFEC8, 0xEC80, 0xC800, 0xFFEC, 0xFE80, 0xE800,
+    0xFFE8, 0xFF00, 0xFFF0, 0xF000, 0xF710, 0x008E, 0x7100, 0x08CE, 0x008C, 0x7310, 0x3100, 0x8CCE,
+    0x088C, 0x3110, 0x6666, 0x366C, 0x17E8, 0x0FF0, 0x718E, 0x399C, 0xAAAA, 0xF0F0, 0x5A5A, 0x33CC,
+    0x3C3C, 0x55AA, 0x9696, 0xA55A, 0x73CE, 0x13C8, 0x324C, 0x3BDC, 0x6996, 0xC33C, 0x9966, 0x0660,
+    0x0272, 0x04E4, 0x4E40, 0x2720, 0xC936, 0x936C, 0x39C6, 0x639C, 0x9336, 0x9CC6, 0x817E, 0xE718,
+    0xCCF0, 0x0FCC, 0x7744, 0xEE22,
+];
+
+/// Subset of every texel in the three subset partitions, two bits per texel in row order.
+const PARTITIONS_3: [u32; 64] = [
+    0xAA685050, 0x6A5A5040, 0x5A5A4200, 0x5450A0A8, 0xA5A50000, 0xA0A05050, 0x5555A0A0, 0x5A5A5050,
+    0xAA550000, 0xAA555500, 0xAAAA5500, 0x90909090, 0x94949494, 0xA4A4A4A4, 0xA9A59450, 0x2A0A4250,
+    0xA5945040, 0x0A425054, 0xA5A5A500, 0x55A0A0A0, 0xA8A85454, 0x6A6A4040, 0xA4A45000, 0x1A1A0500,
+    0x0050A4A4, 0xAAA59090, 0x14696914, 0x69691400, 0xA08585A0, 0xAA821414, 0x50A4A450, 0x6A5A0200,
+    0xA9A58000, 0x5090A0A8, 0xA8A09050, 0x24242424, 0x00AA5500, 0x24924924, 0x24499224, 0x50A50A50,
+    0x500AA550, 0xAAAA4444, 0x66660000, 0xA5A0A5A0, 0x50A050A0, 0x69286928, 0x44AAAA44, 0x66666600,
+    0xAA444444, 0x54A854A8, 0x95809580, 0x96969600, 0xA85454A8, 0x80959580, 0xAA141414, 0x96960000,
+    0xAAAA1414, 0xA05050A0, 0xA0A5A5A0, 0x96000000, 0x40804080, 0xA9A8A9A8, 0xAAAAAA44, 0x2A4A5254,
+];
+
+/// Texel holding the implicit index bit of the second subset, in two subset partitions.
+const ANCHORS_2: [u8; 64] = [
+    15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 2, 8, 2, 2, 8, 8, 15, 2, 8,
+    2, 2, 8, 8, 2, 2, 15, 15, 6, 8, 2, 8, 15, 15, 2, 8, 2, 2, 2, 15, 15, 6, 6, 2, 6, 8, 15, 15, 2,
+    2, 15, 15, 15, 15, 15, 2, 2, 15,
+];
+
+/// Texels holding the implicit index bits of the second and third subsets, in three subset
+/// partitions.
+const ANCHORS_3: [[u8; 2]; 64] = [
+    [3, 15],
+    [3, 8],
+    [15, 8],
+    [15, 3],
+    [8, 15],
+    [3, 15],
+    [15, 3],
+    [15, 8],
+    [8, 15],
+    [8, 15],
+    [6, 15],
+    [6, 15],
+    [6, 15],
+    [5, 15],
+    [3, 15],
+    [3, 8],
+    [3, 15],
+    [3, 8],
+    [8, 15],
+    [15, 3],
+    [3, 15],
+    [3, 8],
+    [6, 15],
+    [10, 8],
+    [5, 3],
+    [8, 15],
+    [8, 6],
+    [6, 10],
+    [8, 15],
+    [5, 15],
+    [15, 10],
+    [15, 8],
+    [8, 15],
+    [15, 3],
+    [3, 15],
+    [5, 10],
+    [6, 10],
+    [10, 8],
+    [8, 9],
+    [15, 10],
+    [15, 6],
+    [3, 15],
+    [15, 8],
+    [5, 15],
+    [15, 3],
+    [15, 6],
+    [15, 6],
+    [15, 8],
+    [3, 15],
+    [15, 3],
+    [5, 15],
+    [5, 15],
+    [5, 15],
+    [8, 15],
+    [5, 15],
+    [10, 15],
+    [5, 15],
+    [10, 15],
+    [8, 15],
+    [13, 15],
+    [15, 3],
+    [12, 15],
+    [3, 15],
+    [3, 8],
+];
+
+fn subset(subsets: usize, partition: usize, texel: usize) -> usize {
+    match subsets {
+        1 => 0,
+        2 => (PARTITIONS_2[partition] >> texel) as usize & 1,
+        _ => (PARTITIONS_3[partition] >> (texel * 2)) as usize & 3,
+    }
+}
+
+/// The texels whose index has an implicit top bit, one per subset.
+fn anchors(subsets: usize, partition: usize) -> Vec<usize> {
+    match subsets {
+        1 => vec![0],
+        2 => vec![0, ANCHORS_2[partition] as usize],
+        _ => {
+            let [second, third] = ANCHORS_3[partition];
+            vec![0, second as usize, third as usize]
+        }
+    }
+}
+
+struct Bc7Mode {
+    subsets: usize,
+    partition_bits: u32,
+    rotation_bits: u32,
+    index_selection_bits: u32,
+    colour_bits: u32,
+    alpha_bits: u32,
+    /// One P bit per endpoint.
+    endpoint_p_bits: bool,
+    /// One P bit per subset, shared by both of its endpoints.
+    shared_p_bits: bool,
+    index_bits: u32,
+    /// Bits of the second index set, 0 without one.
+    secondary_index_bits: u32,
+}
+
+const BC7_MODES: [Bc7Mode; 8] = [
+    Bc7Mode {
+        subsets: 3,
+        partition_bits: 4,
+        rotation_bits: 0,
+        index_selection_bits: 0,
+        colour_bits: 4,
+        alpha_bits: 0,
+        endpoint_p_bits: true,
+        shared_p_bits: false,
+        index_bits: 3,
+        secondary_index_bits: 0,
+    },
+    Bc7Mode {
+        subsets: 2,
+        partition_bits: 6,
+        rotation_bits: 0,
+        index_selection_bits: 0,
+        colour_bits: 6,
+        alpha_bits: 0,
+        endpoint_p_bits: false,
+        shared_p_bits: true,
+        index_bits: 3,
+        secondary_index_bits: 0,
+    },
+    Bc7Mode {
+        subsets: 3,
+        partition_bits: 6,
+        rotation_bits: 0,
+        index_selection_bits: 0,
+        colour_bits: 5,
+        alpha_bits: 0,
+        endpoint_p_bits: false,
+        shared_p_bits: false,
+        index_bits: 2,
+        secondary_index_bits: 0,
+    },
+    Bc7Mode {
+        subsets: 2,
+        partition_bits: 6,
+        rotation_bits: 0,
+        index_selection_bits: 0,
+        colour_bits: 7,
+        alpha_bits: 0,
+        endpoint_p_bits: true,
+        shared_p_bits: false,
+        index_bits: 2,
+        secondary_index_bits: 0,
+    },
+    Bc7Mode {
+        subsets: 1,
+        partition_bits: 0,
+        rotation_bits: 2,
+        index_selection_bits: 1,
+        colour_bits: 5,
+        alpha_bits: 6,
+        endpoint_p_bits: false,
+        shared_p_bits: false,
+        index_bits: 2,
+        secondary_index_bits: 3,
+    },
+    Bc7Mode {
+        subsets: 1,
+        partition_bits: 0,
+        rotation_bits: 2,
+        index_selection_bits: 0,
+        colour_bits: 7,
+        alpha_bits: 8,
+        endpoint_p_bits: false,
+        shared_p_bits: false,
+        index_bits: 2,
+        secondary_index_bits: 2,
+    },
+    Bc7Mode {
+        subsets: 1,
+        partition_bits: 0,
+        rotation_bits: 0,
+        index_selection_bits: 0,
+        colour_bits: 7,
+        alpha_bits: 7,
+        endpoint_p_bits: true,
+        shared_p_bits: false,
+        index_bits: 4,
+        secondary_index_bits: 0,
+    },
+    Bc7Mode {
+        subsets: 2,
+        partition_bits: 6,
+        rotation_bits: 0,
+        index_selection_bits: 0,
+        colour_bits: 5,
+        alpha_bits: 5,
+        endpoint_p_bits: true,
+        shared_p_bits: false,
+        index_bits: 2,
+        secondary_index_bits: 0,
+    },
+];
+
+fn decode_bc7(block: &[u8]) -> [[u8; 4]; 16] {
+    // The mode is the number of 0 bits before the first 1
+    let Some(mode) = BC7_MODES.get(block[0].trailing_zeros() as usize) else {
+        // Reserved, decodes to transparent black
+        return [[0; 4]; 16];
+    };
+
+    let mut bits = BitReader::new(block);
+    bits.read(block[0].trailing_zeros() + 1);
+
+    let partition = bits.read(mode.partition_bits) as usize;
+    let rotation = bits.read(mode.rotation_bits);
+    let index_selection = bits.read(mode.index_selection_bits);
+
+    let endpoint_count = mode.subsets * 2;
+    let mut channel_bits = [
+        mode.colour_bits,
+        mode.colour_bits,
+        mode.colour_bits,
+        mode.alpha_bits,
+    ];
+    let mut endpoints = [[0u32; 4]; 6];
+
+    for (channel, &bits_per_channel) in channel_bits.iter().enumerate() {
+        for endpoint in &mut endpoints[..endpoint_count] {
+            endpoint[channel] = bits.read(bits_per_channel);
+        }
+    }
+
+    // P bits are a shared lowest bit of every channel of an endpoint
+    if mode.endpoint_p_bits || mode.shared_p_bits {
+        let mut p_bits = [0; 6];
+        if mode.endpoint_p_bits {
+            p_bits[..endpoint_count].fill_with(|| bits.read(1));
+        } else {
+            for subset in 0..mode.subsets {
+                let p = bits.read(1);
+                p_bits[subset * 2..subset * 2 + 2].fill(p);
+            }
+        }
+
+        for (endpoint, p) in endpoints[..endpoint_count].iter_mut().zip(p_bits) {
+            for (value, &bits_per_channel) in endpoint.iter_mut().zip(&channel_bits) {
+                if bits_per_channel > 0 {
+                    *value = (*value << 1) | p;
+                }
+            }
+        }
+
+        for bits_per_channel in channel_bits.iter_mut().filter(|bits| **bits > 0) {
+            *bits_per_channel += 1;
+        }
+    }
+
+    // Expand to 8 bits by repeating the top bits, alpha is opaque without any
+    for endpoint in &mut endpoints[..endpoint_count] {
+        for (value, &bits_per_channel) in endpoint.iter_mut().zip(&channel_bits) {
+            *value = match bits_per_channel {
+                0 => 255,
+                bits_per_channel => {
+                    let shifted = *value << (8 - bits_per_channel);
+                    shifted | (shifted >> bits_per_channel)
+                }
+            };
+        }
+    }
+
+    let primary = bits.read_indices(mode.index_bits, &anchors(mode.subsets, partition));
+    let (mut colour, mut alpha) = ((&primary, mode.index_bits), (&primary, mode.index_bits));
+
+    let secondary;
+    if mode.secondary_index_bits > 0 {
+        secondary = bits.read_indices(mode.secondary_index_bits, &[0]);
+        alpha = (&secondary, mode.secondary_index_bits);
+
+        if index_selection == 1 {
+            std::mem::swap(&mut colour, &mut alpha);
+        }
+    }
+
+    std::array::from_fn(|texel| {
+        let subset = subset(mode.subsets, partition, texel);
+        let [e0, e1] = [endpoints[subset * 2], endpoints[subset * 2 + 1]];
+
+        let mut rgba: [u8; 4] = std::array::from_fn(|channel| {
+            let (indices, index_bits) = if channel < 3 { colour } else { alpha };
+            interpolate(
+                e0[channel] as i32,
+                e1[channel] as i32,
+                index_bits,
+                indices[texel],
+            ) as u8
+        });
+
+        // The alpha channel was swapped with another one to give it the better precision
+        if rotation > 0 {
+            rgba.swap(rotation as usize - 1, 3);
+        }
+
+        rgba
+    })
+}
+
+// Endpoint channels of the BC6H header layouts, `endpoint * 3 + channel`
+const R0: usize = 0;
+const G0: usize = 1;
+const B0: usize = 2;
+const R1: usize = 3;
+const G1: usize = 4;
+const B1: usize = 5;
+const R2: usize = 6;
+const G2: usize = 7;
+const B2: usize = 8;
+const R3: usize = 9;
+const G3: usize = 10;
+const B3: usize = 11;
+
+struct Bc6hMode {
+    two_subsets: bool,
+    /// Other endpoints are stored as deltas from the first one.
+    transformed: bool,
+    endpoint_bits: u32,
+    /// Bits of every other endpoint per channel, deltas when transformed.
+    delta_bits: [u32; 3],
+    /// Where the endpoint bits after the mode go, in stream order. `(channel, high, low)`
+    /// fills bits `low` to `high` in turn, which runs backwards when `high` is below `low`.
+    layout: &'static [(usize, u32, u32)],
+}
+
+/// The 14 modes in the order of the specification, see [`bc6h_mode`].
+#[rustfmt::skip]
+const BC6H_MODES: [Bc6hMode; 14] = [
+    Bc6hMode {
+        two_subsets: true,
+        transformed: true,
+        endpoint_bits: 10,
+        delta_bits: [5, 5, 5],
+        layout: &[
+            (G2, 4, 4), (B2, 4, 4), (B3, 4, 4), (R0, 9, 0), (G0, 9, 0), (B0, 9, 0), (R1, 4, 0),
+            (G3, 4, 4), (G2, 3, 0), (G1, 4, 0), (B3, 0, 0), (G3, 3, 0), (B1, 4, 0), (B3, 1, 1),
+            (B2, 3, 0), (R2, 4, 0), (B3, 2, 2), (R3, 4, 0), (B3, 3, 3),
+        ],
+    },
+    Bc6hMode {
+        two_subsets: true,
+        transformed: true,
+        endpoint_bits: 7,
+        delta_bits: [6, 6, 6],
+        layout: &[
+            (G2, 5, 5), (G3, 4, 4), (G3, 5, 5), (R0, 6, 0), (B3, 0, 0), (B3, 1, 1), (B2, 4, 4),
+            (G0, 6, 0), (B2, 5, 5), (B3, 2, 2), (G2, 4, 4), (B0, 6, 0), (B3, 3, 3), (B3, 5, 5),
+            (B3, 4, 4), (R1, 5, 0), (G2, 3, 0), (G1, 5, 0), (G3, 3, 0), (B1, 5, 0), (B2, 3, 0),
+            (R2, 5, 0), (R3, 5, 0),
+        ],
+    },
+    Bc6hMode {
+        two_subsets: true,
+        transformed: true,
+        endpoint_bits: 11,
+        delta_bits: [5, 4, 4],
+        layout: &[
+            (R0, 9, 0), (G0, 9, 0), (B0, 9, 0), (R1, 4, 0), (R0, 10, 10), (G2, 3, 0), (G1, 3, 0),
+            (G0, 10, 10), (B3, 0, 0), (G3, 3, 0), (B1, 3, 0), (B0, 10, 10), (B3, 1, 1), (B2, 3, 0),
+            (R2, 4, 0), (B3, 2, 2), (R3, 4, 0), (B3, 3, 3),
+        ],
+    },
+    Bc6hMode {
+        two_subsets: true,
+        transformed: true,
+        endpoint_bits: 11,
+        delta_bits: [4, 5, 4],
+        layout: &[
+            (R0, 9, 0), (G0, 9, 0), (B0, 9, 0), (R1, 3, 0), (R0, 10, 10), (G3, 4, 4), (G2, 3, 0),
+            (G1, 4, 0), (G0, 10, 10), (G3, 3, 0), (B1, 3, 0), (B0, 10, 10), (B3, 1, 1), (B2, 3, 0),
+            (R2, 3, 0), (B3, 0, 0), (B3, 2, 2), (R3, 3, 0), (G2, 4, 4), (B3, 3, 3),
+        ],
+    },
+    Bc6hMode {
+        two_subsets: true,
+        transformed: true,
+        endpoint_bits: 11,
+        delta_bits: [4, 4, 5],
+        layout: &[
+            (R0, 9, 0), (G0, 9, 0), (B0, 9, 0), (R1, 3, 0), (R0, 10, 10), (B2, 4, 4), (G2, 3, 0),
+            (G1, 3, 0), (G0, 10, 10), (B3, 0, 0), (G3, 3, 0), (B1, 4, 0), (B0, 10, 10), (B2, 3, 0),
+            (R2, 3, 0), (B3, 1, 1), (B3, 2, 2), (R3, 3, 0), (B3, 4, 4), (B3, 3, 3),
+        ],
+    },
+    Bc6hMode {
+        two_subsets: true,
+        transformed: true,
+        endpoint_bits: 9,
+        delta_bits: [5, 5, 5],
+        layout: &[
+            (R0, 8, 0), (B2, 4, 4), (G0, 8, 0), (G2, 4, 4), (B0, 8, 0), (B3, 4, 4), (R1, 4, 0),
+            (G3, 4, 4), (G2, 3, 0), (G1, 4, 0), (B3, 0, 0), (G3, 3, 0), (B1, 4, 0), (B3, 1, 1),
+            (B2, 3, 0), (R2, 4, 0), (B3, 2, 2), (R3, 4, 0), (B3, 3, 3),
+        ],
+    },
+    Bc6hMode {
+        two_subsets: true,
+        transformed: true,
+        endpoint_bits: 8,
+        delta_bits: [6, 5, 5],
+        layout: &[
+            (R0, 7, 0), (G3, 4, 4), (B2, 4, 4), (G0, 7, 0), (B3, 2, 2), (G2, 4, 4), (B0, 7, 0),
+            (B3, 3, 3), (B3, 4, 4), (R1, 5, 0), (G2, 3, 0), (G1, 4, 0), (B3, 0, 0), (G3, 3, 0),
+            (B1, 4, 0), (B3, 1, 1), (B2, 3, 0), (R2, 5, 0), (R3, 5, 0),
+        ],
+    },
+    Bc6hMode {
+        two_subsets: true,
+        transformed: true,
+        endpoint_bits: 8,
+        delta_bits: [5, 6, 5],
+        layout: &[
+            (R0, 7, 0), (B3, 0, 0), (B2, 4, 4), (G0, 7, 0), (G2, 5, 5), (G2, 4, 4), (B0, 7, 0),
+            (G3, 5, 5), (B3, 4, 4), (R1, 4, 0), (G3, 4, 4), (G2, 3, 0), (G1, 5, 0), (G3, 3, 0),
+            (B1, 4, 0), (B3, 1, 1), (B2, 3, 0), (R2, 4, 0), (B3, 2, 2), (R3, 4, 0), (B3, 3, 3),
+        ],
+    },
+    Bc6hMode {
+        two_subsets: true,
+        transformed: true,
+        endpoint_bits: 8,
+        delta_bits: [5, 5, 6],
+        layout: &[
+            (R0, 7, 0), (B3, 1, 1), (B2, 4, 4), (G0, 7, 0), (B2, 5, 5), (G2, 4, 4), (B0, 7, 0),
+            (B3, 5, 5), (B3, 4, 4), (R1, 4, 0), (G3, 4, 4), (G2, 3, 0), (G1, 4, 0), (B3, 0, 0),
+            (G3, 3, 0), (B1, 5, 0), (B2, 3, 0), (R2, 4, 0), (B3, 2, 2), (R3, 4, 0), (B3, 3, 3),
+        ],
+    },
+    Bc6hMode {
+        two_subsets: true,
+        transformed: false,
+        endpoint_bits: 6,
+        delta_bits: [6, 6, 6],
+        layout: &[
+            (R0, 5, 0), (G3, 4, 4), (B3, 0, 0), (B3, 1, 1), (B2, 4, 4), (G0, 5, 0), (G2, 5, 5),
+            (B2, 5, 5), (B3, 2, 2), (G2, 4, 4), (B0, 5, 0), (G3, 5, 5), (B3, 3, 3), (B3, 5, 5),
+            (B3, 4, 4), (R1, 5, 0), (G2, 3, 0), (G1, 5, 0), (G3, 3, 0), (B1, 5, 0), (B2, 3, 0),
+            (R2, 5, 0), (R3, 5, 0),
+        ],
+    },
+    Bc6hMode {
+        two_subsets: false,
+        transformed: false,
+        endpoint_bits: 10,
+        delta_bits: [10, 10, 10],
+        layout: &[
+            (R0, 9, 0), (G0, 9, 0), (B0, 9, 0), (R1, 9, 0), (G1, 9, 0), (B1, 9, 0),
+        ],
+    },
+    Bc6hMode {
+        two_subsets: false,
+        transformed: true,
+        endpoint_bits: 11,
+        delta_bits: [9, 9, 9],
+        layout: &[
+            (R0, 9, 0), (G0, 9, 0), (B0, 9, 0), (R1, 8, 0), (R0, 10, 10), (G1, 8, 0), (G0, 10, 10),
+            (B1, 8, 0), (B0, 10, 10),
+        ],
+    },
+    Bc6hMode {
+        two_subsets: false,
+        transformed: true,
+        endpoint_bits: 12,
+        delta_bits: [8, 8, 8],
+        layout: &[
+            (R0, 9, 0), (G0, 9, 0), (B0, 9, 0), (R1, 7, 0), (R0, 10, 11), (G1, 7, 0), (G0, 10, 11),
+            (B1, 7, 0), (B0, 10, 11),
+        ],
+    },
+    Bc6hMode {
+        two_subsets: false,
+        transformed: true,
+        endpoint_bits: 16,
+        delta_bits: [4, 4, 4],
+        layout: &[
+            (R0, 9, 0), (G0, 9, 0), (B0, 9, 0), (R1, 3, 0), (R0, 10, 15), (G1, 3, 0), (G0, 10, 15),
+            (B1, 3, 0), (B0, 10, 15),
+        ],
+    },
+];
+
+/// Reads the 2 or 5 mode bits, `None` for the reserved modes.
+fn bc6h_mode(bits: &mut BitReader) -> Option<&'static Bc6hMode> {
+    let mode = match bits.read(2) {
+        mode @ (0 | 1) => mode,
+        low => low | (bits.read(3) << 2),
+    };
+
+    let index = match mode {
+        0 | 1 => mode as usize,
+        // The five bit modes count up in steps of 4
+        0b00010..=0b11110 if mode & 3 == 2 => 2 + (mode >> 2) as usize,
+        0b00011..=0b01111 if mode & 3 == 3 => 10 + (mode >> 2) as usize,
+        _ => return None,
+    };
+
+    Some(&BC6H_MODES[index])
+}
+
+fn sign_extend(value: i32, bits: u32) -> i32 {
+    let shift = 32 - bits;
+    (value << shift) >> shift
+}
+
+/// Scales an endpoint to the full 16 bit range before interpolating.
+fn unquantize_bc6h(value: i32, bits: u32, signed: bool) -> i32 {
+    if !signed {
+        match value {
+            _ if bits >= 15 => value,
+            0 => 0,
+            _ if value == (1 << bits) - 1 => 0xFFFF,
+            _ => ((value << 16) + 0x8000) >> bits,
+        }
+    } else {
+        let magnitude = value.abs();
+        let unquantized = match magnitude {
+            _ if bits >= 16 => magnitude,
+            0 => 0,
+            _ if magnitude >= (1 << (bits - 1)) - 1 => 0x7FFF,
+            _ => ((magnitude << 15) + 0x4000) >> (bits - 1),
+        };
+
+        unquantized * value.signum()
+    }
+}
+
+/// Turns an interpolated 16 bit value into the bits of a half float.
+fn finish_bc6h(value: i32, signed: bool) -> u16 {
+    if !signed {
+        ((value * 31) >> 6) as u16
+    } else if value < 0 {
+        0x8000 | ((-value * 31) >> 5) as u16
+    } else {
+        ((value * 31) >> 5) as u16
+    }
+}
+
+fn decode_bc6h(block: &[u8], signed: bool) -> [[u8; 8]; 16] {
+    const ONE: u16 = 0x3C00;
+
+    let mut bits = BitReader::new(block);
+    let Some(mode) = bc6h_mode(&mut bits) else {
+        // Reserved, decodes to opaque black
+        let black = [0, 0, 0, ONE].map(u16::to_le_bytes).concat();
+        return [black.try_into().unwrap(); 16];
+    };
+
+    let mut endpoints = [[0i32; 3]; 4];
+    for &(field, high, low) in mode.layout {
+        let channel = &mut endpoints[field / 3][field % 3];
+
+        for step in 0..=high.abs_diff(low) {
+            let bit = if high >= low { low + step } else { low - step };
+            *channel |= (bits.read(1) as i32) << bit;
+        }
+    }
+
+    let (subsets, index_bits) = if mode.two_subsets { (2, 3) } else { (1, 4) };
+    let partition = if mode.two_subsets { bits.read(5) } else { 0 } as usize;
+    let endpoint_count = subsets * 2;
+
+    let [first, others @ ..] = &mut endpoints[..endpoint_count] else {
+        unreachable!()
+    };
+    for channel in 0..3 {
+        if signed {
+            first[channel] = sign_extend(first[channel], mode.endpoint_bits);
+        }
+
+        for endpoint in others.iter_mut() {
+            let value = &mut endpoint[channel];
+
+            if mode.transformed {
+                let delta = sign_extend(*value, mode.delta_bits[channel]);
+                *value = (first[channel] + delta) & ((1 << mode.endpoint_bits) - 1);
+            }
+            if signed {
+                *value = sign_extend(*value, mode.endpoint_bits);
+            }
+        }
+    }
+
+    for endpoint in &mut endpoints[..endpoint_count] {
+        for value in endpoint {
+            *value = unquantize_bc6h(*value, mode.endpoint_bits, signed);
+        }
+    }
+
+    let indices = bits.read_indices(index_bits, &anchors(subsets, partition));
+
+    std::array::from_fn(|texel| {
+        let subset = subset(subsets, partition, texel);
+        let [e0, e1] = [endpoints[subset * 2], endpoints[subset * 2 + 1]];
+
+        let [r, g, b] = std::array::from_fn(|channel| {
+            let value = interpolate(e0[channel], e1[channel], index_bits, indices[texel]);
+            finish_bc6h(value, signed)
+        });
+
+        [r, g, b, ONE]
+            .map(u16::to_le_bytes)
+            .concat()
+            .try_into()
+            .unwrap()
+    })
+}
+
+fn decode_bc6h_ufloat(block: &[u8]) -> [[u8; 8]; 16] {
+    decode_bc6h(block, false)
+}
+
+fn decode_bc6h_sfloat(block: &[u8]) -> [[u8; 8]; 16] {
+    decode_bc6h(block, true)
+}
@@ -0,0 +1,135 @@
+use std::{collections::HashMap, hash::BuildHasherDefault};
+
+use wgpu::util::DeviceExt;
+
+/// Number of faces in a cube texture, in `+X, -X, +Y, -Y, +Z, -Z` order.
+pub const FACES: u32 = 6;
+
+/// Renders mip level 0 of every face of `target` from an equirectangular (latitude/longitude)
+/// `source` view, so the cube samples the same colour in every direction.
+///
+/// `target` needs six array layers and `RENDER_ATTACHMENT` usage; `source` a filterable format.
+pub fn from_equirectangular(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    source: &wgpu::TextureView,
+    target: &wgpu::Texture,
+) {
+    let pipeline = equirect_pipeline(device, target.format());
+    let layout = pipeline.get_bind_group_layout(0);
+
+    let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+        label: Some("Equirectangular Sampler"),
+        // Longitude wraps around, latitude stops at the poles
+        address_mode_u: wgpu::AddressMode::Repeat,
+        address_mode_v: wgpu::AddressMode::ClampToEdge,
+        address_mode_w: wgpu::AddressMode::ClampToEdge,
+        mag_filter: wgpu::FilterMode::Linear,
+        min_filter: wgpu::FilterMode::Linear,
+        mipmap_filter: wgpu::FilterMode::Nearest,
+        ..Default::default()
+    });
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("Equirectangular Encoder"),
+    });
+
+    for face in 0..FACES {
+        // Padded to 16 bytes, the smallest uniform buffer every backend accepts
+        let face_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Cube Face Buffer"),
+            contents: bytemuck::cast_slice(&[face, 0, 0, 0]),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Equirectangular Bind Group"),
+            layout: &layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(source),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: face_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        let view = target.create_view(&wgpu::TextureViewDescriptor {
+            label: Some("Cube Face View"),
+            dimension: Some(wgpu::TextureViewDimension::D2),
+            base_mip_level: 0,
+            mip_level_count: Some(1),
+            base_array_layer: face,
+            array_layer_count: Some(1),
+            ..Default::default()
+        });
+
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Equirectangular Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+
+        pass.set_pipeline(&pipeline);
+        pass.set_bind_group(0, &bind_group, &[]);
+        pass.draw(0..3, 0..1);
+    }
+
+    queue.submit(std::iter::once(encoder.finish()));
+}
+
+fn equirect_pipeline(device: &wgpu::Device, format: wgpu::TextureFormat) -> wgpu::RenderPipeline {
+    let vertex_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("Fullscreen Shader"),
+        source: wgpu::ShaderSource::Glsl {
+            shader: include_str!("resources/shaders/fullscreen.vert").into(),
+            stage: wgpu::naga::ShaderStage::Vertex,
+            defines: HashMap::with_hasher(BuildHasherDefault::default()),
+        },
+    });
+
+    let fragment_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("Equirectangular Shader"),
+        source: wgpu::ShaderSource::Glsl {
+            shader: include_str!("resources/shaders/equirect_to_cube.frag").into(),
+            stage: wgpu::naga::ShaderStage::Fragment,
+            defines: HashMap::with_hasher(BuildHasherDefault::default()),
+        },
+    });
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Equirectangular Pipeline"),
+        // Derived from the shaders, the bind group layout is read back from the pipeline
+        layout: None,
+        vertex: wgpu::VertexState {
+            module: &vertex_shader,
+            entry_point: "main",
+            buffers: &[],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &fragment_shader,
+            entry_point: "main",
+            targets: &[Some(format.into())],
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+    })
+}
@@ -0,0 +1,336 @@
+use wgpu::util::DeviceExt;
+
+use crate::{
+    camera::Camera,
+    model::{Mesh, Model},
+};
+
+/// An axis aligned bounding box.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: glam::Vec3,
+    pub max: glam::Vec3,
+}
+
+impl Aabb {
+    /// Contains nothing, and grows to exactly what it is joined with.
+    pub const EMPTY: Self = Self {
+        min: glam::Vec3::INFINITY,
+        max: glam::Vec3::NEG_INFINITY,
+    };
+
+    /// The smallest box around `points`, [`Aabb::EMPTY`] without any.
+    pub fn from_points(points: impl IntoIterator<Item = glam::Vec3>) -> Self {
+        points.into_iter().fold(Self::EMPTY, |aabb, point| Self {
+            min: aabb.min.min(point),
+            max: aabb.max.max(point),
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.min.cmpgt(self.max).any()
+    }
+
+    pub fn union(self, other: Self) -> Self {
+        Self {
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+        }
+    }
+
+    pub fn centre(&self) -> glam::Vec3 {
+        (self.min + self.max) * 0.5
+    }
+
+    pub fn half_extents(&self) -> glam::Vec3 {
+        (self.max - self.min) * 0.5
+    }
+
+    /// The box around this one after `transform`, which is looser than the transformed box
+    /// itself once rotated.
+    pub fn transformed(&self, transform: glam::Mat4) -> Self {
+        if self.is_empty() {
+            return *self;
+        }
+
+        let centre = transform.transform_point3(self.centre());
+        let linear = glam::Mat3::from_mat4(transform);
+        let abs = glam::Mat3::from_cols(
+            linear.x_axis.abs(),
+            linear.y_axis.abs(),
+            linear.z_axis.abs(),
+        );
+        let half_extents = abs * self.half_extents();
+
+        Self {
+            min: centre - half_extents,
+            max: centre + half_extents,
+        }
+    }
+}
+
+/// The six planes bounding what a camera sees, facing inwards.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Frustum {
+    /// `xyz` is the plane's normal and `w` its offset, so points inside have a non-negative
+    /// `dot(xyz, point) + w`. Left, right, bottom, top, near, far.
+    pub planes: [glam::Vec4; 6],
+}
+
+impl Frustum {
+    /// Extracts the planes from a view-projection matrix with wgpu's 0 to 1 depth range, see
+    /// [`crate::camera::Camera::build_view_projection`].
+    pub fn from_view_projection(view_projection: glam::Mat4) -> Self {
+        let [x, y, z, w] = [0, 1, 2, 3].map(|i| view_projection.row(i));
+
+        Self {
+            planes: [w + x, w - x, w + y, w - y, z, w - z],
+        }
+    }
+
+    /// Whether any part of `aabb` may be inside. Boxes near the frustum's corners can pass
+    /// without being seen, but nothing seen is ever rejected.
+    pub fn intersects(&self, aabb: &Aabb) -> bool {
+        if aabb.is_empty() {
+            return false;
+        }
+
+        self.planes.iter().all(|plane| {
+            // The corner furthest along the plane's normal
+            let corner =
+                glam::Vec3::select(plane.truncate().cmpge(glam::Vec3::ZERO), aabb.max, aabb.min);
+
+            plane.truncate().dot(corner) + plane.w >= 0.0
+        })
+    }
+}
+
+/// How many instances the last frustum culling kept and dropped, see
+/// [`crate::Context::culling_stats`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CullingStats {
+    pub drawn: usize,
+    pub culled: usize,
+}
+
+/// Size of one set of `DrawIndexedIndirect` arguments.
+const DRAW_SIZE: usize = 5 * std::mem::size_of::<u32>();
+/// Offset of the instance count in one set of `DrawIndexedIndirect` arguments.
+const INSTANCE_COUNT_OFFSET: usize = std::mem::size_of::<u32>();
+/// Invocations per workgroup of cull.wgsl.
+const WORKGROUP_SIZE: u32 = 256;
+
+/// The frustum and model bounds as cull.wgsl sees them.
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+struct CullingData {
+    planes: [glam::Vec4; 6],
+    bounds_min: glam::Vec4,
+    bounds_max: glam::Vec4,
+    instance_count: u32,
+    _padding: [u32; 3],
+}
+
+unsafe impl bytemuck::Pod for CullingData {}
+unsafe impl bytemuck::Zeroable for CullingData {}
+
+/// How many instances each mesh of a camera pass is drawn with.
+#[derive(Clone, Copy)]
+pub(crate) enum InstanceDraws<'a> {
+    /// The same count for every mesh, known on the CPU.
+    Direct(u32),
+    /// Read from the arguments GPU culling wrote, one set per mesh of every model in order.
+    Indirect(&'a wgpu::Buffer),
+}
+
+impl<'a> InstanceDraws<'a> {
+    /// Nothing to draw at all, so the instance buffer may not even be bindable.
+    pub fn is_empty(&self) -> bool {
+        matches!(self, Self::Direct(0))
+    }
+
+    /// Draws `mesh`, the `index`th mesh of every model in order.
+    pub fn draw(&self, render_pass: &mut wgpu::RenderPass<'a>, index: usize, mesh: &Mesh) {
+        match *self {
+            Self::Direct(count) => render_pass.draw_indexed(0..mesh.num_elements, 0, 0..count),
+            Self::Indirect(draws) => {
+                render_pass.draw_indexed_indirect(draws, (index * DRAW_SIZE) as u64)
+            }
+        }
+    }
+}
+
+/// Frustum culling in a compute pass, writing the visible instances and the indirect draw
+/// arguments of every mesh without a round trip through the CPU.
+pub(crate) struct GpuCulling {
+    pipeline: wgpu::ComputePipeline,
+    uniform: wgpu::Buffer,
+    /// `DrawIndexedIndirect` arguments of every mesh, see [`InstanceDraws::Indirect`].
+    draws: wgpu::Buffer,
+    mesh_count: usize,
+    /// How many instances cull.wgsl kept, copied into every mesh's arguments.
+    visible_count: wgpu::Buffer,
+    /// `None` until the first rebuild.
+    bind_group: Option<wgpu::BindGroup>,
+}
+
+impl GpuCulling {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Cull Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("resources/shaders/cull.wgsl").into()),
+        });
+
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Cull Pipeline"),
+            layout: None,
+            module: &shader,
+            entry_point: "main",
+        });
+
+        let uniform = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Culling Buffer"),
+            size: std::mem::size_of::<CullingData>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let visible_count = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Visible Count Buffer"),
+            size: std::mem::size_of::<u32>() as u64,
+            usage: wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::COPY_SRC
+                | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            pipeline,
+            uniform,
+            draws: create_draw_buffer(device, &[]),
+            mesh_count: 0,
+            visible_count,
+            bind_group: None,
+        }
+    }
+
+    pub fn draws(&self) -> &wgpu::Buffer {
+        &self.draws
+    }
+
+    /// Rebuilds the draw arguments and rebinds the instance buffers after the models changed
+    /// or the buffers were replaced. `visible_instance_buffer` needs as much room as
+    /// `instance_buffer`.
+    pub fn rebuild(
+        &mut self,
+        device: &wgpu::Device,
+        models: &[Model],
+        instance_buffer: &wgpu::Buffer,
+        visible_instance_buffer: &wgpu::Buffer,
+    ) {
+        let meshes = models
+            .iter()
+            .flat_map(|model| &model.meshes)
+            .collect::<Vec<_>>();
+        self.draws = create_draw_buffer(device, &meshes);
+        self.mesh_count = meshes.len();
+
+        self.bind_group = Some(device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Cull Bind Group"),
+            layout: &self.pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: self.uniform.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: instance_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: visible_instance_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: self.visible_count.as_entire_binding(),
+                },
+            ],
+        }));
+    }
+
+    /// Uploads the frustum of `camera` and the bounds of `models` for the next dispatch.
+    pub fn update(
+        &self,
+        queue: &wgpu::Queue,
+        camera: &Camera,
+        models: &[Model],
+        instance_count: usize,
+    ) {
+        let bounds = models
+            .iter()
+            .flat_map(|model| &model.meshes)
+            .map(|mesh| mesh.bounds)
+            .fold(Aabb::EMPTY, Aabb::union);
+
+        let data = CullingData {
+            planes: camera.frustum().planes,
+            bounds_min: bounds.min.extend(0.0),
+            bounds_max: bounds.max.extend(0.0),
+            instance_count: instance_count as u32,
+            _padding: [0; 3],
+        };
+
+        queue.write_buffer(&self.uniform, 0, bytemuck::cast_slice(&[data]));
+    }
+
+    /// Records the culling pass over `instance_count` instances, before anything draws the
+    /// visible ones.
+    pub fn dispatch(&self, encoder: &mut wgpu::CommandEncoder, instance_count: usize) {
+        let Some(bind_group) = &self.bind_group else {
+            return;
+        };
+
+        encoder.clear_buffer(&self.visible_count, 0, None);
+
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("Cull Pass"),
+                timestamp_writes: None,
+            });
+
+            pass.set_pipeline(&self.pipeline);
+            pass.set_bind_group(0, bind_group, &[]);
+            pass.dispatch_workgroups((instance_count as u32).div_ceil(WORKGROUP_SIZE), 1, 1);
+        }
+
+        for mesh in 0..self.mesh_count {
+            encoder.copy_buffer_to_buffer(
+                &self.visible_count,
+                0,
+                &self.draws,
+                (mesh * DRAW_SIZE + INSTANCE_COUNT_OFFSET) as u64,
+                std::mem::size_of::<u32>() as u64,
+            );
+        }
+    }
+}
+
+/// Indirect arguments drawing each of `meshes` whole, with the instance counts left for
+/// cull.wgsl. Holds one unused set without meshes, since buffers cannot be empty.
+fn create_draw_buffer(device: &wgpu::Device, meshes: &[&Mesh]) -> wgpu::Buffer {
+    let mut draws = meshes
+        .iter()
+        .flat_map(|mesh| [mesh.num_elements, 0, 0, 0, 0])
+        .collect::<Vec<u32>>();
+
+    if draws.is_empty() {
+        draws.resize(DRAW_SIZE / std::mem::size_of::<u32>(), 0);
+    }
+
+    device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Indirect Draw Buffer"),
+        contents: bytemuck::cast_slice(&draws),
+        usage: wgpu::BufferUsages::INDIRECT | wgpu::BufferUsages::COPY_DST,
+    })
+}
@@ -0,0 +1,359 @@
+use std::{collections::HashMap, hash::BuildHasherDefault};
+
+use wgpu::util::DeviceExt;
+
+use crate::{
+    camera::Camera, include_shader_snippets, instance::InstanceData, model::Vertex,
+    texture::Texture,
+};
+
+/// Formats of the G-buffer targets, in the order of gbuffer.frag's outputs: albedo and alpha,
+/// world space normal, the material scalars and emitted light.
+const GBUFFER_FORMATS: [wgpu::TextureFormat; 4] = [
+    wgpu::TextureFormat::Rgba8UnormSrgb,
+    wgpu::TextureFormat::Rgba16Float,
+    wgpu::TextureFormat::Rgba8Unorm,
+    wgpu::TextureFormat::Rgba16Float,
+];
+
+/// The deferred render path: a geometry pass stores what the forward shader would light into
+/// the G-buffer, and a full-screen pass lights every pixel of it once.
+pub(crate) struct Deferred {
+    geometry: wgpu::RenderPipeline,
+    lighting: wgpu::RenderPipeline,
+    layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    /// The camera's inverse view-projection, to get positions back from depth.
+    buffer: wgpu::Buffer,
+    targets: [Texture; 4],
+    bind_group: wgpu::BindGroup,
+}
+
+impl Deferred {
+    /// `pipeline_layout` is the main pass layout, with the material and camera groups, and
+    /// `depth` the single-sampled depth buffer the geometry pass renders into.
+    pub fn new(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        pipeline_layout: &wgpu::PipelineLayout,
+        camera_layout: &wgpu::BindGroupLayout,
+        camera: &Camera,
+        depth: &Texture,
+    ) -> Self {
+        let texture = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                multisampled: false,
+                view_dimension: wgpu::TextureViewDimension::D2,
+                sample_type: wgpu::TextureSampleType::Float { filterable: false },
+            },
+            count: None,
+        };
+
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("G-Buffer Layout"),
+            entries: &[
+                texture(0),
+                texture(1),
+                texture(2),
+                texture(3),
+                texture(4),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 5,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::NonFiltering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 6,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        // Every pixel is lit from its own texels, nothing is filtered
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("G-Buffer Sampler"),
+            ..Default::default()
+        });
+
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("G-Buffer Buffer"),
+            contents: bytemuck::cast_slice(&[camera.build_view_projection().inverse()]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let lighting_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Deferred Lighting Layout"),
+            bind_group_layouts: &[&layout, camera_layout],
+            push_constant_ranges: &[],
+        });
+
+        let targets = gbuffer_targets(device, config);
+        let bind_group = gbuffer_bind_group(device, &layout, &sampler, &buffer, &targets, depth);
+
+        Self {
+            geometry: geometry_pipeline(device, pipeline_layout),
+            lighting: lighting_pipeline(device, &lighting_layout),
+            layout,
+            sampler,
+            buffer,
+            targets,
+            bind_group,
+        }
+    }
+
+    /// The pipeline the geometry pass draws models with, see [`Deferred::geometry_pass`].
+    pub fn geometry_pipeline(&self) -> &wgpu::RenderPipeline {
+        &self.geometry
+    }
+
+    /// Follows the surface and the depth buffer to a new size.
+    pub fn resize(
+        &mut self,
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        depth: &Texture,
+    ) {
+        self.targets = gbuffer_targets(device, config);
+        self.bind_group = gbuffer_bind_group(
+            device,
+            &self.layout,
+            &self.sampler,
+            &self.buffer,
+            &self.targets,
+            depth,
+        );
+    }
+
+    pub fn update(&self, queue: &wgpu::Queue, camera: &Camera) {
+        queue.write_buffer(
+            &self.buffer,
+            0,
+            bytemuck::cast_slice(&[camera.build_view_projection().inverse()]),
+        );
+    }
+
+    /// Begins the pass that fills the G-buffer and `depth`, for the caller to draw the scene
+    /// into with [`Deferred::geometry_pipeline`].
+    pub fn geometry_pass<'a>(
+        &'a self,
+        encoder: &'a mut wgpu::CommandEncoder,
+        depth: &'a Texture,
+    ) -> wgpu::RenderPass<'a> {
+        let colour_attachments = self
+            .targets
+            .iter()
+            .map(|target| {
+                Some(wgpu::RenderPassColorAttachment {
+                    view: &target.view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })
+            })
+            .collect::<Vec<_>>();
+
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Geometry Pass"),
+            color_attachments: &colour_attachments,
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &depth.view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        })
+    }
+
+    /// Lights the G-buffer into `view`, which is cleared to `background` wherever the
+    /// geometry pass drew nothing.
+    pub fn render(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        camera_bind_group: &wgpu::BindGroup,
+        view: &wgpu::TextureView,
+        background: wgpu::Color,
+    ) {
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Deferred Lighting Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(background),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+
+        pass.set_pipeline(&self.lighting);
+        pass.set_bind_group(0, &self.bind_group, &[]);
+        pass.set_bind_group(1, camera_bind_group, &[]);
+        pass.draw(0..3, 0..1);
+    }
+}
+
+fn gbuffer_targets(device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) -> [Texture; 4] {
+    GBUFFER_FORMATS.map(|format| {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("gbuffer"),
+            size: wgpu::Extent3d {
+                width: config.width,
+                height: config.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor::default());
+
+        Texture {
+            texture,
+            view,
+            sampler,
+        }
+    })
+}
+
+fn gbuffer_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    sampler: &wgpu::Sampler,
+    buffer: &wgpu::Buffer,
+    targets: &[Texture; 4],
+    depth: &Texture,
+) -> wgpu::BindGroup {
+    let textures = targets
+        .iter()
+        .chain([depth])
+        .enumerate()
+        .map(|(binding, texture)| wgpu::BindGroupEntry {
+            binding: binding as u32,
+            resource: wgpu::BindingResource::TextureView(&texture.view),
+        });
+
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("G-Buffer Bind Group"),
+        layout,
+        entries: &textures
+            .chain([
+                wgpu::BindGroupEntry {
+                    binding: 5,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 6,
+                    resource: buffer.as_entire_binding(),
+                },
+            ])
+            .collect::<Vec<_>>(),
+    })
+}
+
+fn geometry_pipeline(device: &wgpu::Device, layout: &wgpu::PipelineLayout) -> wgpu::RenderPipeline {
+    let vertex_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("Shader"),
+        source: wgpu::ShaderSource::Glsl {
+            shader: include_str!("resources/shaders/shader.vert").into(),
+            stage: wgpu::naga::ShaderStage::Vertex,
+            defines: HashMap::with_hasher(BuildHasherDefault::default()),
+        },
+    });
+
+    let fragment_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("G-Buffer Shader"),
+        source: wgpu::ShaderSource::Glsl {
+            shader: include_shader_snippets(include_str!("resources/shaders/gbuffer.frag")).into(),
+            stage: wgpu::naga::ShaderStage::Fragment,
+            defines: HashMap::with_hasher(BuildHasherDefault::default()),
+        },
+    });
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Geometry Pipeline"),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: &vertex_shader,
+            entry_point: "main",
+            buffers: &[Vertex::descriptor(), InstanceData::descriptor()],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &fragment_shader,
+            entry_point: "main",
+            targets: &GBUFFER_FORMATS.map(|format| Some(format.into())),
+        }),
+        // Nothing is culled, the same as the forward pipeline
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: wgpu::TextureFormat::Depth32Float,
+            depth_write_enabled: true,
+            depth_compare: wgpu::CompareFunction::Less,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+    })
+}
+
+fn lighting_pipeline(device: &wgpu::Device, layout: &wgpu::PipelineLayout) -> wgpu::RenderPipeline {
+    let vertex_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("Fullscreen Shader"),
+        source: wgpu::ShaderSource::Glsl {
+            shader: include_str!("resources/shaders/fullscreen.vert").into(),
+            stage: wgpu::naga::ShaderStage::Vertex,
+            defines: HashMap::with_hasher(BuildHasherDefault::default()),
+        },
+    });
+
+    let fragment_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("Deferred Lighting Shader"),
+        source: wgpu::ShaderSource::Glsl {
+            shader: include_shader_snippets(include_str!("resources/shaders/deferred.frag")).into(),
+            stage: wgpu::naga::ShaderStage::Fragment,
+            defines: HashMap::with_hasher(BuildHasherDefault::default()),
+        },
+    });
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Deferred Lighting Pipeline"),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: &vertex_shader,
+            entry_point: "main",
+            buffers: &[],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &fragment_shader,
+            entry_point: "main",
+            targets: &[Some(Texture::HDR_FORMAT.into())],
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+    })
+}
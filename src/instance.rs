@@ -1,3 +1,8 @@
+use std::ops::Range;
+
+use wgpu::util::DeviceExt;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Instance {
     pub position: glam::Vec3,
     pub rotation: glam::Quat,
@@ -12,6 +17,11 @@ impl Instance {
             colour: self.colour,
         }
     }
+
+    /// World space bounds of a model with object space bounds `local` drawn at this instance.
+    pub fn bounds(&self, local: &crate::culling::Aabb) -> crate::culling::Aabb {
+        local.transformed(self.to_raw().model)
+    }
 }
 
 #[repr(C)]
@@ -41,3 +51,237 @@ impl InstanceData {
         }
     }
 }
+
+/// Refers to one spawned instance until it is despawned, see
+/// [`crate::Context::spawn_instance`]. Handles of despawned instances never come back to life,
+/// even once their slot is reused.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct InstanceHandle {
+    slot: u32,
+    generation: u32,
+}
+
+/// Where the instance of a handle lives, if it still does.
+struct Slot {
+    generation: u32,
+    /// Position in the drawn instances.
+    index: Option<usize>,
+}
+
+/// The drawn instances, packed in draw order, and the GPU buffer they are uploaded into.
+///
+/// Despawning moves the last instance into the freed place, so the buffer never has holes.
+/// Changes are only uploaded on [`InstanceStore::flush`], as one range around everything
+/// that changed since the last one.
+pub(crate) struct InstanceStore {
+    instances: Vec<Instance>,
+    /// The handle of each instance, in the same order.
+    handles: Vec<InstanceHandle>,
+    slots: Vec<Slot>,
+    free_slots: Vec<u32>,
+    buffer: wgpu::Buffer,
+    /// Instances the buffer has room for.
+    capacity: usize,
+    dirty: Option<Range<usize>>,
+}
+
+impl InstanceStore {
+    /// Smallest buffer allocated, so a few spawns do not regrow it every time.
+    const MIN_CAPACITY: usize = 16;
+
+    pub fn new(device: &wgpu::Device, instances: Vec<Instance>) -> Self {
+        let mut store = Self {
+            instances: Vec::new(),
+            handles: Vec::new(),
+            slots: Vec::new(),
+            free_slots: Vec::new(),
+            // Allocated by the first grow
+            buffer: create_instance_buffer(device, &[], 0),
+            capacity: 0,
+            dirty: None,
+        };
+        store.replace(instances);
+        store.grow(device);
+
+        store
+    }
+
+    pub fn as_slice(&self) -> &[Instance] {
+        &self.instances
+    }
+
+    pub fn handles(&self) -> &[InstanceHandle] {
+        &self.handles
+    }
+
+    pub fn len(&self) -> usize {
+        self.instances.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.instances.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn buffer(&self) -> &wgpu::Buffer {
+        &self.buffer
+    }
+
+    /// Despawns every instance and spawns `instances` in their place.
+    pub fn replace(&mut self, instances: Vec<Instance>) {
+        for handle in std::mem::take(&mut self.handles) {
+            self.free(handle);
+        }
+
+        self.instances.clear();
+
+        for instance in instances {
+            self.spawn(instance);
+        }
+    }
+
+    pub fn spawn(&mut self, instance: Instance) -> InstanceHandle {
+        let index = self.instances.len();
+        let slot = match self.free_slots.pop() {
+            Some(slot) => slot,
+            None => {
+                self.slots.push(Slot {
+                    generation: 0,
+                    index: None,
+                });
+                self.slots.len() as u32 - 1
+            }
+        };
+        self.slots[slot as usize].index = Some(index);
+
+        let handle = InstanceHandle {
+            slot,
+            generation: self.slots[slot as usize].generation,
+        };
+        self.instances.push(instance);
+        self.handles.push(handle);
+        self.mark_dirty(index);
+
+        handle
+    }
+
+    /// Removes the instance of `handle`, or returns `None` if it was already despawned.
+    pub fn despawn(&mut self, handle: InstanceHandle) -> Option<Instance> {
+        let index = self.index(handle)?;
+        let instance = self.instances.swap_remove(index);
+        self.handles.swap_remove(index);
+        self.free(handle);
+
+        // The last instance took its place
+        if let Some(&moved) = self.handles.get(index) {
+            self.slots[moved.slot as usize].index = Some(index);
+            self.mark_dirty(index);
+        }
+
+        Some(instance)
+    }
+
+    pub fn get(&self, handle: InstanceHandle) -> Option<&Instance> {
+        self.index(handle).map(|index| &self.instances[index])
+    }
+
+    /// Like [`InstanceStore::get`], marking the instance for upload.
+    pub fn get_mut(&mut self, handle: InstanceHandle) -> Option<&mut Instance> {
+        let index = self.index(handle)?;
+        self.mark_dirty(index);
+
+        Some(&mut self.instances[index])
+    }
+
+    /// Replaces the buffer with a bigger one holding every instance, if they no longer fit.
+    /// Returns whether it did, which leaves nothing to flush.
+    pub fn grow(&mut self, device: &wgpu::Device) -> bool {
+        if self.instances.len() <= self.capacity && self.capacity > 0 {
+            return false;
+        }
+
+        self.capacity = self
+            .instances
+            .len()
+            .next_power_of_two()
+            .max(Self::MIN_CAPACITY);
+        let instance_data = self
+            .instances
+            .iter()
+            .map(Instance::to_raw)
+            .collect::<Vec<_>>();
+        self.buffer = create_instance_buffer(device, &instance_data, self.capacity);
+        self.dirty = None;
+
+        true
+    }
+
+    /// Uploads the instances changed since the last flush. They must fit the buffer, see
+    /// [`InstanceStore::grow`].
+    pub fn flush(&mut self, queue: &wgpu::Queue) {
+        let Some(dirty) = self.dirty.take() else {
+            return;
+        };
+        // Despawning may have moved the end of the range past the last instance
+        let dirty = dirty.start..dirty.end.min(self.instances.len());
+
+        if dirty.is_empty() {
+            return;
+        }
+
+        let instance_data = self.instances[dirty.clone()]
+            .iter()
+            .map(Instance::to_raw)
+            .collect::<Vec<_>>();
+        let offset = dirty.start * std::mem::size_of::<InstanceData>();
+
+        queue.write_buffer(
+            &self.buffer,
+            offset as wgpu::BufferAddress,
+            bytemuck::cast_slice(&instance_data),
+        );
+    }
+
+    fn index(&self, handle: InstanceHandle) -> Option<usize> {
+        self.slots
+            .get(handle.slot as usize)
+            .filter(|slot| slot.generation == handle.generation)
+            .and_then(|slot| slot.index)
+    }
+
+    fn free(&mut self, handle: InstanceHandle) {
+        let slot = &mut self.slots[handle.slot as usize];
+        slot.generation = slot.generation.wrapping_add(1);
+        slot.index = None;
+        self.free_slots.push(handle.slot);
+    }
+
+    fn mark_dirty(&mut self, index: usize) {
+        self.dirty = Some(match self.dirty.take() {
+            Some(dirty) => dirty.start.min(index)..dirty.end.max(index + 1),
+            None => index..index + 1,
+        });
+    }
+}
+
+/// Room for `capacity` instances, starting with `instances`. Also read by the culling compute
+/// pass.
+fn create_instance_buffer(
+    device: &wgpu::Device,
+    instances: &[InstanceData],
+    capacity: usize,
+) -> wgpu::Buffer {
+    let mut contents = bytemuck::cast_slice(instances).to_vec();
+    contents.resize(capacity * std::mem::size_of::<InstanceData>(), 0);
+
+    device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Instance buffer"),
+        contents: &contents,
+        usage: wgpu::BufferUsages::VERTEX
+            | wgpu::BufferUsages::STORAGE
+            | wgpu::BufferUsages::COPY_DST,
+    })
+}
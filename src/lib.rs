@@ -1,11 +1,29 @@
-mod camera;
-mod instance;
-mod texture;
+pub mod bloom;
+pub mod camera;
+mod capture;
+pub mod cluster;
+pub mod compressed;
+mod cubemap;
+pub mod culling;
+mod deferred;
+pub mod instance;
+pub mod light;
+mod mipmap;
+pub mod model;
+pub mod post;
+pub mod scene;
+pub mod shadow;
+mod skybox;
+pub mod ssao;
+pub mod texture;
+pub mod tonemap;
 
-use bytemuck::{cast_slice, Pod, Zeroable};
-use glam::{vec2, vec3, vec4, Quat, Vec2, Vec3};
+use bytemuck::cast_slice;
+use glam::{vec2, vec3, vec4, Quat, Vec3};
 use rand::Rng;
-use std::{collections::HashMap, hash::BuildHasherDefault, iter::once, mem::size_of};
+use std::{
+    collections::HashMap, hash::BuildHasherDefault, iter::once, path::Path, time::SystemTime,
+};
 use wgpu::{
     naga::ShaderStage,
     util::{BufferInitDescriptor, DeviceExt},
@@ -18,33 +36,61 @@ use winit::{
     window::{Window, WindowBuilder},
 };
 
-use crate::{camera::Camera, instance::InstanceData};
+use crate::{
+    bloom::{Bloom, BloomSettings},
+    camera::Camera,
+    cluster::{ClusterData, ClusterSettings, LightClusters},
+    culling::{Aabb, CullingStats, GpuCulling, InstanceDraws},
+    deferred::Deferred,
+    instance::{InstanceData, InstanceHandle, InstanceStore},
+    light::{Light, LightKind, LightingData},
+    model::{Material, Mesh, Model, Vertex},
+    post::{PostEffect, PostStack},
+    scene::Scene,
+    shadow::{
+        PointShadowData, PointShadowMap, PointShadowSettings, ShadowData, ShadowMap, ShadowSettings,
+    },
+    ssao::{Ssao, SsaoSettings},
+    tonemap::{Tonemap, TonemapSettings},
+};
 // lib.rs
 const VERTICES: &[Vertex] = &[
     Vertex {
         position: vec3(-0.0868241, 0.49240386, 0.0),
         tex_coords: vec2(0.4131759, 0.00759614),
+        normal: vec3(0.0, 0.0, 1.0),
     }, // A
     Vertex {
         position: vec3(-0.49513406, 0.06958647, 0.0),
         tex_coords: vec2(0.0048659444, 0.43041354),
+        normal: vec3(0.0, 0.0, 1.0),
     }, // B
     Vertex {
         position: vec3(-0.21918549, -0.44939706, 0.0),
         tex_coords: vec2(0.28081453, 0.949397),
+        normal: vec3(0.0, 0.0, 1.0),
     }, // C
     Vertex {
         position: vec3(0.35966998, -0.3473291, 0.0),
         tex_coords: vec2(0.85967, 0.84732914),
+        normal: vec3(0.0, 0.0, 1.0),
     }, // D
     Vertex {
         position: vec3(0.44147372, 0.2347359, 0.0),
         tex_coords: vec2(0.9414737, 0.2652641),
+        normal: vec3(0.0, 0.0, 1.0),
     }, // E
 ];
 
 const CAMERA_SPEED: f32 = 5.0;
-const INDICES: &[u16] = &[0, 1, 4, 1, 2, 4, 2, 3, 4, 0];
+/// Background wherever neither the scene nor a skybox was drawn.
+const CLEAR_COLOUR: Color = Color {
+    r: 0.1,
+    g: 0.2,
+    b: 0.3,
+    a: 1.0,
+};
+const INDICES: &[u32] = &[0, 1, 4, 1, 2, 4, 2, 3, 4];
 const NUM_INSTANCES_PER_ROW: u32 = 10;
 const INSTANCE_DISPLACEMENT: Vec3 = vec3(
     NUM_INSTANCES_PER_ROW as f32 * 0.5,
@@ -52,49 +98,187 @@ const INSTANCE_DISPLACEMENT: Vec3 = vec3(
     NUM_INSTANCES_PER_ROW as f32 * 0.5,
 );
 
-#[repr(C)]
-#[derive(Clone, Copy, Debug)]
-struct Vertex {
-    position: Vec3,
-    tex_coords: Vec2,
+/// Requests a device with every texture compression family the adapter supports, so
+/// pre-compressed textures can be uploaded without decoding them first.
+async fn request_device(adapter: &Adapter) -> Result<(Device, Queue), RequestDeviceError> {
+    let optional = Features::TEXTURE_COMPRESSION_BC
+        | Features::TEXTURE_COMPRESSION_ETC2
+        | Features::TEXTURE_COMPRESSION_ASTC
+        | Features::FLOAT32_FILTERABLE
+        // Lets MSAA use every sample count the adapter has, not just 1 and 4
+        | Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES;
+
+    adapter
+        .request_device(
+            &wgpu::DeviceDescriptor {
+                label: None,
+                required_features: adapter.features() & optional,
+                required_limits: wgpu::Limits::downlevel_defaults(),
+            },
+            None,
+        )
+        .await
 }
 
-unsafe impl Pod for Vertex {}
-unsafe impl Zeroable for Vertex {}
+/// Snippets shared between shaders, naga's GLSL frontend has no `#include` of its own.
+const SHADER_INCLUDES: &[(&str, &str)] = &[
+    (
+        "clusters.glsl",
+        include_str!("resources/shaders/clusters.glsl"),
+    ),
+    ("light.glsl", include_str!("resources/shaders/light.glsl")),
+    (
+        "lighting.glsl",
+        include_str!("resources/shaders/lighting.glsl"),
+    ),
+    (
+        "material.glsl",
+        include_str!("resources/shaders/material.glsl"),
+    ),
+];
 
-impl Vertex {
-    const ATTRIBS: [VertexAttribute; 2] = vertex_attr_array![0 => Float32x3, 1 => Float32x2];
+/// Replaces each `#include "name"` line of `source` with the snippet of that name, which may
+/// include others in turn.
+pub(crate) fn include_shader_snippets(source: &str) -> String {
+    source
+        .lines()
+        .map(|line| match line.trim().strip_prefix("#include ") {
+            Some(name) => {
+                let name = name.trim_matches('"');
 
-    fn desc() -> VertexBufferLayout<'static> {
-        VertexBufferLayout {
-            array_stride: size_of::<Self>() as BufferAddress,
-            step_mode: VertexStepMode::Vertex,
-            attributes: &Self::ATTRIBS,
-        }
+                SHADER_INCLUDES
+                    .iter()
+                    .find(|(include, _)| *include == name)
+                    .map(|(_, snippet)| include_shader_snippets(snippet))
+                    .unwrap_or_else(|| panic!("unknown shader include {name}"))
+            }
+            None => line.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Storage buffers cannot be empty, so an unlit scene still uploads one (unused) light.
+fn create_light_buffer(device: &Device, lights: &[Light]) -> Buffer {
+    let mut light_data = lights.iter().map(Light::to_raw).collect::<Vec<_>>();
+
+    if light_data.is_empty() {
+        light_data.push(Light::point(Vec3::ZERO, Vec3::ZERO, 0.0).to_raw());
     }
+
+    device.create_buffer_init(&BufferInitDescriptor {
+        label: Some("Light Buffer"),
+        contents: cast_slice(&light_data),
+        usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
+    })
+}
+
+/// Room for all `count` instances, of which the visible ones are written each frame.
+fn create_visible_instance_buffer(device: &Device, count: usize) -> Buffer {
+    device.create_buffer(&BufferDescriptor {
+        label: Some("Visible Instance Buffer"),
+        size: (count * std::mem::size_of::<InstanceData>()) as BufferAddress,
+        usage: BufferUsages::VERTEX | BufferUsages::STORAGE | BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+/// Where a frame ends up once the main pass has been recorded.
+enum RenderTarget<'a> {
+    /// Swapchain texture of a window surface, presented after every frame.
+    Surface(Surface<'a>),
+    /// Owned colour texture for headless rendering (CI, build servers, tests).
+    Offscreen(texture::Texture),
+}
+
+/// How the main pass lights the scene, fixed when the [`Context`] is created.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RenderPath {
+    /// Every mesh is lit as it is drawn, the only path with MSAA.
+    #[default]
+    Forward,
+    /// Meshes are drawn into a G-buffer first and each pixel is lit once afterwards, so the
+    /// cost of lighting does not grow with overdraw.
+    Deferred,
+}
+
+/// Settings that can only be chosen when a [`Context`] is created, see
+/// [`Context::new_with_options`] and [`Context::new_headless_with_options`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ContextOptions {
+    pub render_path: RenderPath,
 }
 
 pub struct Context<'a> {
+    adapter: Adapter,
     device: Device,
     queue: Queue,
-    surface: Surface<'a>,
+    target: RenderTarget<'a>,
     depth_texture: texture::Texture,
+    /// The scene is lit into this, then tonemapped into the target.
+    hdr_target: texture::Texture,
+    /// Rendered into instead of the HDR target and resolved into it when MSAA is on.
+    msaa_target: Option<texture::Texture>,
+    sample_count: u32,
     config: SurfaceConfiguration,
+    pipeline_layout: PipelineLayout,
     pipeline: RenderPipeline,
-    vertex_buffer: Buffer,
-    index_buffer: Buffer,
-    indices_length: u32,
-    texture_bind_group: BindGroup,
-    texture: texture::Texture,
+    models: Vec<Model>,
+    texture_bind_group_layout: BindGroupLayout,
+    default_material: Material,
     camera: Camera,
     camera_buffer: Buffer,
+    camera_bind_group_layout: BindGroupLayout,
     camera_bind_group: BindGroup,
-    instances: Vec<instance::Instance>,
-    instance_buffer: Buffer,
+    lights: Vec<Light>,
+    ambient: Vec3,
+    lighting_buffer: Buffer,
+    light_buffer: Buffer,
+    shadow_buffer: Buffer,
+    shadow_map: Option<ShadowMap>,
+    /// Bound in place of the shadow map while shadows are off.
+    shadow_placeholder: texture::Texture,
+    point_shadow_buffer: Buffer,
+    point_shadow_map: Option<PointShadowMap>,
+    /// Bound in place of the point shadow map while point shadows are off.
+    point_shadow_placeholder: texture::Texture,
+    ssao: Option<Ssao>,
+    /// Bound in place of the occlusion texture while SSAO is off.
+    ssao_placeholder: texture::Texture,
+    cluster_buffer: Buffer,
+    clusters: Option<LightClusters>,
+    /// Bound in place of the cluster lists while clustered lighting is off.
+    cluster_placeholder: Buffer,
+    /// Every instance and the buffer holding them, read by the shadow maps, which also see
+    /// what the camera does not.
+    instances: InstanceStore,
+    /// The instances inside the camera's frustum, packed at the front.
+    visible_instance_buffer: Buffer,
+    frustum_culling: bool,
+    culling_stats: CullingStats,
+    /// View-projection the visible instances were last packed for on the CPU, `None` once the
+    /// instances, models or culling settings changed since.
+    culled_view_projection: Option<glam::Mat4>,
+    visible_uploads: usize,
+    /// Culls on the GPU instead, when set and frustum culling is on.
+    gpu_culling: Option<GpuCulling>,
+    skybox: Option<skybox::Skybox>,
+    /// The G-buffer and its passes on the deferred path, `None` on the forward path.
+    deferred: Option<Deferred>,
+    post: PostStack,
+    tonemapping: TonemapSettings,
+    tonemap: Tonemap,
+    /// Blit pipelines for every texture format mips were generated for so far.
+    mipmaps: mipmap::MipmapGenerator,
 }
 
 impl<'a> Context<'a> {
     pub async fn new(window: &'a Window) -> Self {
+        Self::new_with_options(window, &ContextOptions::default()).await
+    }
+
+    /// Like [`Context::new`], with the choices of `options`.
+    pub async fn new_with_options(window: &'a Window, options: &ContextOptions) -> Self {
         let instance = wgpu::Instance::new(Default::default());
         let surface = instance.create_surface(window).unwrap();
         let adapter = instance
@@ -106,24 +290,14 @@ impl<'a> Context<'a> {
             .await
             .unwrap();
 
-        println!("info: {:?}", adapter.get_info());
+        log::debug!("info: {:?}", adapter.get_info());
 
-        let (device, queue) = adapter
-            .request_device(
-                &wgpu::DeviceDescriptor {
-                    label: None,
-                    required_features: wgpu::Features::empty(),
-                    required_limits: wgpu::Limits::downlevel_defaults(),
-                },
-                None,
-            )
-            .await
-            .unwrap();
+        let (device, queue) = request_device(&adapter).await.unwrap();
 
         let caps = surface.get_capabilities(&adapter);
         let format = caps.formats[0];
 
-        println!("caps: {:?}", caps);
+        log::debug!("caps: {:?}", caps);
 
         let config = surface
             .get_default_config(
@@ -135,53 +309,113 @@ impl<'a> Context<'a> {
 
         surface.configure(&device, &config);
 
-        println!("format: {:?}", format);
+        log::debug!("format: {:?}", format);
+
+        Self::build(
+            adapter,
+            device,
+            queue,
+            config,
+            RenderTarget::Surface(surface),
+            options,
+        )
+    }
+
+    /// Creates a context without a window that renders into an owned offscreen texture.
+    ///
+    /// Prefers a hardware adapter and falls back to a software one (llvmpipe, lavapipe, WARP)
+    /// so it also works on machines without a display or GPU.
+    pub async fn new_headless(width: u32, height: u32) -> anyhow::Result<Context<'static>> {
+        Self::new_headless_with_options(width, height, &ContextOptions::default()).await
+    }
+
+    /// Like [`Context::new_headless`], with the choices of `options`.
+    pub async fn new_headless_with_options(
+        width: u32,
+        height: u32,
+        options: &ContextOptions,
+    ) -> anyhow::Result<Context<'static>> {
+        let instance = wgpu::Instance::new(Default::default());
+
+        let adapter = match instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                compatible_surface: None,
+                power_preference: wgpu::PowerPreference::HighPerformance,
+                force_fallback_adapter: false,
+            })
+            .await
+        {
+            Some(adapter) => adapter,
+            None => instance
+                .request_adapter(&wgpu::RequestAdapterOptions {
+                    compatible_surface: None,
+                    power_preference: wgpu::PowerPreference::LowPower,
+                    force_fallback_adapter: true,
+                })
+                .await
+                .ok_or_else(|| anyhow::anyhow!("no suitable adapter found"))?,
+        };
+
+        log::info!("headless adapter: {:?}", adapter.get_info());
+
+        let (device, queue) = request_device(&adapter).await?;
 
-        let texture = texture::Texture::from_file(
+        // There is no surface to ask for a default configuration, so describe the offscreen
+        // target the same way; the depth texture and pipeline are built from it either way.
+        let config = SurfaceConfiguration {
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+            format: TextureFormat::Rgba8UnormSrgb,
+            width: width.max(1),
+            height: height.max(1),
+            present_mode: PresentMode::Fifo,
+            desired_maximum_frame_latency: 2,
+            alpha_mode: CompositeAlphaMode::Opaque,
+            view_formats: vec![],
+        };
+
+        let target = RenderTarget::Offscreen(texture::Texture::create_render_target(
+            &device,
+            &config,
+            "offscreen_target",
+        ));
+
+        Ok(Context::build(
+            adapter, device, queue, config, target, options,
+        ))
+    }
+
+    fn build(
+        adapter: Adapter,
+        device: Device,
+        queue: Queue,
+        config: SurfaceConfiguration,
+        target: RenderTarget<'a>,
+        options: &ContextOptions,
+    ) -> Self {
+        let mipmaps = mipmap::MipmapGenerator::default();
+
+        // Checkouts without the LFS assets (e.g. CI) still need something to sample
+        let texture = texture::Texture::from_file_cached(
             &device,
             &queue,
+            &mipmaps,
             "src/resources/textures/happy-tree.png",
             "happy-tree.png",
+            &texture::TextureOptions::default(),
         )
-        .unwrap();
+        .unwrap_or_else(|e| {
+            log::warn!("could not load happy-tree.png, using a placeholder: {e}");
+            texture::Texture::placeholder(&device, &queue)
+        });
         // Describes a set of resources and how they can be accessed by a shader.
-        let texture_bind_group_layout =
-            device.create_bind_group_layout(&BindGroupLayoutDescriptor {
-                label: Some("texture_bind_group_layout"),
-                entries: &[
-                    BindGroupLayoutEntry {
-                        binding: 0,
-                        visibility: ShaderStages::FRAGMENT,
-                        ty: BindingType::Texture {
-                            sample_type: TextureSampleType::Float { filterable: true },
-                            view_dimension: TextureViewDimension::D2,
-                            multisampled: false,
-                        },
-                        count: None,
-                    },
-                    BindGroupLayoutEntry {
-                        binding: 1,
-                        visibility: ShaderStages::FRAGMENT,
-                        ty: BindingType::Sampler(SamplerBindingType::Filtering),
-                        count: None,
-                    },
-                ],
-            });
+        let texture_bind_group_layout = Material::create_bind_group_layout(&device);
 
-        let texture_bind_group = device.create_bind_group(&BindGroupDescriptor {
-            label: Some("texture_bind_group"),
-            layout: &texture_bind_group_layout,
-            entries: &[
-                BindGroupEntry {
-                    binding: 0,
-                    resource: BindingResource::TextureView(&texture.view),
-                },
-                BindGroupEntry {
-                    binding: 1,
-                    resource: BindingResource::Sampler(&texture.sampler),
-                },
-            ],
-        });
+        let default_material = Material::new(
+            &device,
+            &texture_bind_group_layout,
+            "default_material",
+            texture,
+        );
 
         let camera = Camera {
             eye: vec3(0f32, 1f32, 2f32),
@@ -193,37 +427,220 @@ impl<'a> Context<'a> {
             z_far: 100.0,
         };
 
-        let camera_matrix = camera.build_view_projection();
-
         let camera_buffer = device.create_buffer_init(&BufferInitDescriptor {
             label: Some("Camera Buffer"),
-            contents: cast_slice(&[camera_matrix]),
+            contents: cast_slice(&[camera.to_raw()]),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+
+        // No lights and full ambient light look the same as sampling the texture unlit
+        let lights = Vec::new();
+        let ambient = Vec3::ONE;
+
+        let lighting_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Lighting Buffer"),
+            contents: cast_slice(&[LightingData {
+                ambient: ambient.extend(1.0),
+                light_count: 0,
+                ambient_occlusion: 0,
+                clustered: 0,
+                _padding: [0; 1],
+            }]),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+        let light_buffer = create_light_buffer(&device, &lights);
+
+        let shadow_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Shadow Buffer"),
+            contents: cast_slice(&[ShadowData::disabled()]),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+        // Shadow textures work around GL's guessed texture targets, see create_shadow_map
+        let backend = adapter.get_info().backend;
+        let shadow_placeholder =
+            texture::Texture::create_shadow_map(&device, backend, 1, 1, "shadow_placeholder");
+
+        let point_shadow_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Point Shadow Buffer"),
+            contents: cast_slice(&[PointShadowData::disabled()]),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+        let point_shadow_placeholder = texture::Texture::create_shadow_cube_map(
+            &device,
+            backend,
+            1,
+            1,
+            "point_shadow_placeholder",
+        );
+        let ssao_placeholder = texture::Texture::from_colour(
+            &device,
+            &queue,
+            [255, 255, 255, 255],
+            "ssao_placeholder",
+        );
+
+        let cluster_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Cluster Buffer"),
+            contents: cast_slice(&[ClusterData::disabled()]),
             usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
         });
+        let cluster_placeholder = device.create_buffer(&BufferDescriptor {
+            label: Some("Cluster Placeholder"),
+            size: 16,
+            usage: BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
 
+        // Camera and lights change once per frame, materials once per mesh
         let camera_bind_group_layout =
             device.create_bind_group_layout(&BindGroupLayoutDescriptor {
-                entries: &[BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: ShaderStages::VERTEX,
-                    ty: BindingType::Buffer {
-                        ty: BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
+                entries: &[
+                    BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: ShaderStages::VERTEX | ShaderStages::FRAGMENT,
+                        ty: BindingType::Buffer {
+                            ty: BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: BindingType::Buffer {
+                            ty: BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: BindingType::Buffer {
+                            ty: BufferBindingType::Storage { read_only: true },
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    BindGroupLayoutEntry {
+                        binding: 3,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: BindingType::Buffer {
+                            ty: BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    BindGroupLayoutEntry {
+                        binding: 4,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: BindingType::Texture {
+                            sample_type: TextureSampleType::Depth,
+                            view_dimension: TextureViewDimension::D2Array,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    BindGroupLayoutEntry {
+                        binding: 5,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: BindingType::Sampler(SamplerBindingType::Comparison),
+                        count: None,
+                    },
+                    BindGroupLayoutEntry {
+                        binding: 6,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: BindingType::Buffer {
+                            ty: BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    BindGroupLayoutEntry {
+                        binding: 7,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: BindingType::Texture {
+                            sample_type: TextureSampleType::Depth,
+                            view_dimension: TextureViewDimension::CubeArray,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    BindGroupLayoutEntry {
+                        binding: 8,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: BindingType::Sampler(SamplerBindingType::Comparison),
+                        count: None,
+                    },
+                    BindGroupLayoutEntry {
+                        binding: 9,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: BindingType::Texture {
+                            multisampled: false,
+                            view_dimension: TextureViewDimension::D2,
+                            sample_type: TextureSampleType::Float { filterable: true },
+                        },
+                        count: None,
+                    },
+                    BindGroupLayoutEntry {
+                        binding: 10,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                    BindGroupLayoutEntry {
+                        binding: 11,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: BindingType::Buffer {
+                            ty: BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    BindGroupLayoutEntry {
+                        binding: 12,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: BindingType::Buffer {
+                            ty: BufferBindingType::Storage { read_only: true },
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    BindGroupLayoutEntry {
+                        binding: 13,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: BindingType::Buffer {
+                            ty: BufferBindingType::Storage { read_only: true },
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
                     },
-                    count: None,
-                }],
+                ],
                 label: Some("camera_bind_group_layout"),
             });
 
-        let camera_bind_group = device.create_bind_group(&BindGroupDescriptor {
-            layout: &camera_bind_group_layout,
-            entries: &[BindGroupEntry {
-                binding: 0,
-                resource: camera_buffer.as_entire_binding(),
-            }],
-            label: Some("camera_bind_group"),
-        });
+        let camera_bind_group = Self::create_camera_bind_group(
+            &device,
+            &camera_bind_group_layout,
+            &camera_buffer,
+            &lighting_buffer,
+            &light_buffer,
+            &shadow_buffer,
+            &shadow_placeholder,
+            &point_shadow_buffer,
+            &point_shadow_placeholder,
+            &ssao_placeholder,
+            &cluster_buffer,
+            [&cluster_placeholder, &cluster_placeholder],
+        );
 
         let instances = (0..NUM_INSTANCES_PER_ROW)
             .flat_map(|z| {
@@ -248,31 +665,107 @@ impl<'a> Context<'a> {
             })
             .collect::<Vec<_>>();
 
-        let instance_data = instances
-            .iter()
-            .map(instance::Instance::to_raw)
-            .collect::<Vec<_>>();
+        let instances = InstanceStore::new(&device, instances);
+        let visible_instance_buffer = create_visible_instance_buffer(&device, instances.capacity());
 
-        let instance_buffer = device.create_buffer_init(&BufferInitDescriptor {
-            label: Some("Instance buffer"),
-            contents: cast_slice(&instance_data),
-            usage: BufferUsages::VERTEX,
-        });
+        let pentagon = Model {
+            meshes: vec![Mesh::new(&device, "pentagon", VERTICES, INDICES, None)],
+            materials: vec![],
+        };
 
-        let vertex_buffer = device.create_buffer_init(&BufferInitDescriptor {
-            label: Some("Vertex Buffer"),
-            contents: cast_slice(VERTICES),
-            usage: BufferUsages::VERTEX,
+        let depth_texture =
+            texture::Texture::create_depth_texture(&device, &config, 1, "depth_texture");
+
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Render Pipeline Layout"),
+            bind_group_layouts: &[&texture_bind_group_layout, &camera_bind_group_layout],
+            push_constant_ranges: &[],
         });
 
-        let index_buffer = device.create_buffer_init(&BufferInitDescriptor {
-            label: Some("Index Buffer"),
-            contents: cast_slice(INDICES),
-            usage: BufferUsages::INDEX,
+        let pipeline =
+            Self::create_pipeline(&device, &pipeline_layout, texture::Texture::HDR_FORMAT, 1);
+
+        let deferred = (options.render_path == RenderPath::Deferred).then(|| {
+            Deferred::new(
+                &device,
+                &config,
+                &pipeline_layout,
+                &camera_bind_group_layout,
+                &camera,
+                &depth_texture,
+            )
         });
 
-        let indices_length = INDICES.len() as u32;
+        let hdr_target = texture::Texture::create_hdr_target(&device, &config, "hdr_target");
+        let tonemapping = TonemapSettings::default();
+        let post = PostStack::new(&device, &config, &hdr_target);
+        let tonemap = Tonemap::new(
+            &device,
+            config.format,
+            &[&hdr_target, post.spare()],
+            tonemapping,
+        );
+
+        let mut context = Self {
+            adapter,
+            device,
+            queue,
+            target,
+            depth_texture,
+            hdr_target,
+            msaa_target: None,
+            sample_count: 1,
+            config,
+            pipeline_layout,
+            pipeline,
+            models: vec![pentagon],
+            texture_bind_group_layout,
+            default_material,
+            camera,
+            camera_buffer,
+            camera_bind_group_layout,
+            camera_bind_group,
+            lights,
+            ambient,
+            lighting_buffer,
+            light_buffer,
+            shadow_buffer,
+            shadow_map: None,
+            shadow_placeholder,
+            point_shadow_buffer,
+            point_shadow_map: None,
+            point_shadow_placeholder,
+            ssao: None,
+            ssao_placeholder,
+            cluster_buffer,
+            clusters: None,
+            cluster_placeholder,
+            instances,
+            visible_instance_buffer,
+            frustum_culling: true,
+            culling_stats: CullingStats::default(),
+            culled_view_projection: None,
+            visible_uploads: 0,
+            gpu_culling: None,
+            skybox: None,
+            deferred,
+            post,
+            tonemapping,
+            tonemap,
+            mipmaps,
+        };
+        context.cull_instances();
+
+        context
+    }
 
+    /// Main pipeline drawing models into a `format` target with `sample_count` samples.
+    fn create_pipeline(
+        device: &Device,
+        layout: &PipelineLayout,
+        format: TextureFormat,
+        sample_count: u32,
+    ) -> RenderPipeline {
         let vertex_shader = device.create_shader_module(ShaderModuleDescriptor {
             label: Some("Shader"),
             source: ShaderSource::Glsl {
@@ -285,36 +778,28 @@ impl<'a> Context<'a> {
         let fragment_shader = device.create_shader_module(ShaderModuleDescriptor {
             label: Some("Shader"),
             source: ShaderSource::Glsl {
-                shader: include_str!("resources/shaders/shader.frag").into(),
+                shader: include_shader_snippets(include_str!("resources/shaders/shader.frag"))
+                    .into(),
                 stage: ShaderStage::Fragment,
                 defines: HashMap::with_hasher(BuildHasherDefault::default()),
             },
         });
 
-        let depth_texture =
-            texture::Texture::create_depth_texture(&device, &config, "depth_texture");
-
-        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
-            label: Some("Render Pipeline Layout"),
-            bind_group_layouts: &[&texture_bind_group_layout, &camera_bind_group_layout],
-            push_constant_ranges: &[],
-        });
-
-        let pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+        device.create_render_pipeline(&RenderPipelineDescriptor {
             label: Some("Render Pipeline"),
-            layout: Some(&pipeline_layout),
+            layout: Some(layout),
             // Define vertex pass
             vertex: VertexState {
                 module: &vertex_shader,
                 entry_point: "main",
-                buffers: &[Vertex::desc(), InstanceData::descriptor()],
+                buffers: &[Vertex::descriptor(), InstanceData::descriptor()],
             },
             // Define fragment pass
             fragment: Some(FragmentState {
                 module: &fragment_shader,
                 entry_point: "main",
                 targets: &[Some(ColorTargetState {
-                    format: config.format,
+                    format,
                     blend: Some(BlendState::REPLACE),
                     write_mask: ColorWrites::ALL,
                 })],
@@ -340,85 +825,923 @@ impl<'a> Context<'a> {
             }),
             // MSAA
             multisample: MultisampleState {
-                count: 1,
+                count: sample_count,
                 mask: !0,
                 alpha_to_coverage_enabled: false,
             },
             multiview: None,
-        });
-
-        Self {
-            device,
-            queue,
-            surface,
-            depth_texture,
-            config,
-            pipeline,
-            vertex_buffer,
-            index_buffer,
-            indices_length,
-            texture_bind_group,
-            texture,
-            camera,
-            camera_buffer,
-            camera_bind_group,
-            instances,
-            instance_buffer,
-        }
-    }
-
-    pub fn surface_format(&self) -> TextureFormat {
-        self.config.format
-    }
-
-    pub fn resize(&mut self, width: u32, height: u32) {
-        self.config.width = width.max(1);
-        self.config.height = height.max(1);
-        self.surface.configure(&self.device, &self.config);
-
-        self.depth_texture =
-            texture::Texture::create_depth_texture(&self.device, &self.config, "depth_texture");
-    }
-
-    fn input(&mut self, event: &WindowEvent) -> bool {
-        self.camera.input_move_camera(event, CAMERA_SPEED)
-    }
-
-    fn update(&mut self) {
-        let view_proj = self.camera.build_view_projection();
-
-        std::println!("View: {}", view_proj);
-
-        self.queue
-            .write_buffer(&self.camera_buffer, 0, cast_slice(&[view_proj]))
+        })
     }
 
-    fn render(&mut self) -> Result<(), SurfaceError> {
-        // Get surface texture
-        let output = self.surface.get_current_texture()?;
-        // Texture descriptor (metadata etc)
-        let view = output
-            .texture
-            .create_view(&TextureViewDescriptor::default());
-        // Encoder builds the command buffers
+    #[allow(clippy::too_many_arguments)]
+    fn create_camera_bind_group(
+        device: &Device,
+        layout: &BindGroupLayout,
+        camera_buffer: &Buffer,
+        lighting_buffer: &Buffer,
+        light_buffer: &Buffer,
+        shadow_buffer: &Buffer,
+        shadow_map: &texture::Texture,
+        point_shadow_buffer: &Buffer,
+        point_shadow_map: &texture::Texture,
+        ambient_occlusion: &texture::Texture,
+        cluster_buffer: &Buffer,
+        [cluster_counts, cluster_indices]: [&Buffer; 2],
+    ) -> BindGroup {
+        device.create_bind_group(&BindGroupDescriptor {
+            layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: camera_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: lighting_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: light_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 3,
+                    resource: shadow_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 4,
+                    resource: BindingResource::TextureView(&shadow_map.view),
+                },
+                BindGroupEntry {
+                    binding: 5,
+                    resource: BindingResource::Sampler(&shadow_map.sampler),
+                },
+                BindGroupEntry {
+                    binding: 6,
+                    resource: point_shadow_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 7,
+                    resource: BindingResource::TextureView(&point_shadow_map.view),
+                },
+                BindGroupEntry {
+                    binding: 8,
+                    resource: BindingResource::Sampler(&point_shadow_map.sampler),
+                },
+                BindGroupEntry {
+                    binding: 9,
+                    resource: BindingResource::TextureView(&ambient_occlusion.view),
+                },
+                BindGroupEntry {
+                    binding: 10,
+                    resource: BindingResource::Sampler(&ambient_occlusion.sampler),
+                },
+                BindGroupEntry {
+                    binding: 11,
+                    resource: cluster_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 12,
+                    resource: cluster_counts.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 13,
+                    resource: cluster_indices.as_entire_binding(),
+                },
+            ],
+            label: Some("camera_bind_group"),
+        })
+    }
+
+    /// Rebinds the camera group after one of its buffers, a shadow map, the occlusion
+    /// texture or the light clusters were replaced.
+    fn recreate_camera_bind_group(&mut self) {
+        let shadow_map = self
+            .shadow_map
+            .as_ref()
+            .map(ShadowMap::texture)
+            .unwrap_or(&self.shadow_placeholder);
+        let point_shadow_map = self
+            .point_shadow_map
+            .as_ref()
+            .map(PointShadowMap::texture)
+            .unwrap_or(&self.point_shadow_placeholder);
+        let ambient_occlusion = self
+            .ssao
+            .as_ref()
+            .map(Ssao::texture)
+            .unwrap_or(&self.ssao_placeholder);
+        let clusters = match &self.clusters {
+            Some(clusters) => [clusters.counts(), clusters.indices()],
+            None => [&self.cluster_placeholder; 2],
+        };
+
+        self.camera_bind_group = Self::create_camera_bind_group(
+            &self.device,
+            &self.camera_bind_group_layout,
+            &self.camera_buffer,
+            &self.lighting_buffer,
+            &self.light_buffer,
+            &self.shadow_buffer,
+            shadow_map,
+            &self.point_shadow_buffer,
+            point_shadow_map,
+            ambient_occlusion,
+            &self.cluster_buffer,
+            clusters,
+        );
+    }
+
+    pub fn surface_format(&self) -> TextureFormat {
+        self.config.format
+    }
+
+    pub fn camera(&self) -> &Camera {
+        &self.camera
+    }
+
+    /// Changes to the camera are uploaded on the next `update`.
+    pub fn camera_mut(&mut self) -> &mut Camera {
+        &mut self.camera
+    }
+
+    pub fn models(&self) -> &[Model] {
+        &self.models
+    }
+
+    /// Loads a Wavefront `.obj` file and adds it to the drawn models, returning its index.
+    pub fn load_obj(&mut self, path: impl AsRef<Path>) -> anyhow::Result<usize> {
+        let model = Model::load_obj_cached(
+            &self.device,
+            &self.queue,
+            &self.mipmaps,
+            &self.texture_bind_group_layout,
+            path,
+        )?;
+
+        Ok(self.add_model(model))
+    }
+
+    /// Imports a glTF scene as a single model, returning its index.
+    ///
+    /// The view switches to the scene's first perspective camera, if it has one. Use
+    /// [`Scene::load_gltf`] directly to keep the node hierarchy and every camera.
+    pub fn load_gltf(&mut self, path: impl AsRef<Path>) -> anyhow::Result<usize> {
+        let scene = Scene::load_gltf_cached(
+            &self.device,
+            &self.queue,
+            &self.mipmaps,
+            &self.texture_bind_group_layout,
+            path,
+            self.config.width as f32 / self.config.height as f32,
+        )?;
+
+        if let Some(camera) = scene.cameras.first() {
+            self.camera = *camera;
+        }
+
+        Ok(self.add_model(scene.model))
+    }
+
+    /// Every model is drawn once per instance.
+    pub fn add_model(&mut self, model: Model) -> usize {
+        self.models.push(model);
+        self.culled_view_projection = None;
+        self.rebuild_gpu_culling();
+        self.cull_instances();
+        self.models.len() - 1
+    }
+
+    /// Removes every model, including the built-in pentagon.
+    pub fn clear_models(&mut self) {
+        self.models.clear();
+        self.culled_view_projection = None;
+        self.rebuild_gpu_culling();
+        self.cull_instances();
+    }
+
+    pub fn adapter(&self) -> &Adapter {
+        &self.adapter
+    }
+
+    pub fn device(&self) -> &Device {
+        &self.device
+    }
+
+    pub fn queue(&self) -> &Queue {
+        &self.queue
+    }
+
+    /// Layout materials must be created with to be bound by the main pipeline.
+    pub fn material_layout(&self) -> &BindGroupLayout {
+        &self.texture_bind_group_layout
+    }
+
+    /// Every drawn instance, in draw order.
+    pub fn instances(&self) -> &[instance::Instance] {
+        self.instances.as_slice()
+    }
+
+    /// The handle of each of [`Context::instances`], in the same order.
+    pub fn instance_handles(&self) -> &[InstanceHandle] {
+        self.instances.handles()
+    }
+
+    /// Replaces every drawn instance, so handles to the old ones no longer find anything.
+    pub fn set_instances(&mut self, instances: Vec<instance::Instance>) {
+        self.instances.replace(instances);
+        self.culled_view_projection = None;
+        self.upload_instances();
+        self.cull_instances();
+    }
+
+    /// Adds an instance, drawn with every model from the next [`Context::update`] on.
+    pub fn spawn_instance(&mut self, instance: instance::Instance) -> InstanceHandle {
+        let handle = self.instances.spawn(instance);
+        self.culled_view_projection = None;
+        // Grown right away, so the buffers always have room for every instance
+        self.grow_instances();
+
+        handle
+    }
+
+    /// Removes an instance from the next [`Context::update`] on, or returns `None` if it was
+    /// already despawned. The last instance takes its place in [`Context::instances`].
+    pub fn despawn_instance(&mut self, handle: InstanceHandle) -> Option<instance::Instance> {
+        self.culled_view_projection = None;
+        self.instances.despawn(handle)
+    }
+
+    pub fn instance(&self, handle: InstanceHandle) -> Option<&instance::Instance> {
+        self.instances.get(handle)
+    }
+
+    /// Changes are uploaded on the next [`Context::update`], together with every other
+    /// instance changed since the last one.
+    pub fn instance_mut(&mut self, handle: InstanceHandle) -> Option<&mut instance::Instance> {
+        self.culled_view_projection = None;
+        self.instances.get_mut(handle)
+    }
+
+    /// Replaces the instance buffers with bigger ones if the instances no longer fit.
+    fn grow_instances(&mut self) {
+        if self.instances.grow(&self.device) {
+            self.visible_instance_buffer =
+                create_visible_instance_buffer(&self.device, self.instances.capacity());
+            self.culled_view_projection = None;
+            self.rebuild_gpu_culling();
+        }
+    }
+
+    /// Uploads the instances changed since the last upload.
+    fn upload_instances(&mut self) {
+        self.grow_instances();
+        self.instances.flush(&self.queue);
+    }
+
+    /// Skips instances whose bounds are outside the camera's frustum, checked on every
+    /// [`Context::update`]. On by default.
+    pub fn set_frustum_culling(&mut self, enabled: bool) {
+        self.frustum_culling = enabled;
+        self.culled_view_projection = None;
+        self.cull_instances();
+    }
+
+    pub fn frustum_culling(&self) -> bool {
+        self.frustum_culling
+    }
+
+    /// Moves frustum culling into a compute pass, which also writes the indirect draw
+    /// arguments of every mesh, or back to the CPU with `false`. Fails, leaving culling on the
+    /// CPU, on devices without compute shaders or indirect draws.
+    pub fn set_gpu_culling(&mut self, enabled: bool) -> anyhow::Result<()> {
+        let needed = DownlevelFlags::COMPUTE_SHADERS | DownlevelFlags::INDIRECT_EXECUTION;
+        let supported = self.adapter.get_downlevel_capabilities().flags;
+
+        if enabled && !supported.contains(needed) {
+            anyhow::bail!(
+                "GPU culling needs compute shaders and indirect draws, which the device lacks"
+            );
+        }
+
+        self.gpu_culling = enabled.then(|| GpuCulling::new(&self.device));
+        self.culled_view_projection = None;
+        self.rebuild_gpu_culling();
+        self.cull_instances();
+
+        Ok(())
+    }
+
+    pub fn gpu_culling(&self) -> bool {
+        self.gpu_culling.is_some()
+    }
+
+    /// How many instances the camera passes draw and skip since the last update. Always zero
+    /// while culling runs on the GPU, whose counts never come back to the CPU.
+    pub fn culling_stats(&self) -> CullingStats {
+        self.culling_stats
+    }
+
+    /// How many times culling on the CPU packed the visible instances and uploaded them. Frames
+    /// where neither the camera nor anything culled changed reuse the last upload.
+    #[doc(hidden)]
+    pub fn visible_instance_uploads(&self) -> usize {
+        self.visible_uploads
+    }
+
+    /// GPU culling when it is on and has anything to cull.
+    fn active_gpu_culling(&self) -> Option<&GpuCulling> {
+        self.gpu_culling
+            .as_ref()
+            .filter(|_| self.frustum_culling && !self.instances.is_empty())
+    }
+
+    /// How the camera passes draw the visible instances.
+    fn instance_draws(&self) -> InstanceDraws<'_> {
+        match self.active_gpu_culling() {
+            Some(gpu_culling) => InstanceDraws::Indirect(gpu_culling.draws()),
+            None => InstanceDraws::Direct(self.culling_stats.drawn as u32),
+        }
+    }
+
+    fn rebuild_gpu_culling(&mut self) {
+        if let Some(gpu_culling) = &mut self.gpu_culling {
+            gpu_culling.rebuild(
+                &self.device,
+                &self.models,
+                self.instances.buffer(),
+                &self.visible_instance_buffer,
+            );
+        }
+    }
+
+    /// Packs the instances the camera can see into the visible instance buffer.
+    ///
+    /// Every model is drawn with every instance, so each instance is tested with the bounds of
+    /// all models together. Skipped while the camera and everything culled stay the same.
+    fn cull_instances(&mut self) {
+        if let Some(gpu_culling) = self.active_gpu_culling() {
+            gpu_culling.update(
+                &self.queue,
+                &self.camera,
+                &self.models,
+                self.instances.len(),
+            );
+            self.culling_stats = CullingStats::default();
+            return;
+        }
+
+        let view_projection = self.camera.to_raw().view_projection;
+
+        if self.culled_view_projection == Some(view_projection) {
+            return;
+        }
+
+        let bounds = self
+            .models
+            .iter()
+            .flat_map(|model| &model.meshes)
+            .map(|mesh| mesh.bounds)
+            .fold(Aabb::EMPTY, Aabb::union);
+        let frustum = self.camera.frustum();
+
+        let visible = self
+            .instances
+            .as_slice()
+            .iter()
+            .filter(|instance| {
+                !self.frustum_culling || frustum.intersects(&instance.bounds(&bounds))
+            })
+            .map(instance::Instance::to_raw)
+            .collect::<Vec<_>>();
+
+        if !visible.is_empty() {
+            self.queue
+                .write_buffer(&self.visible_instance_buffer, 0, cast_slice(&visible));
+            self.visible_uploads += 1;
+        }
+
+        self.culled_view_projection = Some(view_projection);
+        self.culling_stats = CullingStats {
+            drawn: visible.len(),
+            culled: self.instances.len() - visible.len(),
+        };
+    }
+
+    /// Replaces the texture of meshes without a material of their own.
+    pub fn set_texture(&mut self, img: &image::DynamicImage, label: &str) -> anyhow::Result<()> {
+        let texture = texture::Texture::from_image_cached(
+            &self.device,
+            &self.queue,
+            &self.mipmaps,
+            img,
+            Some(label),
+            &texture::TextureOptions::default(),
+        )?;
+
+        self.default_material = Material::new(
+            &self.device,
+            &self.texture_bind_group_layout,
+            "default_material",
+            texture,
+        );
+
+        Ok(())
+    }
+
+    pub fn lights(&self) -> &[Light] {
+        &self.lights
+    }
+
+    /// Replaces every light, uploaded on the next `update`. Lower the ambient light when
+    /// adding lights, the default full ambient already shows textures at full brightness.
+    pub fn set_lights(&mut self, lights: Vec<Light>) {
+        // The buffer only has to grow, a smaller light count leaves the tail unread
+        let needed = (lights.len().max(1) * std::mem::size_of::<light::LightData>()) as u64;
+
+        if needed > self.light_buffer.size() {
+            self.light_buffer = create_light_buffer(&self.device, &lights);
+            self.recreate_camera_bind_group();
+
+            if let Some(clusters) = &mut self.clusters {
+                clusters.set_light_buffer(&self.device, &self.cluster_buffer, &self.light_buffer);
+            }
+        }
+
+        self.lights = lights;
+    }
+
+    pub fn ambient(&self) -> Vec3 {
+        self.ambient
+    }
+
+    /// Light reaching every surface from every direction, in linear RGB.
+    pub fn set_ambient(&mut self, ambient: Vec3) {
+        self.ambient = ambient;
+    }
+
+    /// Lets the first directional light cast shadows, or turns shadows off with `None`.
+    pub fn set_shadows(&mut self, settings: Option<ShadowSettings>) {
+        let backend = self.adapter.get_info().backend;
+        self.shadow_map = settings.map(|settings| ShadowMap::new(&self.device, backend, settings));
+        self.recreate_camera_bind_group();
+    }
+
+    pub fn shadows(&self) -> Option<&ShadowSettings> {
+        self.shadow_map.as_ref().map(ShadowMap::settings)
+    }
+
+    /// Lets the first point lights cast shadows in every direction, or turns point light
+    /// shadows off with `None`.
+    pub fn set_point_shadows(&mut self, settings: Option<PointShadowSettings>) {
+        let backend = self.adapter.get_info().backend;
+        self.point_shadow_map =
+            settings.map(|settings| PointShadowMap::new(&self.device, backend, settings));
+        self.recreate_camera_bind_group();
+    }
+
+    pub fn point_shadows(&self) -> Option<&PointShadowSettings> {
+        self.point_shadow_map.as_ref().map(PointShadowMap::settings)
+    }
+
+    /// Darkens ambient light in creases and where objects meet, from a depth prepass of the
+    /// scene, or turns ambient occlusion off with `None`.
+    pub fn set_ssao(&mut self, settings: Option<SsaoSettings>) {
+        self.ssao = settings.map(|settings| {
+            Ssao::new(
+                &self.device,
+                &self.queue,
+                &self.config,
+                &self.camera,
+                settings,
+            )
+        });
+        self.recreate_camera_bind_group();
+    }
+
+    pub fn ssao(&self) -> Option<&SsaoSettings> {
+        self.ssao.as_ref().map(Ssao::settings)
+    }
+
+    /// Bins lights into a grid of clusters over the camera's frustum each frame, so every
+    /// fragment only walks the lights that reach it, or walks every light again with `None`.
+    /// Fails, leaving the current setting, on devices without compute shaders.
+    pub fn set_clustered_lighting(
+        &mut self,
+        settings: Option<ClusterSettings>,
+    ) -> anyhow::Result<()> {
+        let compute = self
+            .adapter
+            .get_downlevel_capabilities()
+            .flags
+            .contains(DownlevelFlags::COMPUTE_SHADERS);
+
+        if settings.is_some() && !compute {
+            anyhow::bail!("clustered lighting needs compute shaders, which the device lacks");
+        }
+
+        self.clusters = settings.map(|settings| {
+            LightClusters::new(
+                &self.device,
+                settings,
+                &self.cluster_buffer,
+                &self.light_buffer,
+            )
+        });
+        self.recreate_camera_bind_group();
+
+        Ok(())
+    }
+
+    pub fn clustered_lighting(&self) -> Option<&ClusterSettings> {
+        self.clusters.as_ref().map(LightClusters::settings)
+    }
+
+    /// The light the shadow map is rendered for, with its index in the light buffer.
+    fn shadow_caster(&self) -> Option<(usize, Vec3)> {
+        self.lights
+            .iter()
+            .enumerate()
+            .find_map(|(index, light)| match light.kind {
+                LightKind::Directional { direction } => Some((index, direction)),
+                _ => None,
+            })
+    }
+
+    /// Draws `cubemap` behind the scene instead of the clear colour, following the camera's
+    /// rotation. Build one with [`texture::Texture::cube_from_faces`] or
+    /// [`texture::Texture::cube_from_equirectangular`].
+    pub fn set_skybox(&mut self, cubemap: texture::Texture) {
+        self.skybox = Some(skybox::Skybox::new(
+            &self.device,
+            texture::Texture::HDR_FORMAT,
+            self.sample_count,
+            cubemap,
+            &self.camera,
+        ));
+    }
+
+    /// Goes back to clearing the background to a flat colour.
+    pub fn clear_skybox(&mut self) {
+        self.skybox = None;
+    }
+
+    pub fn skybox(&self) -> Option<&texture::Texture> {
+        self.skybox.as_ref().map(skybox::Skybox::cubemap)
+    }
+
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.config.width = width.max(1);
+        self.config.height = height.max(1);
+
+        match &mut self.target {
+            RenderTarget::Surface(surface) => surface.configure(&self.device, &self.config),
+            RenderTarget::Offscreen(texture) => {
+                *texture = texture::Texture::create_render_target(
+                    &self.device,
+                    &self.config,
+                    "offscreen_target",
+                )
+            }
+        }
+
+        self.create_attachments();
+    }
+
+    /// (Re)creates the depth buffer, HDR, MSAA and ambient occlusion targets for the current
+    /// size and sample count.
+    fn create_attachments(&mut self) {
+        self.depth_texture = texture::Texture::create_depth_texture(
+            &self.device,
+            &self.config,
+            self.sample_count,
+            "depth_texture",
+        );
+
+        self.msaa_target = (self.sample_count > 1).then(|| {
+            texture::Texture::create_msaa_target(
+                &self.device,
+                &self.config,
+                self.sample_count,
+                "msaa_target",
+            )
+        });
+
+        self.hdr_target =
+            texture::Texture::create_hdr_target(&self.device, &self.config, "hdr_target");
+        self.post
+            .resize(&self.device, &self.queue, &self.config, &self.hdr_target);
+        self.tonemap
+            .set_sources(&self.device, &[&self.hdr_target, self.post.spare()]);
+
+        if let Some(deferred) = &mut self.deferred {
+            deferred.resize(&self.device, &self.config, &self.depth_texture);
+        }
+
+        if let Some(ssao) = &mut self.ssao {
+            ssao.resize(&self.device, &self.config);
+            self.recreate_camera_bind_group();
+        }
+    }
+
+    pub fn render_path(&self) -> RenderPath {
+        match self.deferred {
+            Some(_) => RenderPath::Deferred,
+            None => RenderPath::Forward,
+        }
+    }
+
+    pub fn sample_count(&self) -> u32 {
+        self.sample_count
+    }
+
+    /// Sample counts both the HDR target and the depth buffer can be created with. Only 1 on
+    /// the deferred path, whose G-buffer is never multisampled.
+    pub fn supported_sample_counts(&self) -> Vec<u32> {
+        if self.deferred.is_some() {
+            return vec![1];
+        }
+
+        let features = |format: TextureFormat| {
+            if self
+                .device
+                .features()
+                .contains(Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES)
+            {
+                self.adapter.get_texture_format_features(format)
+            } else {
+                format.guaranteed_format_features(self.device.features())
+            }
+        };
+        let colour = features(texture::Texture::HDR_FORMAT);
+        let depth = features(TextureFormat::Depth32Float);
+
+        [1, 2, 4, 8]
+            .into_iter()
+            .filter(|&count| {
+                colour.flags.sample_count_supported(count)
+                    && depth.flags.sample_count_supported(count)
+            })
+            .collect()
+    }
+
+    /// Switches multisample anti-aliasing to `sample_count` samples per pixel, 1 turns it
+    /// off. Fails, leaving the current setting, if the count is not supported.
+    pub fn set_sample_count(&mut self, sample_count: u32) -> anyhow::Result<()> {
+        let supported = self.supported_sample_counts();
+
+        if !supported.contains(&sample_count) {
+            anyhow::bail!(
+                "{sample_count}x MSAA is not supported with {:?} on the {:?} path, use one of \
+                 {supported:?}",
+                texture::Texture::HDR_FORMAT,
+                self.render_path()
+            );
+        }
+
+        self.sample_count = sample_count;
+        self.pipeline = Self::create_pipeline(
+            &self.device,
+            &self.pipeline_layout,
+            texture::Texture::HDR_FORMAT,
+            sample_count,
+        );
+
+        if let Some(skybox) = self.skybox.take() {
+            self.set_skybox(skybox.into_cubemap());
+        }
+
+        self.create_attachments();
+
+        Ok(())
+    }
+
+    pub fn tonemapping(&self) -> TonemapSettings {
+        self.tonemapping
+    }
+
+    /// Picks the curve and exposure the HDR scene is brought into the surface format with.
+    pub fn set_tonemapping(&mut self, settings: TonemapSettings) {
+        self.tonemapping = settings;
+    }
+
+    /// Adds a glow around everything brighter than the bloom threshold, or turns bloom off with
+    /// `None`. Runs on the HDR scene, before the post effects.
+    pub fn set_bloom(&mut self, settings: Option<BloomSettings>) {
+        self.post.bloom = settings
+            .map(|settings| Bloom::new(&self.device, &self.config, self.post.layout(), settings));
+    }
+
+    pub fn bloom(&self) -> Option<&BloomSettings> {
+        self.post.bloom.as_ref().map(Bloom::settings)
+    }
+
+    /// Layout of the input an effect reads, at set 0, see [`PostEffect::new`].
+    pub fn post_layout(&self) -> &BindGroupLayout {
+        self.post.layout()
+    }
+
+    pub fn post_effects(&self) -> &[PostEffect] {
+        &self.post.effects
+    }
+
+    /// The post-processing stack, run front to back. Effects can be reordered, removed or
+    /// switched off with [`PostEffect::enabled`] at any time.
+    pub fn post_effects_mut(&mut self) -> &mut Vec<PostEffect> {
+        &mut self.post.effects
+    }
+
+    /// Appends `effect` to the end of the post-processing stack and returns its index.
+    pub fn add_post_effect(&mut self, effect: PostEffect) -> usize {
+        self.post.effects.push(effect);
+        self.post.effects.len() - 1
+    }
+
+    fn input(&mut self, event: &WindowEvent) -> bool {
+        self.camera.input_move_camera(event, CAMERA_SPEED)
+    }
+
+    pub fn update(&mut self) {
+        let camera_data = self.camera.to_raw();
+
+        log::debug!("View: {}", camera_data.view_projection);
+
+        self.queue
+            .write_buffer(&self.camera_buffer, 0, cast_slice(&[camera_data]));
+
+        self.upload_instances();
+        self.cull_instances();
+
+        let light_data = self.lights.iter().map(Light::to_raw).collect::<Vec<_>>();
+        let lighting_data = LightingData {
+            ambient: self.ambient.extend(1.0),
+            light_count: self.lights.len() as u32,
+            ambient_occlusion: self.ssao.is_some() as u32,
+            clustered: self.clusters.is_some() as u32,
+            _padding: [0; 1],
+        };
+
+        self.queue
+            .write_buffer(&self.lighting_buffer, 0, cast_slice(&[lighting_data]));
+        self.queue
+            .write_buffer(&self.light_buffer, 0, cast_slice(&light_data));
+
+        let shadow_data = match (&self.shadow_map, self.shadow_caster()) {
+            (Some(shadow_map), Some((index, direction))) => {
+                shadow_map.update(&self.queue, &self.camera, index, direction)
+            }
+            _ => ShadowData::disabled(),
+        };
+
+        self.queue
+            .write_buffer(&self.shadow_buffer, 0, cast_slice(&[shadow_data]));
+
+        let point_shadow_data = match &self.point_shadow_map {
+            Some(point_shadow_map) => point_shadow_map.update(&self.queue, &self.lights),
+            None => PointShadowData::disabled(),
+        };
+
+        self.queue.write_buffer(
+            &self.point_shadow_buffer,
+            0,
+            cast_slice(&[point_shadow_data]),
+        );
+
+        if let Some(skybox) = &self.skybox {
+            skybox.update(&self.queue, &self.camera);
+        }
+
+        if let Some(ssao) = &self.ssao {
+            ssao.update(&self.queue, &self.camera);
+        }
+
+        let cluster_data = match &self.clusters {
+            Some(clusters) => clusters.update(
+                &self.camera,
+                self.config.width,
+                self.config.height,
+                self.lights.len(),
+            ),
+            None => ClusterData::disabled(),
+        };
+
+        self.queue
+            .write_buffer(&self.cluster_buffer, 0, cast_slice(&[cluster_data]));
+
+        if let Some(deferred) = &self.deferred {
+            deferred.update(&self.queue, &self.camera);
+        }
+
+        self.post.update(&self.queue);
+        self.tonemap.update(&self.queue, self.tonemapping);
+    }
+
+    pub fn render(&mut self) -> Result<(), SurfaceError> {
+        // Get surface texture (or the offscreen one) and its descriptor (metadata etc)
+        let (output, view) = match &self.target {
+            RenderTarget::Surface(surface) => {
+                let output = surface.get_current_texture()?;
+                let view = output
+                    .texture
+                    .create_view(&TextureViewDescriptor::default());
+
+                (Some(output), view)
+            }
+            RenderTarget::Offscreen(texture) => (
+                None,
+                texture
+                    .texture
+                    .create_view(&TextureViewDescriptor::default()),
+            ),
+        };
+        self.draw(&view);
+        // Show rendertarget on the surface
+        if let Some(output) = output {
+            output.present();
+        }
+
+        Ok(())
+    }
+
+    /// Records and submits the main pass into the HDR target, then post-processes and
+    /// tonemaps it into `view`.
+    fn draw(&self, view: &TextureView) {
+        // Encoder builds the command buffers
         let mut encoder = self
             .device
             .create_command_encoder(&CommandEncoderDescriptor {
                 label: Some("Render Encoder"),
             });
+
+        if let (Some(shadow_map), Some(_)) = (&self.shadow_map, self.shadow_caster()) {
+            if !self.instances.is_empty() {
+                shadow_map.render(
+                    &mut encoder,
+                    &self.models,
+                    self.instances.buffer(),
+                    self.instances.len() as _,
+                );
+            }
+        }
+
+        if let Some(point_shadow_map) = &self.point_shadow_map {
+            let casters = point_shadow_map.casters(&self.lights).len();
+
+            if casters > 0 && !self.instances.is_empty() {
+                point_shadow_map.render(
+                    &mut encoder,
+                    &self.models,
+                    self.instances.buffer(),
+                    self.instances.len() as _,
+                    casters,
+                );
+            }
+        }
+
+        if let Some(clusters) = &self.clusters {
+            clusters.dispatch(&mut encoder);
+        }
+
+        if let Some(gpu_culling) = self.active_gpu_culling() {
+            gpu_culling.dispatch(&mut encoder, self.instances.len());
+        }
+
+        if let Some(ssao) = &self.ssao {
+            ssao.render(
+                &mut encoder,
+                &self.models,
+                &self.visible_instance_buffer,
+                self.instance_draws(),
+            );
+        }
+
+        match &self.deferred {
+            None => self.draw_forward(&mut encoder),
+            Some(deferred) => self.draw_deferred(&mut encoder, deferred),
+        }
+
+        let output = self.post.render(&mut encoder, &self.hdr_target);
+        self.tonemap.render(&mut encoder, output, view);
+        // Submit the clear pass
+        self.queue.submit(once(encoder.finish()));
+    }
+
+    /// The main pass of the forward path, lighting every mesh as it is drawn.
+    fn draw_forward(&self, encoder: &mut CommandEncoder) {
+        // With MSAA every sample is rendered separately and averaged into the HDR target at
+        // the end
+        let hdr_view = &self.hdr_target.view;
+        let (colour_view, resolve_target) = match &self.msaa_target {
+            Some(msaa_target) => (&msaa_target.view, Some(hdr_view)),
+            None => (hdr_view, None),
+        };
         // Creates the clear pass (render pass == bucket o' drawing calls)
         let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
             label: Some("Clear Pass"),
             color_attachments: &[Some(RenderPassColorAttachment {
-                view: &view,
-                resolve_target: None,
+                view: colour_view,
+                resolve_target,
                 ops: Operations {
-                    load: LoadOp::Clear(Color {
-                        r: 0.1,
-                        g: 0.2,
-                        b: 0.3,
-                        a: 1.0,
-                    }),
+                    load: LoadOp::Clear(CLEAR_COLOUR),
                     store: StoreOp::Store,
                 },
             })],
@@ -434,24 +1757,126 @@ impl<'a> Context<'a> {
             occlusion_query_set: None,
         });
 
-        render_pass.set_pipeline(&self.pipeline);
-        render_pass.set_bind_group(0, &self.texture_bind_group, &[]);
-        render_pass.set_bind_group(1, &self.camera_bind_group, &[]);
-        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-        render_pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
-        render_pass.set_index_buffer(self.index_buffer.slice(..), IndexFormat::Uint16);
-        render_pass.draw_indexed(0..self.indices_length, 0, 0..self.instances.len() as _);
-        // Release the mutable borrow of the render pass
+        self.draw_models(&mut render_pass, &self.pipeline);
+        // Last, so only pixels the scene left empty pay for the sky
+        if let Some(skybox) = &self.skybox {
+            skybox.draw(&mut render_pass);
+        }
+    }
+
+    /// The geometry and lighting passes of the deferred path, then the skybox behind them.
+    fn draw_deferred(&self, encoder: &mut CommandEncoder, deferred: &Deferred) {
+        let mut render_pass = deferred.geometry_pass(encoder, &self.depth_texture);
+        self.draw_models(&mut render_pass, deferred.geometry_pipeline());
         drop(render_pass);
-        // Submit the clear pass
-        self.queue.submit(once(encoder.finish()));
-        // Show rendertarget on the surface
-        output.present();
+
+        deferred.render(
+            encoder,
+            &self.camera_bind_group,
+            &self.hdr_target.view,
+            CLEAR_COLOUR,
+        );
+
+        // Tested against the geometry pass's depth, like on the forward path
+        if let Some(skybox) = &self.skybox {
+            let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+                label: Some("Skybox Pass"),
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view: &self.hdr_target.view,
+                    resolve_target: None,
+                    ops: Operations {
+                        load: LoadOp::Load,
+                        store: StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
+                    view: &self.depth_texture.view,
+                    depth_ops: Some(Operations {
+                        load: LoadOp::Load,
+                        store: StoreOp::Store,
+                    }),
+                    stencil_ops: None,
+                }),
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+
+            skybox.draw(&mut render_pass);
+        }
+    }
+
+    /// Draws every visible instance of every model with `pipeline`, binding each mesh's
+    /// material.
+    fn draw_models<'p>(&'p self, render_pass: &mut RenderPass<'p>, pipeline: &'p RenderPipeline) {
+        let draws = self.instance_draws();
+        // Empty buffers cannot be bound, so an empty scene is just the clear
+        if draws.is_empty() {
+            return;
+        }
+
+        render_pass.set_pipeline(pipeline);
+        render_pass.set_bind_group(1, &self.camera_bind_group, &[]);
+        render_pass.set_vertex_buffer(1, self.visible_instance_buffer.slice(..));
+
+        let meshes = self
+            .models
+            .iter()
+            .flat_map(|model| model.meshes.iter().map(move |mesh| (model, mesh)));
+
+        for (index, (model, mesh)) in meshes
+            .enumerate()
+            .filter(|(_, (_, mesh))| mesh.num_elements > 0)
+        {
+            let material = mesh
+                .material
+                .and_then(|index| model.materials.get(index))
+                .unwrap_or(&self.default_material);
+
+            render_pass.set_bind_group(0, &material.bind_group, &[]);
+            render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+            render_pass.set_index_buffer(mesh.index_buffer.slice(..), IndexFormat::Uint32);
+            draws.draw(render_pass, index, mesh);
+        }
+    }
+
+    /// Renders the current scene and reads it back as an RGBA image.
+    ///
+    /// Headless contexts read their offscreen target directly; windowed contexts render into a
+    /// temporary texture of the surface format, since swapchain textures cannot be copied from.
+    pub fn capture_frame(&self) -> anyhow::Result<image::RgbaImage> {
+        match &self.target {
+            RenderTarget::Offscreen(texture) => {
+                self.draw(&texture.view);
+
+                capture::read_texture(&self.device, &self.queue, &texture.texture)
+            }
+            RenderTarget::Surface(_) => {
+                let texture = texture::Texture::create_render_target(
+                    &self.device,
+                    &self.config,
+                    "capture_target",
+                );
+
+                self.draw(&texture.view);
+
+                capture::read_texture(&self.device, &self.queue, &texture.texture)
+            }
+        }
+    }
+
+    /// Renders the current scene and writes it to `path` as a PNG.
+    pub fn save_frame(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        self.capture_frame()?
+            .save_with_format(path, image::ImageFormat::Png)?;
+
+        log::info!("saved frame to {}", path.display());
 
         Ok(())
     }
 }
 
+#[allow(clippy::single_match)]
 pub async fn run() {
     env_logger::init();
 
@@ -468,7 +1893,7 @@ pub async fn run() {
                         WindowEvent::Resized(size) => context.resize(size.width, size.height),
                         WindowEvent::CloseRequested => elwt.exit(),
                         WindowEvent::RedrawRequested => {
-                            std::println!("Update");
+                            log::debug!("Update");
 
                             context.update();
 
@@ -490,6 +1915,27 @@ pub async fn run() {
                                 },
                             ..
                         } => elwt.exit(),
+                        WindowEvent::KeyboardInput {
+                            event:
+                                KeyEvent {
+                                    physical_key: PhysicalKey::Code(KeyCode::F12),
+                                    state: ElementState::Pressed,
+                                    repeat: false,
+                                    ..
+                                },
+                            ..
+                        } => {
+                            let timestamp = SystemTime::now()
+                                .duration_since(SystemTime::UNIX_EPOCH)
+                                .map(|duration| duration.as_millis())
+                                .unwrap_or_default();
+
+                            if let Err(e) =
+                                context.save_frame(format!("screenshot-{timestamp}.png"))
+                            {
+                                eprintln!("{:?}", e);
+                            }
+                        }
                         _ => {}
                     }
                 } else {
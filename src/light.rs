@@ -0,0 +1,179 @@
+/// Distance falloff of point and spot lights, `1 / (constant + linear * d + quadratic * d²)`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Attenuation {
+    pub constant: f32,
+    pub linear: f32,
+    pub quadratic: f32,
+}
+
+impl Default for Attenuation {
+    /// Falls off to about 5% at 20 units.
+    fn default() -> Self {
+        Self {
+            constant: 1.0,
+            linear: 0.14,
+            quadratic: 0.07,
+        }
+    }
+}
+
+impl Attenuation {
+    /// No falloff at all, used by directional lights.
+    pub const NONE: Self = Self {
+        constant: 1.0,
+        linear: 0.0,
+        quadratic: 0.0,
+    };
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LightKind {
+    /// Infinitely far away, like the sun. `direction` is the way the light travels.
+    Directional {
+        direction: glam::Vec3,
+    },
+    Point {
+        position: glam::Vec3,
+    },
+    /// Full intensity inside `inner_angle`, fading to nothing at `outer_angle`. Both are half
+    /// angles in degrees, measured from `direction`.
+    Spot {
+        position: glam::Vec3,
+        direction: glam::Vec3,
+        inner_angle: f32,
+        outer_angle: f32,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Light {
+    pub kind: LightKind,
+    /// Linear RGB.
+    pub colour: glam::Vec3,
+    pub intensity: f32,
+    /// Ignored by directional lights.
+    pub attenuation: Attenuation,
+}
+
+impl Light {
+    pub fn directional(direction: glam::Vec3, colour: glam::Vec3, intensity: f32) -> Self {
+        Self {
+            kind: LightKind::Directional { direction },
+            colour,
+            intensity,
+            attenuation: Attenuation::NONE,
+        }
+    }
+
+    pub fn point(position: glam::Vec3, colour: glam::Vec3, intensity: f32) -> Self {
+        Self {
+            kind: LightKind::Point { position },
+            colour,
+            intensity,
+            attenuation: Attenuation::default(),
+        }
+    }
+
+    pub fn spot(
+        position: glam::Vec3,
+        direction: glam::Vec3,
+        inner_angle: f32,
+        outer_angle: f32,
+        colour: glam::Vec3,
+        intensity: f32,
+    ) -> Self {
+        Self {
+            kind: LightKind::Spot {
+                position,
+                direction,
+                inner_angle,
+                outer_angle,
+            },
+            colour,
+            intensity,
+            attenuation: Attenuation::default(),
+        }
+    }
+
+    pub fn to_raw(&self) -> LightData {
+        let attenuation = glam::vec3(
+            self.attenuation.constant,
+            self.attenuation.linear,
+            self.attenuation.quadratic,
+        );
+        let colour = (self.colour * self.intensity).extend(0.0);
+
+        match self.kind {
+            LightKind::Directional { direction } => LightData {
+                position: glam::Vec3::ZERO.extend(LightData::DIRECTIONAL),
+                direction: direction.normalize_or_zero().extend(0.0),
+                colour,
+                attenuation: glam::Vec4::new(1.0, 0.0, 0.0, 0.0),
+            },
+            LightKind::Point { position } => LightData {
+                position: position.extend(LightData::POINT),
+                direction: glam::Vec4::ZERO,
+                colour,
+                attenuation: attenuation.extend(0.0),
+            },
+            LightKind::Spot {
+                position,
+                direction,
+                inner_angle,
+                outer_angle,
+            } => {
+                // Keep the fade well defined when both cones are the same
+                let outer_angle = outer_angle.max(inner_angle + 0.01);
+
+                LightData {
+                    position: position.extend(LightData::SPOT),
+                    direction: direction
+                        .normalize_or_zero()
+                        .extend(outer_angle.to_radians().cos()),
+                    colour,
+                    attenuation: attenuation.extend(inner_angle.to_radians().cos()),
+                }
+            }
+        }
+    }
+}
+
+/// A light as laid out in the storage buffer read by the fragment shader.
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct LightData {
+    /// `w` holds the kind of light.
+    pub position: glam::Vec4,
+    /// `w` holds the cosine of a spot light's outer cone.
+    pub direction: glam::Vec4,
+    /// Colour premultiplied by intensity.
+    pub colour: glam::Vec4,
+    /// Constant, linear and quadratic falloff, `w` holds the cosine of a spot light's inner
+    /// cone.
+    pub attenuation: glam::Vec4,
+}
+
+unsafe impl bytemuck::Pod for LightData {}
+unsafe impl bytemuck::Zeroable for LightData {}
+
+impl LightData {
+    pub const DIRECTIONAL: f32 = 0.0;
+    pub const POINT: f32 = 1.0;
+    pub const SPOT: f32 = 2.0;
+}
+
+/// Scene-wide lighting parameters, the uniform that goes with the light buffer.
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct LightingData {
+    pub ambient: glam::Vec4,
+    pub light_count: u32,
+    /// 1 when the screen-space ambient occlusion texture is bound.
+    pub ambient_occlusion: u32,
+    /// 1 when each fragment walks only the lights binned into its cluster.
+    pub clustered: u32,
+    pub _padding: [u32; 1],
+}
+
+unsafe impl bytemuck::Pod for LightingData {}
+unsafe impl bytemuck::Zeroable for LightingData {}
@@ -0,0 +1,168 @@
+use std::{
+    collections::HashMap,
+    hash::BuildHasherDefault,
+    sync::{Mutex, OnceLock},
+};
+
+/// Number of levels in a full mip chain down to 1x1.
+pub fn level_count(width: u32, height: u32) -> u32 {
+    u32::BITS - width.max(height).max(1).leading_zeros()
+}
+
+/// Fills mip levels `1..` of 2D textures by repeatedly blitting each level into the next one
+/// with a linear filter.
+///
+/// The blit pipeline of each format is built on first use and kept, so a context shares one
+/// generator between every texture it uploads. Creating one builds nothing yet.
+#[derive(Default)]
+pub(crate) struct MipmapGenerator {
+    pipelines: Mutex<HashMap<wgpu::TextureFormat, wgpu::RenderPipeline>>,
+    sampler: OnceLock<wgpu::Sampler>,
+}
+
+impl MipmapGenerator {
+    /// Fills the mip chain of every array layer (or cube face) of `texture` from its level 0.
+    ///
+    /// The texture needs `TEXTURE_BINDING` and `RENDER_ATTACHMENT` usage and a filterable
+    /// format.
+    pub fn generate(&self, device: &wgpu::Device, queue: &wgpu::Queue, texture: &wgpu::Texture) {
+        let mip_count = texture.mip_level_count();
+
+        if mip_count <= 1 {
+            return;
+        }
+
+        let mut pipelines = self.pipelines.lock().unwrap();
+        let pipeline = pipelines
+            .entry(texture.format())
+            .or_insert_with(|| blit_pipeline(device, texture.format()));
+
+        let sampler = self.sampler.get_or_init(|| {
+            device.create_sampler(&wgpu::SamplerDescriptor {
+                label: Some("Mipmap Sampler"),
+                address_mode_u: wgpu::AddressMode::ClampToEdge,
+                address_mode_v: wgpu::AddressMode::ClampToEdge,
+                address_mode_w: wgpu::AddressMode::ClampToEdge,
+                mag_filter: wgpu::FilterMode::Linear,
+                min_filter: wgpu::FilterMode::Linear,
+                mipmap_filter: wgpu::FilterMode::Nearest,
+                ..Default::default()
+            })
+        });
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Mipmap Encoder"),
+        });
+
+        for layer in 0..texture.depth_or_array_layers() {
+            let views = (0..mip_count)
+                .map(|level| {
+                    texture.create_view(&wgpu::TextureViewDescriptor {
+                        label: Some("Mipmap View"),
+                        dimension: Some(wgpu::TextureViewDimension::D2),
+                        base_mip_level: level,
+                        mip_level_count: Some(1),
+                        base_array_layer: layer,
+                        array_layer_count: Some(1),
+                        ..Default::default()
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            blit_chain(device, &mut encoder, pipeline, sampler, &views);
+        }
+
+        queue.submit(std::iter::once(encoder.finish()));
+    }
+}
+
+/// Blits every view in `views` into the one after it.
+fn blit_chain(
+    device: &wgpu::Device,
+    encoder: &mut wgpu::CommandEncoder,
+    pipeline: &wgpu::RenderPipeline,
+    sampler: &wgpu::Sampler,
+    views: &[wgpu::TextureView],
+) {
+    let layout = pipeline.get_bind_group_layout(0);
+
+    for pair in views.windows(2) {
+        let [source, target] = pair else {
+            unreachable!()
+        };
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Mipmap Bind Group"),
+            layout: &layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(source),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+            ],
+        });
+
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Mipmap Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+
+        pass.set_pipeline(pipeline);
+        pass.set_bind_group(0, &bind_group, &[]);
+        pass.draw(0..3, 0..1);
+    }
+}
+
+fn blit_pipeline(device: &wgpu::Device, format: wgpu::TextureFormat) -> wgpu::RenderPipeline {
+    let vertex_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("Fullscreen Shader"),
+        source: wgpu::ShaderSource::Glsl {
+            shader: include_str!("resources/shaders/fullscreen.vert").into(),
+            stage: wgpu::naga::ShaderStage::Vertex,
+            defines: HashMap::with_hasher(BuildHasherDefault::default()),
+        },
+    });
+
+    let fragment_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("Blit Shader"),
+        source: wgpu::ShaderSource::Glsl {
+            shader: include_str!("resources/shaders/blit.frag").into(),
+            stage: wgpu::naga::ShaderStage::Fragment,
+            defines: HashMap::with_hasher(BuildHasherDefault::default()),
+        },
+    });
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Mipmap Pipeline"),
+        // Derived from the shaders, the bind group layout is read back from the pipeline
+        layout: None,
+        vertex: wgpu::VertexState {
+            module: &vertex_shader,
+            entry_point: "main",
+            buffers: &[],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &fragment_shader,
+            entry_point: "main",
+            targets: &[Some(format.into())],
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+    })
+}
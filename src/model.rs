@@ -0,0 +1,483 @@
+use std::path::Path;
+
+use anyhow::Context as _;
+use wgpu::util::DeviceExt;
+
+use crate::{culling::Aabb, mipmap, texture};
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct Vertex {
+    pub position: glam::Vec3,
+    pub tex_coords: glam::Vec2,
+    pub normal: glam::Vec3,
+}
+
+unsafe impl bytemuck::Pod for Vertex {}
+unsafe impl bytemuck::Zeroable for Vertex {}
+
+impl Vertex {
+    // Locations 2..=6 are taken by the per-instance data
+    const ATTRIBS: [wgpu::VertexAttribute; 3] =
+        wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x2, 7 => Float32x3];
+
+    pub fn descriptor() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBS,
+        }
+    }
+}
+
+/// Which lighting model a material's surface uses.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ShadingModel {
+    /// Diffuse texture with a fixed specular highlight, for simple assets such as OBJ files.
+    BlinnPhong,
+    /// Cook-Torrance with GGX, matching the glTF metallic-roughness model.
+    MetallicRoughness,
+}
+
+/// Scalar inputs of a material, multiplied with the matching maps.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MaterialFactors {
+    /// Linear RGBA.
+    pub base_colour: glam::Vec4,
+    pub metallic: f32,
+    pub roughness: f32,
+    /// Scales the X and Y of the normal map.
+    pub normal_scale: f32,
+    /// 0 ignores the occlusion map, 1 applies it fully.
+    pub occlusion_strength: f32,
+    /// Linear RGB.
+    pub emissive: glam::Vec3,
+}
+
+impl Default for MaterialFactors {
+    /// An untinted dielectric with medium roughness.
+    fn default() -> Self {
+        Self {
+            base_colour: glam::Vec4::ONE,
+            metallic: 0.0,
+            roughness: 0.5,
+            normal_scale: 1.0,
+            occlusion_strength: 1.0,
+            emissive: glam::Vec3::ZERO,
+        }
+    }
+}
+
+/// The maps of a material. Only the base colour is required, missing maps fall back to the
+/// factors alone.
+pub struct MaterialTextures {
+    /// sRGB.
+    pub base_colour: texture::Texture,
+    /// Linear, roughness in green and metallic in blue.
+    pub metallic_roughness: Option<texture::Texture>,
+    /// Linear, tangent space with +Y up.
+    pub normal: Option<texture::Texture>,
+    /// Linear, occlusion in red.
+    pub occlusion: Option<texture::Texture>,
+    /// sRGB.
+    pub emissive: Option<texture::Texture>,
+}
+
+impl MaterialTextures {
+    pub fn new(base_colour: texture::Texture) -> Self {
+        Self {
+            base_colour,
+            metallic_roughness: None,
+            normal: None,
+            occlusion: None,
+            emissive: None,
+        }
+    }
+}
+
+/// A material as laid out in its uniform buffer.
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct MaterialData {
+    pub base_colour: glam::Vec4,
+    pub emissive: glam::Vec4,
+    pub metallic: f32,
+    pub roughness: f32,
+    pub normal_scale: f32,
+    pub occlusion_strength: f32,
+    pub shading: u32,
+    /// Which optional maps are bound, see the `HAS_*` flags.
+    pub maps: u32,
+    pub _padding: [u32; 2],
+}
+
+unsafe impl bytemuck::Pod for MaterialData {}
+unsafe impl bytemuck::Zeroable for MaterialData {}
+
+impl MaterialData {
+    pub const BLINN_PHONG: u32 = 0;
+    pub const METALLIC_ROUGHNESS: u32 = 1;
+
+    pub const HAS_METALLIC_ROUGHNESS: u32 = 1 << 0;
+    pub const HAS_NORMAL: u32 = 1 << 1;
+    pub const HAS_OCCLUSION: u32 = 1 << 2;
+    pub const HAS_EMISSIVE: u32 = 1 << 3;
+}
+
+pub struct Material {
+    pub name: String,
+    pub shading: ShadingModel,
+    pub factors: MaterialFactors,
+    pub textures: MaterialTextures,
+    pub bind_group: wgpu::BindGroup,
+}
+
+impl Material {
+    /// Blinn-Phong material showing `diffuse_texture`.
+    ///
+    /// `layout` is the material bind group layout of the main pipeline, see
+    /// [`Material::create_bind_group_layout`].
+    pub fn new(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        name: &str,
+        diffuse_texture: texture::Texture,
+    ) -> Self {
+        Self::with_shading(
+            device,
+            layout,
+            name,
+            ShadingModel::BlinnPhong,
+            MaterialTextures::new(diffuse_texture),
+            MaterialFactors::default(),
+        )
+    }
+
+    /// Metallic-roughness material, shaded with Cook-Torrance.
+    pub fn pbr(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        name: &str,
+        textures: MaterialTextures,
+        factors: MaterialFactors,
+    ) -> Self {
+        Self::with_shading(
+            device,
+            layout,
+            name,
+            ShadingModel::MetallicRoughness,
+            textures,
+            factors,
+        )
+    }
+
+    pub fn with_shading(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        name: &str,
+        shading: ShadingModel,
+        textures: MaterialTextures,
+        factors: MaterialFactors,
+    ) -> Self {
+        let maps = [
+            (
+                &textures.metallic_roughness,
+                MaterialData::HAS_METALLIC_ROUGHNESS,
+            ),
+            (&textures.normal, MaterialData::HAS_NORMAL),
+            (&textures.occlusion, MaterialData::HAS_OCCLUSION),
+            (&textures.emissive, MaterialData::HAS_EMISSIVE),
+        ]
+        .iter()
+        .filter(|(texture, _)| texture.is_some())
+        .fold(0, |maps, (_, flag)| maps | flag);
+
+        let data = MaterialData {
+            base_colour: factors.base_colour,
+            emissive: factors.emissive.extend(0.0),
+            metallic: factors.metallic,
+            roughness: factors.roughness,
+            normal_scale: factors.normal_scale,
+            occlusion_strength: factors.occlusion_strength,
+            shading: match shading {
+                ShadingModel::BlinnPhong => MaterialData::BLINN_PHONG,
+                ShadingModel::MetallicRoughness => MaterialData::METALLIC_ROUGHNESS,
+            },
+            maps,
+            _padding: [0; 2],
+        };
+
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(&format!("{name} Material Buffer")),
+            contents: bytemuck::cast_slice(&[data]),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+
+        // Every slot needs something bound, missing maps reuse the base colour and are
+        // skipped by the shader
+        let slots = [
+            Some(&textures.base_colour),
+            textures.metallic_roughness.as_ref(),
+            textures.normal.as_ref(),
+            textures.occlusion.as_ref(),
+            textures.emissive.as_ref(),
+        ]
+        .map(|texture| texture.unwrap_or(&textures.base_colour));
+
+        let mut entries = slots
+            .iter()
+            .enumerate()
+            .flat_map(|(slot, texture)| {
+                [
+                    wgpu::BindGroupEntry {
+                        binding: 2 * slot as u32,
+                        resource: wgpu::BindingResource::TextureView(&texture.view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2 * slot as u32 + 1,
+                        resource: wgpu::BindingResource::Sampler(&texture.sampler),
+                    },
+                ]
+            })
+            .collect::<Vec<_>>();
+        entries.push(wgpu::BindGroupEntry {
+            binding: Self::FACTORS_BINDING,
+            resource: buffer.as_entire_binding(),
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some(name),
+            layout,
+            entries: &entries,
+        });
+
+        Self {
+            name: name.to_owned(),
+            shading,
+            factors,
+            textures,
+            bind_group,
+        }
+    }
+
+    /// Base colour, metallic-roughness, normal, occlusion and emissive maps each take a texture
+    /// and sampler binding, in that order.
+    const MAPS: u32 = 5;
+    const FACTORS_BINDING: u32 = 2 * Self::MAPS;
+
+    /// Layout of [`Material::bind_group`]: a texture and sampler per map followed by the
+    /// factors uniform.
+    pub fn create_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        let mut entries = (0..Self::MAPS)
+            .flat_map(|slot| {
+                [
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2 * slot,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2 * slot + 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                ]
+            })
+            .collect::<Vec<_>>();
+        entries.push(wgpu::BindGroupLayoutEntry {
+            binding: Self::FACTORS_BINDING,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        });
+
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("material_bind_group_layout"),
+            entries: &entries,
+        })
+    }
+}
+
+pub struct Mesh {
+    pub name: String,
+    pub vertex_buffer: wgpu::Buffer,
+    pub index_buffer: wgpu::Buffer,
+    pub num_elements: u32,
+    /// Index into the owning model's materials, `None` uses the context's default material.
+    pub material: Option<usize>,
+    /// Object space bounds of the vertices, for culling.
+    pub bounds: Aabb,
+}
+
+impl Mesh {
+    pub fn new(
+        device: &wgpu::Device,
+        name: &str,
+        vertices: &[Vertex],
+        indices: &[u32],
+        material: Option<usize>,
+    ) -> Self {
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(&format!("{name} Vertex Buffer")),
+            contents: bytemuck::cast_slice(vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(&format!("{name} Index Buffer")),
+            contents: bytemuck::cast_slice(indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        Self {
+            name: name.to_owned(),
+            vertex_buffer,
+            index_buffer,
+            num_elements: indices.len() as u32,
+            material,
+            bounds: Aabb::from_points(vertices.iter().map(|vertex| vertex.position)),
+        }
+    }
+}
+
+pub struct Model {
+    pub meshes: Vec<Mesh>,
+    pub materials: Vec<Material>,
+}
+
+impl Model {
+    /// Loads a Wavefront `.obj` file and the `.mtl` libraries it references.
+    ///
+    /// Every object/group becomes its own mesh. Diffuse textures are resolved relative to the
+    /// `.obj` file; materials without one use their diffuse colour instead. Missing normals are
+    /// generated by averaging the face normals around each vertex.
+    pub fn load_obj(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        layout: &wgpu::BindGroupLayout,
+        path: impl AsRef<Path>,
+    ) -> anyhow::Result<Self> {
+        let mipmaps = mipmap::MipmapGenerator::default();
+        Self::load_obj_cached(device, queue, &mipmaps, layout, path)
+    }
+
+    /// Like [`Model::load_obj`], blitting mip chains with the pipelines `mipmaps` already
+    /// built.
+    pub(crate) fn load_obj_cached(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        mipmaps: &mipmap::MipmapGenerator,
+        layout: &wgpu::BindGroupLayout,
+        path: impl AsRef<Path>,
+    ) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let dir = path.parent().unwrap_or_else(|| Path::new(""));
+
+        let (obj_models, obj_materials) = tobj::load_obj(path, &tobj::GPU_LOAD_OPTIONS)
+            .with_context(|| format!("failed to load {}", path.display()))?;
+
+        let obj_materials = obj_materials.unwrap_or_else(|e| {
+            log::warn!("no materials for {}: {e}", path.display());
+            Vec::new()
+        });
+
+        let materials = obj_materials
+            .into_iter()
+            .map(|material| {
+                let texture = match &material.diffuse_texture {
+                    Some(file) => texture::Texture::from_file_cached(
+                        device,
+                        queue,
+                        mipmaps,
+                        &dir.join(file).to_string_lossy(),
+                        file,
+                        &texture::TextureOptions::default(),
+                    )?,
+                    None => {
+                        let [r, g, b] = material.diffuse.unwrap_or([1.0; 3]);
+                        let alpha = material.dissolve.unwrap_or(1.0);
+                        let to_u8 = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+                        let img = image::RgbaImage::from_pixel(
+                            1,
+                            1,
+                            image::Rgba([to_u8(r), to_u8(g), to_u8(b), to_u8(alpha)]),
+                        );
+
+                        // MTL colours are already linear, an sRGB texture would darken them
+                        texture::Texture::from_image_cached(
+                            device,
+                            queue,
+                            mipmaps,
+                            &image::DynamicImage::ImageRgba8(img),
+                            Some(&material.name),
+                            &texture::TextureOptions {
+                                srgb: false,
+                                ..Default::default()
+                            },
+                        )?
+                    }
+                };
+
+                Ok(Material::new(device, layout, &material.name, texture))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let meshes = obj_models
+            .into_iter()
+            .map(|model| {
+                let mesh = &model.mesh;
+                let mut vertices = (0..mesh.positions.len() / 3)
+                    .map(|i| Vertex {
+                        position: glam::Vec3::from_slice(&mesh.positions[i * 3..i * 3 + 3]),
+                        // OBJ has its UV origin in the bottom left, wgpu in the top left
+                        tex_coords: mesh
+                            .texcoords
+                            .get(i * 2..i * 2 + 2)
+                            .map_or(glam::Vec2::ZERO, |uv| glam::vec2(uv[0], 1.0 - uv[1])),
+                        normal: mesh
+                            .normals
+                            .get(i * 3..i * 3 + 3)
+                            .map_or(glam::Vec3::ZERO, glam::Vec3::from_slice),
+                    })
+                    .collect::<Vec<_>>();
+
+                if mesh.normals.is_empty() {
+                    generate_normals(&mut vertices, &mesh.indices);
+                }
+
+                let material = mesh.material_id.filter(|&id| id < materials.len());
+
+                Mesh::new(device, &model.name, &vertices, &mesh.indices, material)
+            })
+            .collect();
+
+        Ok(Self { meshes, materials })
+    }
+}
+
+pub(crate) fn generate_normals(vertices: &mut [Vertex], indices: &[u32]) {
+    for triangle in indices.chunks_exact(3) {
+        let [a, b, c] = [0, 1, 2].map(|i| triangle[i] as usize);
+        // Area weighted, the cross product is left unnormalised on purpose
+        let normal = (vertices[b].position - vertices[a].position)
+            .cross(vertices[c].position - vertices[a].position);
+
+        for i in [a, b, c] {
+            vertices[i].normal += normal;
+        }
+    }
+
+    for vertex in vertices {
+        vertex.normal = vertex.normal.normalize_or_zero();
+    }
+}
@@ -0,0 +1,321 @@
+use std::{collections::HashMap, hash::BuildHasherDefault};
+
+use crate::{bloom::Bloom, texture::Texture};
+
+/// A full-screen pass of the post-processing stack, see [`crate::Context::add_post_effect`].
+///
+/// Effects run in HDR, after the main pass and before tonemapping, each reading the output of
+/// the one before it. The fragment shader is GLSL with this interface:
+///
+/// ```glsl
+/// layout(location = 0) in vec2 texCoords;
+///
+/// layout(set = 0, binding = 0) uniform texture2D t_input;
+/// layout(set = 0, binding = 1) uniform sampler s_input;
+///
+/// layout(set = 1, binding = 0) uniform Parameters { ... };
+///
+/// layout(location = 0) out vec4 outColor;
+/// ```
+pub struct PostEffect {
+    name: String,
+    /// Disabled effects are skipped, their input is passed on untouched.
+    pub enabled: bool,
+    pipeline: wgpu::RenderPipeline,
+    parameters: Vec<u8>,
+    buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+}
+
+impl PostEffect {
+    /// Compiles `shader` against `layout`, the input layout from
+    /// [`crate::Context::post_layout`]. `parameters` fixes the size of the uniform at set 1,
+    /// which later [`PostEffect::set_parameters`] calls have to keep.
+    pub fn new(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        name: &str,
+        shader: &str,
+        parameters: &impl bytemuck::Pod,
+    ) -> Self {
+        let parameters = bytemuck::bytes_of(parameters).to_vec();
+
+        let parameters_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Post Effect Parameters Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+
+        // Uniform buffers are read in 16 byte rows, and cannot be empty
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(name),
+            size: (parameters.len().max(1) as wgpu::BufferAddress).next_multiple_of(16),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some(name),
+            layout: &parameters_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            }],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some(name),
+            bind_group_layouts: &[layout, &parameters_layout],
+            push_constant_ranges: &[],
+        });
+
+        Self {
+            name: name.to_owned(),
+            enabled: true,
+            pipeline: effect_pipeline(device, &pipeline_layout, name, shader),
+            parameters,
+            buffer,
+            bind_group,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Replaces the uniform contents, uploaded on the next [`crate::Context::update`].
+    ///
+    /// # Panics
+    ///
+    /// If `parameters` is not the size the effect was created with.
+    pub fn set_parameters(&mut self, parameters: &impl bytemuck::Pod) {
+        let parameters = bytemuck::bytes_of(parameters);
+
+        assert_eq!(
+            parameters.len(),
+            self.parameters.len(),
+            "parameters of {} changed size",
+            self.name
+        );
+
+        self.parameters.copy_from_slice(parameters);
+    }
+
+    fn update(&self, queue: &wgpu::Queue) {
+        if !self.parameters.is_empty() {
+            queue.write_buffer(&self.buffer, 0, &self.parameters);
+        }
+    }
+
+    fn render(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        input: &wgpu::BindGroup,
+        view: &wgpu::TextureView,
+    ) {
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some(&self.name),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, input, &[]);
+        pass.set_bind_group(1, &self.bind_group, &[]);
+        pass.draw(0..3, 0..1);
+    }
+}
+
+/// The post effects and the two targets they ping-pong between: the HDR target the scene was
+/// rendered into and a spare one of the same size. Bloom, when on, runs before the effects.
+pub(crate) struct PostStack {
+    layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    spare: Texture,
+    /// Reads the HDR target and the spare target, in that order.
+    inputs: [wgpu::BindGroup; 2],
+    pub bloom: Option<Bloom>,
+    pub effects: Vec<PostEffect>,
+}
+
+impl PostStack {
+    pub fn new(device: &wgpu::Device, config: &wgpu::SurfaceConfiguration, hdr: &Texture) -> Self {
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Post Input Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        // Effects such as blurs read between texels, so unlike tonemapping they filter
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Post Input Sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let spare = Texture::create_hdr_target(device, config, "post_target");
+        let inputs = [hdr, &spare].map(|input| Self::input(device, &layout, &sampler, input));
+
+        Self {
+            layout,
+            sampler,
+            spare,
+            inputs,
+            bloom: None,
+            effects: vec![],
+        }
+    }
+
+    fn input(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        sampler: &wgpu::Sampler,
+        input: &Texture,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Post Input Bind Group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&input.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+            ],
+        })
+    }
+
+    pub fn layout(&self) -> &wgpu::BindGroupLayout {
+        &self.layout
+    }
+
+    pub fn spare(&self) -> &Texture {
+        &self.spare
+    }
+
+    /// Follows the HDR target to a new size.
+    pub fn resize(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        config: &wgpu::SurfaceConfiguration,
+        hdr: &Texture,
+    ) {
+        self.spare = Texture::create_hdr_target(device, config, "post_target");
+        self.inputs =
+            [hdr, &self.spare].map(|input| Self::input(device, &self.layout, &self.sampler, input));
+
+        if let Some(bloom) = &mut self.bloom {
+            bloom.resize(device, queue, config, &self.layout);
+        }
+    }
+
+    pub fn update(&self, queue: &wgpu::Queue) {
+        for effect in &self.effects {
+            effect.update(queue);
+        }
+    }
+
+    /// Runs bloom and the enabled effects in order and returns where the result ended up, 0
+    /// for the HDR target and 1 for [`PostStack::spare`].
+    pub fn render(&self, encoder: &mut wgpu::CommandEncoder, hdr: &Texture) -> usize {
+        let targets = [&hdr.view, &self.spare.view];
+        let mut current = 0;
+
+        if let Some(bloom) = &self.bloom {
+            bloom.render(encoder, &self.inputs[current], targets[1 - current]);
+            current = 1 - current;
+        }
+
+        for effect in self.effects.iter().filter(|effect| effect.enabled) {
+            effect.render(encoder, &self.inputs[current], targets[1 - current]);
+            current = 1 - current;
+        }
+
+        current
+    }
+}
+
+fn effect_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    name: &str,
+    shader: &str,
+) -> wgpu::RenderPipeline {
+    let vertex_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("Fullscreen Shader"),
+        source: wgpu::ShaderSource::Glsl {
+            shader: include_str!("resources/shaders/fullscreen.vert").into(),
+            stage: wgpu::naga::ShaderStage::Vertex,
+            defines: HashMap::with_hasher(BuildHasherDefault::default()),
+        },
+    });
+
+    let fragment_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some(name),
+        source: wgpu::ShaderSource::Glsl {
+            shader: shader.into(),
+            stage: wgpu::naga::ShaderStage::Fragment,
+            defines: HashMap::with_hasher(BuildHasherDefault::default()),
+        },
+    });
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some(name),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: &vertex_shader,
+            entry_point: "main",
+            buffers: &[],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &fragment_shader,
+            entry_point: "main",
+            targets: &[Some(Texture::HDR_FORMAT.into())],
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+    })
+}
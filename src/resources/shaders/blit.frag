@@ -0,0 +1,12 @@
+#version 460
+
+layout(location = 0) in vec2 texCoords;
+
+layout(set = 0, binding = 0) uniform texture2D t_source;
+layout(set = 0, binding = 1) uniform sampler s_source;
+
+layout(location = 0) out vec4 outColor;
+
+void main() {
+    outColor = texture(sampler2D(t_source, s_source), texCoords);
+}
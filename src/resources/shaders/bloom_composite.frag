@@ -0,0 +1,24 @@
+#version 460
+
+layout(location = 0) in vec2 texCoords;
+
+layout(set = 0, binding = 0) uniform texture2D t_input;
+layout(set = 0, binding = 1) uniform sampler s_input;
+
+layout(set = 1, binding = 0) uniform Bloom {
+    float threshold;
+    float knee;
+    float intensity;
+    float radius;
+};
+layout(set = 1, binding = 1) uniform texture2D t_bloom;
+layout(set = 1, binding = 2) uniform sampler s_bloom;
+
+layout(location = 0) out vec4 outColor;
+
+void main() {
+    vec4 scene = texture(sampler2D(t_input, s_input), texCoords);
+    vec3 bloom = texture(sampler2D(t_bloom, s_bloom), texCoords).rgb;
+
+    outColor = vec4(scene.rgb + bloom * intensity, scene.a);
+}
@@ -0,0 +1,79 @@
+#version 460
+
+// Built twice: with PREFILTER defined it reads the scene, keeps only what is above the
+// threshold and tames single bright pixels, without it it halves one bloom level into the next
+
+layout(location = 0) in vec2 texCoords;
+
+layout(set = 0, binding = 0) uniform texture2D t_input;
+layout(set = 0, binding = 1) uniform sampler s_input;
+
+layout(set = 1, binding = 0) uniform Bloom {
+    float threshold;
+    float knee;
+    float intensity;
+    float radius;
+};
+
+layout(location = 0) out vec4 outColor;
+
+vec3 sampleInput(vec2 offset, vec2 texel) {
+    return texture(sampler2D(t_input, s_input), texCoords + offset * texel).rgb;
+}
+
+// Weight that stops a single very bright pixel from turning into a flickering blob
+float karisWeight(vec3 colour) {
+    return 1.0 / (1.0 + dot(colour, vec3(0.2126, 0.7152, 0.0722)));
+}
+
+// Soft threshold, fading in over `knee` below it rather than cutting off
+vec3 applyThreshold(vec3 colour) {
+    float brightness = max(colour.r, max(colour.g, colour.b));
+    float soft = clamp(brightness - threshold + knee, 0.0, 2.0 * knee);
+    soft = soft * soft / (4.0 * knee + 1e-5);
+
+    return colour * max(soft, brightness - threshold) / max(brightness, 1e-5);
+}
+
+void main() {
+    vec2 texel = 1.0 / vec2(textureSize(sampler2D(t_input, s_input), 0));
+
+    // The 13 taps of Jimenez's downsample, as five overlapping 2x2 boxes
+    vec3 a = sampleInput(vec2(-2.0, 2.0), texel);
+    vec3 b = sampleInput(vec2(0.0, 2.0), texel);
+    vec3 c = sampleInput(vec2(2.0, 2.0), texel);
+    vec3 d = sampleInput(vec2(-2.0, 0.0), texel);
+    vec3 e = sampleInput(vec2(0.0, 0.0), texel);
+    vec3 f = sampleInput(vec2(2.0, 0.0), texel);
+    vec3 g = sampleInput(vec2(-2.0, -2.0), texel);
+    vec3 h = sampleInput(vec2(0.0, -2.0), texel);
+    vec3 i = sampleInput(vec2(2.0, -2.0), texel);
+    vec3 j = sampleInput(vec2(-1.0, 1.0), texel);
+    vec3 k = sampleInput(vec2(1.0, 1.0), texel);
+    vec3 l = sampleInput(vec2(-1.0, -1.0), texel);
+    vec3 m = sampleInput(vec2(1.0, -1.0), texel);
+
+    vec3 centre = (j + k + l + m) * 0.25;
+    vec3 topLeft = (a + b + d + e) * 0.25;
+    vec3 topRight = (b + c + e + f) * 0.25;
+    vec3 bottomLeft = (d + e + g + h) * 0.25;
+    vec3 bottomRight = (e + f + h + i) * 0.25;
+
+#ifdef PREFILTER
+    float centreWeight = 0.5 * karisWeight(centre);
+    float topLeftWeight = 0.125 * karisWeight(topLeft);
+    float topRightWeight = 0.125 * karisWeight(topRight);
+    float bottomLeftWeight = 0.125 * karisWeight(bottomLeft);
+    float bottomRightWeight = 0.125 * karisWeight(bottomRight);
+
+    vec3 colour = (centre * centreWeight + topLeft * topLeftWeight + topRight * topRightWeight
+        + bottomLeft * bottomLeftWeight + bottomRight * bottomRightWeight)
+        / (centreWeight + topLeftWeight + topRightWeight + bottomLeftWeight + bottomRightWeight);
+
+    colour = applyThreshold(max(colour, vec3(0.0)));
+#else
+    vec3 colour = centre * 0.5 + (topLeft + topRight + bottomLeft + bottomRight) * 0.125;
+#endif
+
+    outColor = vec4(colour, 1.0);
+}
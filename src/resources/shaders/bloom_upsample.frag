@@ -0,0 +1,32 @@
+#version 460
+
+layout(location = 0) in vec2 texCoords;
+
+layout(set = 0, binding = 0) uniform texture2D t_input;
+layout(set = 0, binding = 1) uniform sampler s_input;
+
+layout(set = 1, binding = 0) uniform Bloom {
+    float threshold;
+    float knee;
+    float intensity;
+    float radius;
+};
+
+layout(location = 0) out vec4 outColor;
+
+vec3 sampleInput(vec2 offset, vec2 texel) {
+    return texture(sampler2D(t_input, s_input), texCoords + offset * texel).rgb;
+}
+
+void main() {
+    vec2 texel = radius / vec2(textureSize(sampler2D(t_input, s_input), 0));
+
+    // 3x3 tent filter, added onto the larger level by the blend state
+    vec3 colour = sampleInput(vec2(0.0, 0.0), texel) * 4.0;
+    colour += (sampleInput(vec2(0.0, 1.0), texel) + sampleInput(vec2(-1.0, 0.0), texel)
+        + sampleInput(vec2(1.0, 0.0), texel) + sampleInput(vec2(0.0, -1.0), texel)) * 2.0;
+    colour += sampleInput(vec2(-1.0, 1.0), texel) + sampleInput(vec2(1.0, 1.0), texel)
+        + sampleInput(vec2(-1.0, -1.0), texel) + sampleInput(vec2(1.0, -1.0), texel);
+
+    outColor = vec4(colour / 16.0, 1.0);
+}
@@ -0,0 +1,12 @@
+// The cluster grid as `ClusterData` lays it out, shared by lighting.glsl and cluster.comp.
+// Spliced in by `#include "clusters.glsl"` after defining CLUSTERS_SET and CLUSTERS_BINDING.
+
+layout(set = CLUSTERS_SET, binding = CLUSTERS_BINDING) uniform Clusters {
+    mat4 clusterInverseProjection;
+    mat4 clusterView;
+    uvec4 clusterGrid; // w: most lights per cluster
+    vec4 clusterScreen; // xy: surface size, zw: tile size, in pixels
+    vec4 clusterDepth; // near, far, slice scale, slice bias
+    uint clusterLightCount;
+    float clusterThreshold;
+};
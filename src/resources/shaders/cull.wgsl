@@ -0,0 +1,63 @@
+// Frustum culls one instance per invocation and appends the visible ones to visible_instances,
+// reserving each slot with an atomic add on visible_count. The count starts at zero every
+// frame and is copied into the indirect arguments of every mesh afterwards.
+//
+// WGSL rather than GLSL like the other shaders, naga's GLSL frontend has no atomics.
+
+struct Instance {
+    model: mat4x4<f32>,
+    colour: vec4<f32>,
+}
+
+struct Culling {
+    // Left, right, bottom, top, near, far, facing inwards
+    planes: array<vec4<f32>, 6>,
+    // Object space bounds of every model together
+    bounds_min: vec4<f32>,
+    bounds_max: vec4<f32>,
+    instance_count: u32,
+}
+
+@group(0) @binding(0) var<uniform> culling: Culling;
+@group(0) @binding(1) var<storage, read> instances: array<Instance>;
+@group(0) @binding(2) var<storage, read_write> visible_instances: array<Instance>;
+@group(0) @binding(3) var<storage, read_write> visible_count: atomic<u32>;
+
+fn is_visible(model: mat4x4<f32>) -> bool {
+    let bounds_min = culling.bounds_min.xyz;
+    let bounds_max = culling.bounds_max.xyz;
+
+    if any(bounds_min > bounds_max) {
+        return false;
+    }
+
+    // The world space box around the transformed bounds
+    let centre = (model * vec4((bounds_min + bounds_max) * 0.5, 1.0)).xyz;
+    let half_extents = mat3x3(abs(model[0].xyz), abs(model[1].xyz), abs(model[2].xyz))
+        * ((bounds_max - bounds_min) * 0.5);
+    let world_min = centre - half_extents;
+    let world_max = centre + half_extents;
+
+    for (var i = 0; i < 6; i++) {
+        let plane = culling.planes[i];
+        // The corner furthest along the plane's normal
+        let corner = select(world_min, world_max, plane.xyz >= vec3(0.0));
+
+        if dot(plane.xyz, corner) + plane.w < 0.0 {
+            return false;
+        }
+    }
+
+    return true;
+}
+
+@compute @workgroup_size(256)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let index = id.x;
+
+    if index >= culling.instance_count || !is_visible(instances[index].model) {
+        return;
+    }
+
+    visible_instances[atomicAdd(&visible_count, 1u)] = instances[index];
+}
@@ -0,0 +1,51 @@
+#version 460
+
+// Lighting pass of the deferred path: lights the surface gbuffer.frag stored at each pixel
+
+layout(location = 0) in vec2 texCoords;
+
+layout(set = 0, binding = 0) uniform texture2D t_albedo;
+layout(set = 0, binding = 1) uniform texture2D t_normal;
+layout(set = 0, binding = 2) uniform texture2D t_material;
+layout(set = 0, binding = 3) uniform texture2D t_emissive;
+layout(set = 0, binding = 4) uniform texture2D t_depth;
+layout(set = 0, binding = 5) uniform sampler s_gbuffer;
+
+layout(set = 0, binding = 6) uniform GBuffer {
+    mat4 inverseViewProjection;
+};
+
+#include "lighting.glsl"
+
+layout(location = 0) out vec4 outColor;
+
+void main() {
+    ivec2 pixel = ivec2(gl_FragCoord.xy);
+    float depth = texelFetch(sampler2D(t_depth, s_gbuffer), pixel, 0).r;
+
+    // Nothing was drawn here, the background stays
+    if (depth >= 1.0) {
+        discard;
+    }
+
+    // World space position back from the depth buffer
+    vec2 uv = (vec2(pixel) + 0.5) / vec2(textureSize(sampler2D(t_depth, s_gbuffer), 0));
+    vec4 world = inverseViewProjection * vec4(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, depth, 1.0);
+
+    vec4 albedo = texelFetch(sampler2D(t_albedo, s_gbuffer), pixel, 0);
+    vec4 material = texelFetch(sampler2D(t_material, s_gbuffer), pixel, 0);
+
+    Surface surface = Surface(
+        world.xyz / world.w,
+        normalize(texelFetch(sampler2D(t_normal, s_gbuffer), pixel, 0).xyz),
+        albedo.rgb,
+        albedo.a,
+        material.r,
+        material.g,
+        material.b,
+        texelFetch(sampler2D(t_emissive, s_gbuffer), pixel, 0).rgb,
+        uint(material.a + 0.5) // 0 or 1, stored as is
+    );
+
+    outColor = vec4(shade(surface), surface.alpha);
+}
@@ -0,0 +1,43 @@
+#version 460
+
+layout(location = 0) in vec2 texCoords;
+
+layout(set = 0, binding = 0) uniform texture2D t_equirect;
+layout(set = 0, binding = 1) uniform sampler s_equirect;
+layout(set = 0, binding = 2) uniform Face {
+    uint face;
+};
+
+layout(location = 0) out vec4 outColor;
+
+const float PI = 3.14159265359;
+
+// Direction through a texel of a cube face, in the order and orientation cube samplers use
+vec3 faceDirection(vec2 st) {
+    if (face == 0u) {
+        return vec3(1.0, -st.y, -st.x);
+    } else if (face == 1u) {
+        return vec3(-1.0, -st.y, st.x);
+    } else if (face == 2u) {
+        return vec3(st.x, 1.0, st.y);
+    } else if (face == 3u) {
+        return vec3(st.x, -1.0, -st.y);
+    } else if (face == 4u) {
+        return vec3(st.x, -st.y, 1.0);
+    }
+
+    return vec3(-st.x, -st.y, -1.0);
+}
+
+void main() {
+    vec3 direction = normalize(faceDirection(texCoords * 2.0 - 1.0));
+
+    // Longitude around +Y, latitude from the top row down
+    vec2 uv = vec2(
+        atan(direction.z, direction.x) / (2.0 * PI) + 0.5,
+        acos(clamp(direction.y, -1.0, 1.0)) / PI
+    );
+
+    // Explicit level, the longitude wraps around and would confuse the derivatives
+    outColor = textureLod(sampler2D(t_equirect, s_equirect), uv, 0.0);
+}
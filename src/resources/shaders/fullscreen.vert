@@ -0,0 +1,12 @@
+#version 460
+
+layout(location = 0) out vec2 texCoords;
+
+void main() {
+    // One triangle covering the whole screen, no vertex buffer needed
+    vec2 position = vec2(float((gl_VertexIndex << 1) & 2), float(gl_VertexIndex & 2));
+
+    texCoords = vec2(position.x, 1.0 - position.y);
+
+    gl_Position = vec4(position * 2.0 - 1.0, 0.0, 1.0);
+}
@@ -0,0 +1,26 @@
+#version 460
+
+// Geometry pass of the deferred path: the material at each pixel goes into the G-buffer, to
+// be lit by deferred.frag
+
+layout(location = 0) in vec4 fragColor;
+layout(location = 1) in vec2 texCoords;
+layout(location = 2) in vec3 worldPosition;
+layout(location = 3) in vec3 worldNormal;
+
+#include "lighting.glsl"
+#include "material.glsl"
+
+layout(location = 0) out vec4 outAlbedo;   // rgb: albedo, a: alpha
+layout(location = 1) out vec4 outNormal;   // xyz: world space normal
+layout(location = 2) out vec4 outMaterial; // metallic, roughness, occlusion, shading model (0 or 1)
+layout(location = 3) out vec4 outEmissive; // rgb: emitted light
+
+void main() {
+    Surface surface = materialSurface();
+
+    outAlbedo = vec4(surface.albedo, surface.alpha);
+    outNormal = vec4(surface.normal, 0.0);
+    outMaterial = vec4(surface.metallic, surface.roughness, surface.occlusion, float(surface.shading));
+    outEmissive = vec4(surface.emissive, 0.0);
+}
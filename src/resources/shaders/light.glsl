@@ -0,0 +1,9 @@
+// One light as `Light::to_raw` lays it out, shared by lighting.glsl and cluster.comp. Spliced
+// in by `#include "light.glsl"`.
+
+struct Light {
+    vec4 position;    // w: kind
+    vec4 direction;   // w: cos of the outer spot cone
+    vec4 colour;      // premultiplied by intensity
+    vec4 attenuation; // constant, linear, quadratic, w: cos of the inner spot cone
+};
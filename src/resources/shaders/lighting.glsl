@@ -0,0 +1,313 @@
+// Scene lighting, shared by the forward shader and the deferred lighting pass. Spliced in by
+// `#include "lighting.glsl"`, it expects nothing from the including shader.
+
+layout(set = 1, binding = 0) uniform Camera {
+    mat4 viewProjection;
+    vec4 cameraPosition;
+};
+
+layout(set = 1, binding = 1) uniform Lighting {
+    vec4 ambient;
+    uint lightCount;
+    uint ambientOcclusion; // 1 when t_ambientOcclusion holds screen-space occlusion
+    uint clustered; // 1 when only the lights binned into each cluster are walked
+};
+
+#include "light.glsl"
+
+layout(set = 1, binding = 2) readonly buffer Lights {
+    Light lights[];
+};
+
+const int MAX_CASCADES = 4;
+
+layout(set = 1, binding = 3) uniform Shadow {
+    mat4 lightViewProjections[MAX_CASCADES];
+    vec4 cascadeSplits; // view distance where each cascade ends
+    int shadowLight; // -1 without a shadow casting light
+    uint cascadeCount;
+    uint pcfRadius;
+    float shadowTexelSize;
+    float cascadeBlend;
+};
+
+layout(set = 1, binding = 4) uniform texture2DArray t_shadow;
+layout(set = 1, binding = 5) uniform samplerShadow s_shadow;
+
+const int MAX_POINT_SHADOWS = 4;
+
+layout(set = 1, binding = 6) uniform PointShadows {
+    vec4 pointShadowLights[MAX_POINT_SHADOWS]; // w: far plane
+    ivec4 pointShadowIndices; // index of each light in the light buffer
+    uint pointShadowCount;
+    float pointShadowBias;
+};
+
+layout(set = 1, binding = 7) uniform textureCubeArray t_pointShadow;
+layout(set = 1, binding = 8) uniform samplerShadow s_pointShadow;
+
+layout(set = 1, binding = 9) uniform texture2D t_ambientOcclusion; // one texel per pixel
+layout(set = 1, binding = 10) uniform sampler s_ambientOcclusion;
+
+#define CLUSTERS_SET 1
+#define CLUSTERS_BINDING 11
+#include "clusters.glsl"
+
+// Lights binned into each cluster by cluster.comp, a run of clusterGrid.w slots per cluster
+layout(set = 1, binding = 12) readonly buffer ClusterCounts {
+    uint clusterCounts[];
+};
+
+layout(set = 1, binding = 13) readonly buffer ClusterIndices {
+    uint clusterIndices[];
+};
+
+const int DIRECTIONAL = 0;
+const int SPOT = 2;
+
+const uint BLINN_PHONG = 0u;
+
+const float SPECULAR_STRENGTH = 0.5;
+const float SHININESS = 32.0;
+
+const float PI = 3.14159265359;
+
+// Everything lighting needs to know about the surface seen through a pixel
+struct Surface {
+    vec3 position; // world space
+    vec3 normal;   // world space, facing the camera
+    vec3 albedo;
+    float alpha;
+    float metallic;
+    float roughness;
+    float occlusion;
+    vec3 emissive;
+    uint shading;
+};
+
+// Light arriving at `position` from one light, and the direction it comes from
+vec3 incidentLight(Light light, vec3 position, out vec3 toLight) {
+    int kind = int(light.position.w);
+
+    toLight = -light.direction.xyz;
+    float attenuation = 1.0;
+
+    if (kind != DIRECTIONAL) {
+        vec3 offset = light.position.xyz - position;
+        float distance = length(offset);
+
+        toLight = offset / distance;
+        attenuation = 1.0 / (light.attenuation.x
+            + light.attenuation.y * distance
+            + light.attenuation.z * distance * distance);
+
+        if (kind == SPOT) {
+            float theta = dot(-toLight, light.direction.xyz);
+            float cone = (theta - light.direction.w) / (light.attenuation.w - light.direction.w);
+
+            attenuation *= clamp(cone, 0.0, 1.0);
+        }
+    }
+
+    return light.colour.rgb * attenuation;
+}
+
+vec3 blinnPhong(vec3 albedo, vec3 normal, vec3 toEye, vec3 toLight) {
+    float diffuse = max(dot(normal, toLight), 0.0);
+    float specular = 0.0;
+
+    if (diffuse > 0.0) {
+        vec3 halfway = normalize(toLight + toEye);
+        specular = SPECULAR_STRENGTH * pow(max(dot(normal, halfway), 0.0), SHININESS);
+    }
+
+    return albedo * diffuse + specular;
+}
+
+// GGX / Trowbridge-Reitz normal distribution
+float distributionGGX(float NdotH, float roughness) {
+    float a = roughness * roughness;
+    float a2 = a * a;
+    float d = NdotH * NdotH * (a2 - 1.0) + 1.0;
+
+    return a2 / (PI * d * d);
+}
+
+// Smith's method with Schlick-GGX for both the view and light directions
+float geometrySmith(float NdotV, float NdotL, float roughness) {
+    float r = roughness + 1.0;
+    float k = r * r / 8.0;
+
+    return NdotV / (NdotV * (1.0 - k) + k) * NdotL / (NdotL * (1.0 - k) + k);
+}
+
+vec3 fresnelSchlick(float cosTheta, vec3 F0) {
+    return F0 + (1.0 - F0) * pow(clamp(1.0 - cosTheta, 0.0, 1.0), 5.0);
+}
+
+vec3 cookTorrance(vec3 albedo, float metallic, float roughness, vec3 normal, vec3 toEye, vec3 toLight) {
+    float NdotL = max(dot(normal, toLight), 0.0);
+
+    if (NdotL <= 0.0) {
+        return vec3(0.0);
+    }
+
+    vec3 halfway = normalize(toLight + toEye);
+    float NdotV = max(dot(normal, toEye), 1e-4);
+    float NdotH = max(dot(normal, halfway), 0.0);
+
+    // Dielectrics reflect about 4% head on, metals tint the reflection with their colour
+    vec3 F0 = mix(vec3(0.04), albedo, metallic);
+    vec3 F = fresnelSchlick(max(dot(halfway, toEye), 0.0), F0);
+
+    vec3 specular = distributionGGX(NdotH, roughness) * geometrySmith(NdotV, NdotL, roughness) * F
+        / (4.0 * NdotV * NdotL + 1e-4);
+    vec3 diffuse = (1.0 - F) * (1.0 - metallic) * albedo / PI;
+
+    // Multiplied by PI so a white light of intensity 1 lights a white diffuse surface fully,
+    // the same as Blinn-Phong
+    return (diffuse + specular) * NdotL * PI;
+}
+
+// Fraction of the shadow casting light reaching `position` in one cascade, averaged over a
+// PCF kernel
+float cascadeShadow(int cascade, vec3 position) {
+    vec4 clip = lightViewProjections[cascade] * vec4(position, 1.0);
+    vec3 ndc = clip.xyz / clip.w;
+    vec2 uv = vec2(ndc.x, -ndc.y) * 0.5 + 0.5;
+
+    int radius = int(pcfRadius);
+    float lit = 0.0;
+
+    for (int y = -radius; y <= radius; y++) {
+        for (int x = -radius; x <= radius; x++) {
+            vec2 offset = vec2(x, y) * shadowTexelSize;
+            lit += texture(sampler2DArrayShadow(t_shadow, s_shadow), vec4(uv + offset, float(cascade), ndc.z));
+        }
+    }
+
+    lit /= float((2 * radius + 1) * (2 * radius + 1));
+
+    // Nothing outside the light's view is known to be shadowed
+    bool outside = any(lessThan(uv, vec2(0.0))) || any(greaterThan(uv, vec2(1.0))) || ndc.z > 1.0;
+
+    return outside ? 1.0 : lit;
+}
+
+// Shadow of the cascade `position` falls in, fading into the next cascade (or no shadow after
+// the last one) towards its far end
+float shadowFactor(vec3 position) {
+    // The perspective divisor is the distance along the camera's view direction
+    float viewDepth = (viewProjection * vec4(position, 1.0)).w;
+    int last = int(cascadeCount) - 1;
+
+    int cascade = 0;
+    for (int i = 0; i < last; i++) {
+        cascade += viewDepth > cascadeSplits[i] ? 1 : 0;
+    }
+    int next = min(cascade + 1, last);
+
+    // Both are always sampled, texture lookups need uniform control flow
+    float current = cascadeShadow(cascade, position);
+    float following = cascadeShadow(next, position);
+
+    if (next == cascade) {
+        following = 1.0;
+    }
+
+    float start = cascade == 0 ? 0.0 : cascadeSplits[cascade - 1];
+    float end = cascadeSplits[cascade];
+    float band = max((end - start) * cascadeBlend, 1e-4);
+    float fade = clamp((end - viewDepth) / band, 0.0, 1.0);
+
+    return mix(following, current, fade);
+}
+
+// Fraction of a shadow casting point light reaching `position`, from the distances stored in
+// its cube
+float pointShadowFactor(int slot, vec3 position) {
+    vec3 fromLight = position - pointShadowLights[slot].xyz;
+    float far = pointShadowLights[slot].w;
+    float distance = length(fromLight);
+
+    float lit = texture(
+        samplerCubeArrayShadow(t_pointShadow, s_pointShadow),
+        vec4(fromLight, float(slot)),
+        (distance - pointShadowBias) / far
+    );
+
+    // Nothing is stored past the far plane
+    return distance > far ? 1.0 : lit;
+}
+
+// Index of the cluster `position` falls in, seen through this fragment's pixel
+uint clusterIndex(vec3 position) {
+    float viewDepth = (viewProjection * vec4(position, 1.0)).w;
+    float slice = floor(log(max(viewDepth, 1e-4)) * clusterDepth.z + clusterDepth.w);
+
+    uvec3 cluster = uvec3(
+        min(uvec2(gl_FragCoord.xy / clusterScreen.zw), clusterGrid.xy - 1u),
+        uint(clamp(slice, 0.0, float(clusterGrid.z - 1u)))
+    );
+
+    return cluster.x + clusterGrid.x * (cluster.y + clusterGrid.y * cluster.z);
+}
+
+// Light leaving `surface` towards the camera: ambient, emitted and from every light
+vec3 shade(Surface surface) {
+    vec3 toEye = normalize(cameraPosition.xyz - surface.position);
+    float occlusion = surface.occlusion;
+
+    // Screen-space occlusion only takes away ambient light, like the occlusion map
+    if (ambientOcclusion != 0u) {
+        occlusion *= texelFetch(
+            sampler2D(t_ambientOcclusion, s_ambientOcclusion),
+            ivec2(gl_FragCoord.xy),
+            0
+        ).r;
+    }
+
+    vec3 colour = ambient.rgb * surface.albedo * occlusion + surface.emissive;
+    float shadow = shadowFactor(surface.position);
+
+    // Sampled up front, texture lookups need uniform control flow
+    float pointShadows[MAX_POINT_SHADOWS] = float[](1.0, 1.0, 1.0, 1.0);
+    for (int slot = 0; slot < int(pointShadowCount); slot++) {
+        pointShadows[slot] = pointShadowFactor(slot, surface.position);
+    }
+
+    // Clustered, only the lights that reach this fragment's cluster are walked
+    uint cluster = clustered != 0u ? clusterIndex(surface.position) : 0u;
+    uint count = clustered != 0u ? clusterCounts[cluster] : lightCount;
+
+    for (uint n = 0u; n < count; n++) {
+        uint i = clustered != 0u ? clusterIndices[cluster * clusterGrid.w + n] : n;
+        vec3 toLight;
+        vec3 radiance = incidentLight(lights[i], surface.position, toLight);
+
+        if (int(i) == shadowLight) {
+            radiance *= shadow;
+        }
+
+        for (int slot = 0; slot < int(pointShadowCount); slot++) {
+            if (pointShadowIndices[slot] == int(i)) {
+                radiance *= pointShadows[slot];
+            }
+        }
+
+        if (surface.shading == BLINN_PHONG) {
+            colour += blinnPhong(surface.albedo, surface.normal, toEye, toLight) * radiance;
+        } else {
+            colour += cookTorrance(
+                surface.albedo,
+                surface.metallic,
+                surface.roughness,
+                surface.normal,
+                toEye,
+                toLight
+            ) * radiance;
+        }
+    }
+
+    return colour;
+}
@@ -0,0 +1,102 @@
+// Material inputs of a mesh, shared by the forward shader and the deferred geometry pass.
+// Spliced in by `#include "material.glsl"` after lighting.glsl, it expects the vertex outputs
+// of shader.vert as `texCoords`, `worldPosition` and `worldNormal`.
+
+layout(set = 0, binding = 0) uniform texture2D t_texture;
+layout(set = 0, binding = 1) uniform sampler s_texture;
+layout(set = 0, binding = 2) uniform texture2D t_metallicRoughness;
+layout(set = 0, binding = 3) uniform sampler s_metallicRoughness;
+layout(set = 0, binding = 4) uniform texture2D t_normal;
+layout(set = 0, binding = 5) uniform sampler s_normal;
+layout(set = 0, binding = 6) uniform texture2D t_occlusion;
+layout(set = 0, binding = 7) uniform sampler s_occlusion;
+layout(set = 0, binding = 8) uniform texture2D t_emissive;
+layout(set = 0, binding = 9) uniform sampler s_emissive;
+
+layout(set = 0, binding = 10) uniform Material {
+    vec4 baseColourFactor;
+    vec4 emissiveFactor;
+    float metallicFactor;
+    float roughnessFactor;
+    float normalScale;
+    float occlusionStrength;
+    uint shading;
+    uint maps;
+};
+
+const uint HAS_METALLIC_ROUGHNESS = 1u;
+const uint HAS_NORMAL = 2u;
+const uint HAS_OCCLUSION = 4u;
+const uint HAS_EMISSIVE = 8u;
+
+// Tangent frame from screen space derivatives, so meshes need no tangents
+mat3 cotangentFrame(vec3 normal, vec3 position, vec2 uv) {
+    vec3 dp1 = dFdx(position);
+    vec3 dp2 = dFdy(position);
+    vec2 duv1 = dFdx(uv);
+    vec2 duv2 = dFdy(uv);
+
+    vec3 dp2perp = cross(dp2, normal);
+    vec3 dp1perp = cross(normal, dp1);
+    vec3 tangent = dp2perp * duv1.x + dp1perp * duv2.x;
+    vec3 bitangent = dp2perp * duv1.y + dp1perp * duv2.y;
+
+    float scale = inversesqrt(max(max(dot(tangent, tangent), dot(bitangent, bitangent)), 1e-12));
+
+    return mat3(tangent * scale, bitangent * scale, normal);
+}
+
+// The material evaluated at this fragment
+Surface materialSurface() {
+    vec4 albedo = texture(sampler2D(t_texture, s_texture), texCoords) * baseColourFactor; //* fragColor;
+
+    // Nothing is culled, so light the back of a surface as if it faced the camera
+    vec3 normal = normalize(gl_FrontFacing ? worldNormal : -worldNormal);
+
+    // Sampled unconditionally, texture lookups need uniform control flow for their derivatives
+    vec4 metallicRoughness = texture(sampler2D(t_metallicRoughness, s_metallicRoughness), texCoords);
+    vec3 mappedNormal = texture(sampler2D(t_normal, s_normal), texCoords).xyz;
+    float occlusionSample = texture(sampler2D(t_occlusion, s_occlusion), texCoords).r;
+    vec3 emissiveSample = texture(sampler2D(t_emissive, s_emissive), texCoords).rgb;
+    mat3 frame = cotangentFrame(normal, worldPosition, texCoords);
+
+    float metallic = metallicFactor;
+    float roughness = roughnessFactor;
+    float occlusion = 1.0;
+    vec3 emissive = emissiveFactor.rgb;
+
+    if ((maps & HAS_METALLIC_ROUGHNESS) != 0u) {
+        roughness *= metallicRoughness.g;
+        metallic *= metallicRoughness.b;
+    }
+
+    if ((maps & HAS_NORMAL) != 0u) {
+        vec3 tangentNormal = mappedNormal * 2.0 - 1.0;
+        tangentNormal.xy *= normalScale;
+        // The maps have +Y up while texture V, and so the bitangent, points down
+        tangentNormal.y = -tangentNormal.y;
+
+        normal = normalize(frame * tangentNormal);
+    }
+
+    if ((maps & HAS_OCCLUSION) != 0u) {
+        occlusion = mix(1.0, occlusionSample, occlusionStrength);
+    }
+
+    if ((maps & HAS_EMISSIVE) != 0u) {
+        emissive *= emissiveSample;
+    }
+
+    // Fully smooth surfaces turn highlights into single pixels
+    return Surface(
+        worldPosition,
+        normal,
+        albedo.rgb,
+        albedo.a,
+        clamp(metallic, 0.0, 1.0),
+        clamp(roughness, 0.04, 1.0),
+        occlusion,
+        emissive,
+        shading
+    );
+}
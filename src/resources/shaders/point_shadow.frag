@@ -0,0 +1,14 @@
+#version 460
+
+layout(location = 0) in vec3 worldPosition;
+
+layout(set = 0, binding = 0) uniform Face {
+    mat4 faceViewProjection;
+    vec4 lightPosition; // w: far plane
+};
+
+void main() {
+    // Distance to the light rather than projected depth, so every face of the cube stores the
+    // same quantity and lookups need no projection
+    gl_FragDepth = length(worldPosition - lightPosition.xyz) / lightPosition.w;
+}
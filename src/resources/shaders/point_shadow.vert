@@ -0,0 +1,24 @@
+#version 460
+
+layout(location = 0) in vec3 position;
+
+layout(location = 2) in vec4 modelMatrixRow0;
+layout(location = 3) in vec4 modelMatrixRow1;
+layout(location = 4) in vec4 modelMatrixRow2;
+layout(location = 5) in vec4 modelMatrixRow3;
+
+layout(set = 0, binding = 0) uniform Face {
+    mat4 faceViewProjection;
+    vec4 lightPosition; // w: far plane
+};
+
+layout(location = 0) out vec3 worldPosition;
+
+void main() {
+    mat4 modelMatrix = mat4(modelMatrixRow0, modelMatrixRow1, modelMatrixRow2, modelMatrixRow3);
+    vec4 world = modelMatrix * vec4(position, 1.0);
+
+    worldPosition = world.xyz;
+
+    gl_Position = faceViewProjection * world;
+}
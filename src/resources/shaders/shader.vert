@@ -2,6 +2,7 @@
 
 layout(location = 0) in vec3 position;
 layout(location = 1) in vec2 uv;
+layout(location = 7) in vec3 normal;
 
 layout(location = 2) in vec4 modelMatrixRow0;
 layout(location = 3) in vec4 modelMatrixRow1;
@@ -10,18 +11,25 @@ layout(location = 5) in vec4 modelMatrixRow3;
 
 layout(location = 6) in vec4 modelColor;
 
-layout(set = 1, binding = 0) uniform ViewProjection {
+layout(set = 1, binding = 0) uniform Camera {
     mat4 viewProjection;
+    vec4 cameraPosition;
 };
 
 layout(location = 0) out vec4 fragColor;
 layout(location = 1) out vec2 texCoords;
+layout(location = 2) out vec3 worldPosition;
+layout(location = 3) out vec3 worldNormal;
 
 void main() {
     mat4 modelMatrix = mat4(modelMatrixRow0, modelMatrixRow1, modelMatrixRow2, modelMatrixRow3);
+    vec4 world = modelMatrix * vec4(position, 1.0);
 
     fragColor = modelColor;
     texCoords = uv;
+    worldPosition = world.xyz;
+    // Instances only rotate and translate, so the model matrix keeps normals perpendicular
+    worldNormal = mat3(modelMatrix) * normal;
 
-    gl_Position = viewProjection * modelMatrix * vec4(position, 1.0);
+    gl_Position = viewProjection * world;
 }
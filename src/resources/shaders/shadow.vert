@@ -0,0 +1,18 @@
+#version 460
+
+layout(location = 0) in vec3 position;
+
+layout(location = 2) in vec4 modelMatrixRow0;
+layout(location = 3) in vec4 modelMatrixRow1;
+layout(location = 4) in vec4 modelMatrixRow2;
+layout(location = 5) in vec4 modelMatrixRow3;
+
+layout(set = 0, binding = 0) uniform Light {
+    mat4 lightViewProjection;
+};
+
+void main() {
+    mat4 modelMatrix = mat4(modelMatrixRow0, modelMatrixRow1, modelMatrixRow2, modelMatrixRow3);
+
+    gl_Position = lightViewProjection * modelMatrix * vec4(position, 1.0);
+}
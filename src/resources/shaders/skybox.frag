@@ -0,0 +1,12 @@
+#version 460
+
+layout(location = 0) in vec3 direction;
+
+layout(set = 0, binding = 1) uniform textureCube t_sky;
+layout(set = 0, binding = 2) uniform sampler s_sky;
+
+layout(location = 0) out vec4 outColor;
+
+void main() {
+    outColor = vec4(texture(samplerCube(t_sky, s_sky), normalize(direction)).rgb, 1.0);
+}
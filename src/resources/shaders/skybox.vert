@@ -0,0 +1,17 @@
+#version 460
+
+layout(set = 0, binding = 0) uniform Sky {
+    mat4 inverseRotationProjection;
+};
+
+layout(location = 0) out vec3 direction;
+
+void main() {
+    // Fullscreen triangle on the far plane, behind everything already drawn
+    vec2 position = vec2(float((gl_VertexIndex << 1) & 2), float(gl_VertexIndex & 2)) * 2.0 - 1.0;
+
+    vec4 world = inverseRotationProjection * vec4(position, 1.0, 1.0);
+    direction = world.xyz / world.w;
+
+    gl_Position = vec4(position, 1.0, 1.0);
+}
@@ -0,0 +1,85 @@
+#version 460
+
+const int MAX_SSAO_SAMPLES = 64;
+
+// Unused, pixels are addressed through gl_FragCoord
+layout(location = 0) in vec2 texCoords;
+
+layout(set = 0, binding = 0) uniform texture2D t_depth;
+layout(set = 0, binding = 1) uniform texture2D t_noise;
+layout(set = 0, binding = 2) uniform sampler s_point;
+
+layout(set = 0, binding = 3) uniform Ssao {
+    mat4 projection;
+    mat4 inverseProjection;
+    vec4 kernel[MAX_SSAO_SAMPLES]; // tangent space hemisphere around +Z
+    float radius;
+    float bias;
+    float power;
+    uint sampleCount;
+    uint blurRadius;
+};
+
+layout(location = 0) out float outOcclusion;
+
+ivec2 depthSize() {
+    return textureSize(sampler2D(t_depth, s_point), 0);
+}
+
+// View space position of the surface seen through a pixel
+vec3 viewPosition(ivec2 pixel) {
+    ivec2 size = depthSize();
+    pixel = clamp(pixel, ivec2(0), size - 1);
+
+    float depth = texelFetch(sampler2D(t_depth, s_point), pixel, 0).r;
+    vec2 uv = (vec2(pixel) + 0.5) / vec2(size);
+    vec4 view = inverseProjection * vec4(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, depth, 1.0);
+
+    return view.xyz / view.w;
+}
+
+void main() {
+    ivec2 pixel = ivec2(gl_FragCoord.xy);
+
+    // Nothing was drawn here, so nothing to occlude
+    if (texelFetch(sampler2D(t_depth, s_point), pixel, 0).r >= 1.0) {
+        outOcclusion = 1.0;
+        return;
+    }
+
+    vec3 position = viewPosition(pixel);
+
+    // Normal from the neighbours, each time from the side on the same surface so that edges
+    // don't bend it
+    vec3 right = viewPosition(pixel + ivec2(1, 0)) - position;
+    vec3 left = position - viewPosition(pixel - ivec2(1, 0));
+    vec3 down = viewPosition(pixel + ivec2(0, 1)) - position;
+    vec3 up = position - viewPosition(pixel - ivec2(0, 1));
+    vec3 dx = abs(right.z) < abs(left.z) ? right : left;
+    vec3 dy = abs(down.z) < abs(up.z) ? down : up;
+    vec3 normal = normalize(cross(dy, dx));
+
+    // The 4x4 noise tile rotates the kernel differently for neighbouring pixels, the blur
+    // averages the pattern away
+    vec3 random = texelFetch(sampler2D(t_noise, s_point), pixel % ivec2(4), 0).xyz;
+    vec3 tangent = normalize(random - normal * dot(random, normal));
+    mat3 frame = mat3(tangent, cross(normal, tangent), normal);
+
+    ivec2 size = depthSize();
+    float occlusion = 0.0;
+
+    for (uint i = 0u; i < sampleCount; i++) {
+        vec3 samplePosition = position + frame * kernel[i].xyz * radius;
+
+        vec4 clip = projection * vec4(samplePosition, 1.0);
+        vec2 ndc = clip.xy / clip.w;
+        vec2 uv = vec2(ndc.x * 0.5 + 0.5, 0.5 - ndc.y * 0.5);
+        float sceneDepth = viewPosition(ivec2(uv * vec2(size))).z;
+
+        // Surfaces far in front of the point are separate objects and should not darken it
+        float range = smoothstep(0.0, 1.0, radius / abs(position.z - sceneDepth));
+        occlusion += (sceneDepth >= samplePosition.z + bias ? 1.0 : 0.0) * range;
+    }
+
+    outOcclusion = pow(1.0 - occlusion / float(max(sampleCount, 1u)), power);
+}
@@ -0,0 +1,62 @@
+#version 460
+
+// One direction of a separable blur, horizontal with HORIZONTAL defined and vertical without.
+// Depth aware, so occlusion does not bleed across the edges of objects
+
+const int MAX_SSAO_SAMPLES = 64;
+
+// Unused, pixels are addressed through gl_FragCoord
+layout(location = 0) in vec2 texCoords;
+
+layout(set = 0, binding = 0) uniform texture2D t_occlusion;
+layout(set = 0, binding = 1) uniform texture2D t_depth;
+layout(set = 0, binding = 2) uniform sampler s_point;
+
+layout(set = 0, binding = 3) uniform Ssao {
+    mat4 projection;
+    mat4 inverseProjection;
+    vec4 kernel[MAX_SSAO_SAMPLES];
+    float radius;
+    float bias;
+    float power;
+    uint sampleCount;
+    uint blurRadius;
+};
+
+layout(location = 0) out float outOcclusion;
+
+// View space depth, larger for closer surfaces
+float viewDepth(ivec2 pixel) {
+    float depth = texelFetch(sampler2D(t_depth, s_point), pixel, 0).r;
+    vec4 view = inverseProjection * vec4(0.0, 0.0, depth, 1.0);
+
+    return view.z / view.w;
+}
+
+void main() {
+    ivec2 pixel = ivec2(gl_FragCoord.xy);
+    ivec2 size = textureSize(sampler2D(t_occlusion, s_point), 0);
+
+#ifdef HORIZONTAL
+    ivec2 direction = ivec2(1, 0);
+#else
+    ivec2 direction = ivec2(0, 1);
+#endif
+
+    float centre = viewDepth(pixel);
+    float total = 0.0;
+    float weights = 0.0;
+
+    for (int i = -int(blurRadius); i <= int(blurRadius); i++) {
+        ivec2 neighbour = clamp(pixel + direction * i, ivec2(0), size - 1);
+
+        // Neighbours further away in depth than the occlusion radius are another surface
+        float weight = max(0.0, 1.0 - abs(viewDepth(neighbour) - centre) / radius);
+
+        total += texelFetch(sampler2D(t_occlusion, s_point), neighbour, 0).r * weight;
+        weights += weight;
+    }
+
+    // The centre always counts fully, so weights never ends up 0
+    outOcclusion = total / weights;
+}
@@ -0,0 +1,82 @@
+#version 460
+
+layout(location = 0) in vec2 texCoords;
+
+layout(set = 0, binding = 0) uniform texture2D t_hdr;
+layout(set = 0, binding = 1) uniform sampler s_hdr;
+
+layout(set = 0, binding = 2) uniform Tonemap {
+    float exposure;
+    // 0 clamp, 1 Reinhard, 2 ACES, 3 AgX
+    uint tonemapper;
+};
+
+layout(location = 0) out vec4 outColor;
+
+vec3 reinhard(vec3 colour) {
+    return colour / (1.0 + colour);
+}
+
+// Krzysztof Narkowicz's fit of the ACES filmic curve
+vec3 aces(vec3 colour) {
+    return clamp((colour * (2.51 * colour + 0.03)) / (colour * (2.43 * colour + 0.59) + 0.14), 0.0, 1.0);
+}
+
+// Polynomial fit of the AgX base contrast curve, in log space
+vec3 agxContrast(vec3 x) {
+    vec3 x2 = x * x;
+    vec3 x4 = x2 * x2;
+
+    return 15.5 * x4 * x2 - 40.14 * x4 * x + 31.96 * x4 - 6.868 * x2 * x + 0.4298 * x2
+        + 0.1191 * x - 0.00232;
+}
+
+// Minimal AgX after Troy Sobotka's, with the default look
+vec3 agx(vec3 colour) {
+    const mat3 inset = mat3(
+        0.842479062253094, 0.0423282422610123, 0.0423756549057051,
+        0.0784335999999992, 0.878468636469772, 0.0784336,
+        0.0792237451477643, 0.0791661274605434, 0.879142973793104);
+    const mat3 outset = mat3(
+        1.19687900512017, -0.0528968517574562, -0.0529716355144438,
+        -0.0980208811401368, 1.15190312990417, -0.0980434501171241,
+        -0.0990297440797205, -0.0989611768448433, 1.15107367264116);
+    const float minEv = -12.47393;
+    const float maxEv = 4.026069;
+
+    vec3 encoded = clamp(log2(max(inset * colour, vec3(1e-10))), minEv, maxEv);
+    encoded = agxContrast((encoded - minEv) / (maxEv - minEv));
+
+    // The curve gives display encoded values, which are encoded again on the way out
+    return pow(max(outset * encoded, vec3(0.0)), vec3(2.2));
+}
+
+// The sRGB transfer function, for targets that store what they are given. Defined as
+// ENCODE_SRGB by the pipeline when the surface format is not sRGB itself.
+vec3 encodeSrgb(vec3 linear) {
+    vec3 low = linear * 12.92;
+    vec3 high = 1.055 * pow(linear, vec3(1.0 / 2.4)) - 0.055;
+
+    return mix(high, low, lessThanEqual(linear, vec3(0.0031308)));
+}
+
+void main() {
+    vec4 hdr = texture(sampler2D(t_hdr, s_hdr), texCoords);
+    vec3 colour = hdr.rgb * exposure;
+
+    if (tonemapper == 1) {
+        colour = reinhard(colour);
+    } else if (tonemapper == 2) {
+        colour = aces(colour);
+    } else if (tonemapper == 3) {
+        colour = agx(colour);
+    }
+
+    colour = clamp(colour, 0.0, 1.0);
+
+#ifdef ENCODE_SRGB
+    colour = encodeSrgb(colour);
+#endif
+
+    outColor = vec4(colour, hdr.a);
+}
@@ -0,0 +1,348 @@
+use std::{ops::Range, path::Path};
+
+use anyhow::Context as _;
+
+use crate::{
+    camera::Camera,
+    mipmap,
+    model::{self, Material, Mesh, Model, Vertex},
+    texture,
+};
+
+pub struct Node {
+    pub name: Option<String>,
+    pub parent: Option<usize>,
+    pub children: Vec<usize>,
+    /// Transform relative to the parent node.
+    pub local: glam::Mat4,
+    /// Transform relative to the scene root, already baked into this node's meshes.
+    pub world: glam::Mat4,
+    /// Range of `Scene::model.meshes` generated from this node.
+    pub meshes: Range<usize>,
+}
+
+/// A glTF scene flattened into the renderer's own structures.
+///
+/// Every mesh primitive referenced by a node becomes its own [`Mesh`] with the node's world
+/// transform baked into the vertices, so the whole scene is drawn as a single [`Model`] and can
+/// itself be placed and repeated with instances. The hierarchy is kept in `nodes` for reference.
+pub struct Scene {
+    pub model: Model,
+    pub nodes: Vec<Node>,
+    pub cameras: Vec<Camera>,
+}
+
+impl Scene {
+    /// Imports the default scene (or the first one) of a `.gltf`/`.glb` file.
+    ///
+    /// Materials become metallic-roughness [`Material`]s with all of their maps and factors.
+    /// Cameras without an aspect ratio use `aspect`.
+    pub fn load_gltf(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        layout: &wgpu::BindGroupLayout,
+        path: impl AsRef<Path>,
+        aspect: f32,
+    ) -> anyhow::Result<Self> {
+        let mipmaps = mipmap::MipmapGenerator::default();
+        Self::load_gltf_cached(device, queue, &mipmaps, layout, path, aspect)
+    }
+
+    /// Like [`Scene::load_gltf`], blitting mip chains with the pipelines `mipmaps` already
+    /// built.
+    pub(crate) fn load_gltf_cached(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        mipmaps: &mipmap::MipmapGenerator,
+        layout: &wgpu::BindGroupLayout,
+        path: impl AsRef<Path>,
+        aspect: f32,
+    ) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let (document, buffers, images) =
+            gltf::import(path).with_context(|| format!("failed to import {}", path.display()))?;
+
+        let load_texture = |texture: gltf::Texture, name: &str, srgb: bool| {
+            let data = &images[texture.source().index()];
+            let img = image_from_gltf(data)?;
+
+            texture::Texture::from_image_cached(
+                device,
+                queue,
+                mipmaps,
+                &image::DynamicImage::ImageRgba8(img),
+                Some(name),
+                &texture::TextureOptions {
+                    srgb,
+                    ..texture_options(&texture.sampler())
+                },
+            )
+        };
+
+        let materials = document
+            .materials()
+            .map(|material| {
+                let pbr = material.pbr_metallic_roughness();
+                let name = material
+                    .name()
+                    .map(str::to_owned)
+                    .unwrap_or_else(|| format!("material {}", material.index().unwrap_or(0)));
+
+                let base_colour = match pbr.base_color_texture() {
+                    Some(info) => load_texture(info.texture(), &name, true)?,
+                    // The factor alone gives the colour
+                    None => texture::Texture::from_colour(device, queue, [255; 4], &name),
+                };
+
+                let mut textures = model::MaterialTextures::new(base_colour);
+                let mut factors = model::MaterialFactors {
+                    base_colour: glam::Vec4::from(pbr.base_color_factor()),
+                    metallic: pbr.metallic_factor(),
+                    roughness: pbr.roughness_factor(),
+                    emissive: glam::Vec3::from(material.emissive_factor()),
+                    ..Default::default()
+                };
+
+                if let Some(info) = pbr.metallic_roughness_texture() {
+                    textures.metallic_roughness = Some(load_texture(info.texture(), &name, false)?);
+                }
+
+                if let Some(normal) = material.normal_texture() {
+                    textures.normal = Some(load_texture(normal.texture(), &name, false)?);
+                    factors.normal_scale = normal.scale();
+                }
+
+                if let Some(occlusion) = material.occlusion_texture() {
+                    textures.occlusion = Some(load_texture(occlusion.texture(), &name, false)?);
+                    factors.occlusion_strength = occlusion.strength();
+                }
+
+                if let Some(info) = material.emissive_texture() {
+                    textures.emissive = Some(load_texture(info.texture(), &name, true)?);
+                }
+
+                Ok(Material::pbr(device, layout, &name, textures, factors))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let scene = document
+            .default_scene()
+            .or_else(|| document.scenes().next())
+            .ok_or_else(|| anyhow::anyhow!("{} contains no scene", path.display()))?;
+
+        let mut importer = Importer {
+            device,
+            buffers: &buffers,
+            aspect,
+            meshes: Vec::new(),
+            nodes: Vec::new(),
+            cameras: Vec::new(),
+        };
+
+        for node in scene.nodes() {
+            importer.visit(&node, None, glam::Mat4::IDENTITY);
+        }
+
+        Ok(Self {
+            model: Model {
+                meshes: importer.meshes,
+                materials,
+            },
+            nodes: importer.nodes,
+            cameras: importer.cameras,
+        })
+    }
+}
+
+struct Importer<'a> {
+    device: &'a wgpu::Device,
+    buffers: &'a [gltf::buffer::Data],
+    aspect: f32,
+    meshes: Vec<Mesh>,
+    nodes: Vec<Node>,
+    cameras: Vec<Camera>,
+}
+
+impl Importer<'_> {
+    fn visit(&mut self, node: &gltf::Node, parent: Option<usize>, parent_world: glam::Mat4) {
+        let local = glam::Mat4::from_cols_array_2d(&node.transform().matrix());
+        let world = parent_world * local;
+        let index = self.nodes.len();
+
+        let first_mesh = self.meshes.len();
+        if let Some(mesh) = node.mesh() {
+            self.load_mesh(&mesh, world);
+        }
+
+        if let Some(camera) = node.camera() {
+            self.load_camera(&camera, world);
+        }
+
+        self.nodes.push(Node {
+            name: node.name().map(str::to_owned),
+            parent,
+            children: Vec::new(),
+            local,
+            world,
+            meshes: first_mesh..self.meshes.len(),
+        });
+
+        if let Some(parent) = parent {
+            self.nodes[parent].children.push(index);
+        }
+
+        for child in node.children() {
+            self.visit(&child, Some(index), world);
+        }
+    }
+
+    fn load_mesh(&mut self, mesh: &gltf::Mesh, world: glam::Mat4) {
+        let normal_matrix = glam::Mat3::from_mat4(world).inverse().transpose();
+
+        for primitive in mesh.primitives() {
+            if primitive.mode() != gltf::mesh::Mode::Triangles {
+                log::warn!("skipping non-triangle primitive in mesh {:?}", mesh.name());
+                continue;
+            }
+
+            let reader = primitive.reader(|buffer| Some(&self.buffers[buffer.index()]));
+
+            let Some(positions) = reader.read_positions() else {
+                log::warn!(
+                    "skipping primitive without positions in mesh {:?}",
+                    mesh.name()
+                );
+                continue;
+            };
+
+            let mut vertices = positions
+                .map(|position| Vertex {
+                    position: world.transform_point3(glam::Vec3::from(position)),
+                    tex_coords: glam::Vec2::ZERO,
+                    normal: glam::Vec3::ZERO,
+                })
+                .collect::<Vec<_>>();
+
+            if let Some(tex_coords) = reader.read_tex_coords(0) {
+                for (vertex, uv) in vertices.iter_mut().zip(tex_coords.into_f32()) {
+                    vertex.tex_coords = glam::Vec2::from(uv);
+                }
+            }
+
+            let mut indices = match reader.read_indices() {
+                Some(indices) => indices.into_u32().collect::<Vec<_>>(),
+                None => (0..vertices.len() as u32).collect(),
+            };
+
+            // Mirroring turns the winding around, which would make front faces back faces
+            if world.determinant() < 0.0 {
+                for triangle in indices.chunks_exact_mut(3) {
+                    triangle.swap(1, 2);
+                }
+            }
+
+            match reader.read_normals() {
+                Some(normals) => {
+                    for (vertex, normal) in vertices.iter_mut().zip(normals) {
+                        vertex.normal = (normal_matrix * glam::Vec3::from(normal)).normalize();
+                    }
+                }
+                None => model::generate_normals(&mut vertices, &indices),
+            }
+
+            let name = mesh.name().unwrap_or("gltf mesh");
+
+            self.meshes.push(Mesh::new(
+                self.device,
+                name,
+                &vertices,
+                &indices,
+                primitive.material().index(),
+            ));
+        }
+    }
+
+    fn load_camera(&mut self, camera: &gltf::Camera, world: glam::Mat4) {
+        let gltf::camera::Projection::Perspective(perspective) = camera.projection() else {
+            log::warn!("skipping orthographic camera {:?}", camera.name());
+            return;
+        };
+
+        // glTF cameras look down their local -Z with +Y up
+        let eye = world.transform_point3(glam::Vec3::ZERO);
+        let forward = world.transform_vector3(glam::Vec3::NEG_Z).normalize();
+
+        self.cameras.push(Camera {
+            eye,
+            target: eye + forward,
+            up: world.transform_vector3(glam::Vec3::Y).normalize(),
+            aspect: perspective.aspect_ratio().unwrap_or(self.aspect),
+            fov_y: perspective.yfov().to_degrees(),
+            z_near: perspective.znear(),
+            z_far: perspective.zfar().unwrap_or(100.0),
+        });
+    }
+}
+
+fn texture_options(sampler: &gltf::texture::Sampler) -> texture::TextureOptions {
+    use gltf::texture::{MagFilter, MinFilter, WrappingMode};
+
+    let address_mode = |mode| match mode {
+        WrappingMode::ClampToEdge => wgpu::AddressMode::ClampToEdge,
+        WrappingMode::MirroredRepeat => wgpu::AddressMode::MirrorRepeat,
+        WrappingMode::Repeat => wgpu::AddressMode::Repeat,
+    };
+
+    let mag_filter = match sampler.mag_filter() {
+        Some(MagFilter::Nearest) => wgpu::FilterMode::Nearest,
+        Some(MagFilter::Linear) | None => wgpu::FilterMode::Linear,
+    };
+
+    let (min_filter, mipmap_filter) = match sampler.min_filter() {
+        Some(MinFilter::Nearest | MinFilter::NearestMipmapNearest) => {
+            (wgpu::FilterMode::Nearest, wgpu::FilterMode::Nearest)
+        }
+        Some(MinFilter::NearestMipmapLinear) => {
+            (wgpu::FilterMode::Nearest, wgpu::FilterMode::Linear)
+        }
+        Some(MinFilter::LinearMipmapNearest) => {
+            (wgpu::FilterMode::Linear, wgpu::FilterMode::Nearest)
+        }
+        Some(MinFilter::Linear | MinFilter::LinearMipmapLinear) | None => {
+            (wgpu::FilterMode::Linear, wgpu::FilterMode::Linear)
+        }
+    };
+
+    texture::TextureOptions {
+        address_mode_u: address_mode(sampler.wrap_s()),
+        address_mode_v: address_mode(sampler.wrap_t()),
+        mag_filter,
+        min_filter,
+        mipmap_filter,
+        ..Default::default()
+    }
+}
+
+fn image_from_gltf(data: &gltf::image::Data) -> anyhow::Result<image::RgbaImage> {
+    let (width, height) = (data.width, data.height);
+    let pixels = data.pixels.clone();
+
+    let img = match data.format {
+        gltf::image::Format::R8G8B8A8 => {
+            image::RgbaImage::from_raw(width, height, pixels).map(image::DynamicImage::from)
+        }
+        gltf::image::Format::R8G8B8 => {
+            image::RgbImage::from_raw(width, height, pixels).map(image::DynamicImage::from)
+        }
+        gltf::image::Format::R8G8 => {
+            image::GrayAlphaImage::from_raw(width, height, pixels).map(image::DynamicImage::from)
+        }
+        gltf::image::Format::R8 => {
+            image::GrayImage::from_raw(width, height, pixels).map(image::DynamicImage::from)
+        }
+        format => anyhow::bail!("unsupported glTF image format {format:?}"),
+    };
+
+    img.map(|img| img.to_rgba8())
+        .ok_or_else(|| anyhow::anyhow!("glTF image data does not match its size"))
+}
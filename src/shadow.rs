@@ -0,0 +1,710 @@
+use std::{collections::HashMap, hash::BuildHasherDefault};
+
+use wgpu::util::DeviceExt;
+
+use crate::{
+    camera::Camera,
+    cubemap,
+    instance::InstanceData,
+    light::{Light, LightKind},
+    model::{Model, Vertex},
+    texture::Texture,
+};
+
+/// Most cascades a shadow map can be split into, the size of the arrays in [`ShadowData`].
+pub const MAX_CASCADES: usize = 4;
+/// Most point lights that can cast shadows at once, the size of the arrays in
+/// [`PointShadowData`].
+pub const MAX_POINT_SHADOWS: usize = 4;
+
+/// How the first directional light casts shadows, see [`crate::Context::set_shadows`].
+///
+/// The camera frustum, up to `distance`, is split into `cascades` slices that each get a
+/// shadow map of their own, so nearby shadows stay sharp while distant ones still show.
+#[derive(Clone, Copy, Debug)]
+pub struct ShadowSettings {
+    /// Width and height of each cascade in texels, at most the device's largest 2D texture.
+    pub resolution: u32,
+    /// Between 1 and [`MAX_CASCADES`].
+    pub cascades: u32,
+    /// Shadows end this far from the camera, or at its `z_far` if that is closer.
+    pub distance: f32,
+    /// Where the cascades are split, from 0 for evenly spaced to 1 for logarithmically
+    /// spaced, which gives the cascades near the camera more detail.
+    pub split_lambda: f32,
+    /// Fraction of each cascade, at its far end, that fades into the next one.
+    pub blend: f32,
+    /// Applied while rendering the shadow map, pushing the stored depths away from the light
+    /// so surfaces do not shadow themselves.
+    pub bias: wgpu::DepthBiasState,
+    /// Texels filtered in each direction around a fragment, 0 gives hard edges and 1 a 3x3
+    /// kernel.
+    pub pcf_radius: u32,
+}
+
+impl Default for ShadowSettings {
+    fn default() -> Self {
+        Self {
+            resolution: 2048,
+            cascades: 4,
+            distance: 50.0,
+            split_lambda: 0.75,
+            blend: 0.1,
+            bias: wgpu::DepthBiasState {
+                constant: 2,
+                slope_scale: 2.0,
+                clamp: 0.0,
+            },
+            pcf_radius: 1,
+        }
+    }
+}
+
+/// One slice of the camera frustum and the light's view of it.
+#[derive(Clone, Copy, Debug)]
+pub struct Cascade {
+    /// Distance from the camera, along its view direction, where the cascade ends.
+    pub split: f32,
+    pub view_projection: glam::Mat4,
+}
+
+/// What the main fragment shader needs to look up the shadow map.
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct ShadowData {
+    pub light_view_projections: [glam::Mat4; MAX_CASCADES],
+    /// [`Cascade::split`] of each cascade.
+    pub splits: glam::Vec4,
+    /// Index of the shadow casting light in the light buffer, -1 without one.
+    pub light_index: i32,
+    pub cascade_count: u32,
+    pub pcf_radius: u32,
+    pub texel_size: f32,
+    pub blend: f32,
+    pub _padding: [u32; 3],
+}
+
+unsafe impl bytemuck::Pod for ShadowData {}
+unsafe impl bytemuck::Zeroable for ShadowData {}
+
+impl ShadowData {
+    pub fn disabled() -> Self {
+        Self {
+            light_view_projections: [glam::Mat4::IDENTITY; MAX_CASCADES],
+            splits: glam::Vec4::ZERO,
+            light_index: -1,
+            cascade_count: 1,
+            pcf_radius: 0,
+            texel_size: 0.0,
+            blend: 0.0,
+            _padding: [0; 3],
+        }
+    }
+}
+
+/// Cascaded depth of the scene as seen from a directional light, rendered before the main
+/// pass.
+pub struct ShadowMap {
+    settings: ShadowSettings,
+    texture: Texture,
+    pipeline: wgpu::RenderPipeline,
+    cascades: Vec<LayerTarget>,
+}
+
+/// A single layer of a shadow map and the uniform it is rendered with.
+struct LayerTarget {
+    view: wgpu::TextureView,
+    buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+}
+
+impl ShadowMap {
+    /// The map of a lone cascade gets a spare layer on `backend`s that need one to sample it as
+    /// an array.
+    pub fn new(device: &wgpu::Device, backend: wgpu::Backend, settings: ShadowSettings) -> Self {
+        let settings = ShadowSettings {
+            resolution: settings
+                .resolution
+                .clamp(1, device.limits().max_texture_dimension_2d),
+            cascades: settings.cascades.clamp(1, MAX_CASCADES as u32),
+            ..settings
+        };
+
+        let texture = Texture::create_shadow_map(
+            device,
+            backend,
+            settings.resolution,
+            settings.cascades,
+            "shadow_map",
+        );
+        let pipeline = shadow_pipeline(device, settings.bias);
+
+        let cascades = (0..settings.cascades)
+            .map(|layer| {
+                let view = texture.texture.create_view(&wgpu::TextureViewDescriptor {
+                    label: Some("Shadow Cascade View"),
+                    dimension: Some(wgpu::TextureViewDimension::D2),
+                    base_array_layer: layer,
+                    array_layer_count: Some(1),
+                    ..Default::default()
+                });
+
+                let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some("Shadow Buffer"),
+                    contents: bytemuck::cast_slice(&[glam::Mat4::IDENTITY]),
+                    usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                });
+
+                let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: Some("Shadow Bind Group"),
+                    layout: &pipeline.get_bind_group_layout(0),
+                    entries: &[wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: buffer.as_entire_binding(),
+                    }],
+                });
+
+                LayerTarget {
+                    view,
+                    buffer,
+                    bind_group,
+                }
+            })
+            .collect();
+
+        Self {
+            settings,
+            texture,
+            pipeline,
+            cascades,
+        }
+    }
+
+    pub fn settings(&self) -> &ShadowSettings {
+        &self.settings
+    }
+
+    pub fn texture(&self) -> &Texture {
+        &self.texture
+    }
+
+    /// Distances from the camera where each cascade ends, blending evenly and logarithmically
+    /// spaced splits by `split_lambda`.
+    pub fn splits(&self, camera: &Camera) -> Vec<f32> {
+        let near = camera.z_near;
+        let far = self.settings.distance.min(camera.z_far);
+        let count = self.settings.cascades;
+
+        (1..=count)
+            .map(|i| {
+                let fraction = i as f32 / count as f32;
+                let uniform = near + (far - near) * fraction;
+                let logarithmic = near * (far / near).powf(fraction);
+
+                uniform + (logarithmic - uniform) * self.settings.split_lambda
+            })
+            .collect()
+    }
+
+    /// Fits an orthographic view of a light shining along `direction` around each slice of
+    /// `camera`'s frustum.
+    ///
+    /// Each slice is bounded by a sphere, so its size does not change as the camera turns, and
+    /// its centre is snapped to whole texels, so shadow edges do not crawl as the camera moves.
+    pub fn cascades(&self, camera: &Camera, direction: glam::Vec3) -> Vec<Cascade> {
+        let direction = direction.normalize_or_zero();
+        let up = if direction.cross(glam::Vec3::Y).length_squared() < 1e-6 {
+            glam::Vec3::Z
+        } else {
+            glam::Vec3::Y
+        };
+        // Only rotates, so snapping in it lines up with the texel grid of every cascade
+        let light_rotation = glam::Mat4::look_at_rh(glam::Vec3::ZERO, direction, up);
+        let view = camera.build_view();
+
+        let mut near = camera.z_near;
+        self.splits(camera)
+            .into_iter()
+            .map(|split| {
+                let projection = glam::Mat4::perspective_rh(
+                    camera.fov_y.to_radians(),
+                    camera.aspect,
+                    near,
+                    split,
+                );
+                let inverse = (projection * view).inverse();
+                let corners = [-1.0, 1.0].into_iter().flat_map(|x| {
+                    [-1.0, 1.0].into_iter().flat_map(move |y| {
+                        [0.0, 1.0]
+                            .into_iter()
+                            .map(move |z| inverse.project_point3(glam::vec3(x, y, z)))
+                    })
+                });
+                let corners = corners.collect::<Vec<_>>();
+
+                let centre = corners.iter().sum::<glam::Vec3>() / corners.len() as f32;
+                let radius = corners
+                    .iter()
+                    .map(|corner| corner.distance(centre))
+                    .fold(0.0, f32::max);
+                // Rounded up so floating point noise does not change the texel size
+                let radius = (radius * 16.0).ceil() / 16.0;
+
+                let texel = 2.0 * radius / self.settings.resolution as f32;
+                let snapped = light_rotation.transform_point3(centre);
+                let snapped = glam::vec3(
+                    (snapped.x / texel).floor() * texel,
+                    (snapped.y / texel).floor() * texel,
+                    snapped.z,
+                );
+                let centre = light_rotation.inverse().transform_point3(snapped);
+
+                // Anything up to two radii towards the light still casts into the slice
+                let eye = centre - direction * 3.0 * radius;
+                let light_view = glam::Mat4::look_at_rh(eye, centre, up);
+                let projection = glam::Mat4::orthographic_rh(
+                    -radius,
+                    radius,
+                    -radius,
+                    radius,
+                    0.0,
+                    4.0 * radius,
+                );
+
+                near = split;
+
+                Cascade {
+                    split,
+                    view_projection: projection * light_view,
+                }
+            })
+            .collect()
+    }
+
+    /// Fits and uploads the cascades, returning what the main pass reads.
+    pub fn update(
+        &self,
+        queue: &wgpu::Queue,
+        camera: &Camera,
+        light_index: usize,
+        direction: glam::Vec3,
+    ) -> ShadowData {
+        let cascades = self.cascades(camera, direction);
+        let mut data = ShadowData {
+            light_index: light_index as i32,
+            cascade_count: cascades.len() as u32,
+            pcf_radius: self.settings.pcf_radius,
+            texel_size: 1.0 / self.settings.resolution as f32,
+            blend: self.settings.blend,
+            ..ShadowData::disabled()
+        };
+
+        for (i, (cascade, target)) in cascades.iter().zip(&self.cascades).enumerate() {
+            queue.write_buffer(
+                &target.buffer,
+                0,
+                bytemuck::cast_slice(&[cascade.view_projection]),
+            );
+
+            data.light_view_projections[i] = cascade.view_projection;
+            data.splits[i] = cascade.split;
+        }
+
+        data
+    }
+
+    /// Records a depth pass of every instance of every model for each cascade.
+    pub fn render(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        models: &[Model],
+        instance_buffer: &wgpu::Buffer,
+        instance_count: u32,
+    ) {
+        for cascade in &self.cascades {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Shadow Pass"),
+                color_attachments: &[],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &cascade.view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: wgpu::StoreOp::Store,
+                    }),
+                    stencil_ops: None,
+                }),
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+
+            render_pass.set_pipeline(&self.pipeline);
+            render_pass.set_bind_group(0, &cascade.bind_group, &[]);
+            render_pass.set_vertex_buffer(1, instance_buffer.slice(..));
+
+            for mesh in models
+                .iter()
+                .flat_map(|model| &model.meshes)
+                .filter(|mesh| mesh.num_elements > 0)
+            {
+                render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+                render_pass
+                    .set_index_buffer(mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+                render_pass.draw_indexed(0..mesh.num_elements, 0, 0..instance_count);
+            }
+        }
+    }
+}
+
+pub(crate) fn shadow_pipeline(
+    device: &wgpu::Device,
+    bias: wgpu::DepthBiasState,
+) -> wgpu::RenderPipeline {
+    let vertex_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("Shadow Vertex Shader"),
+        source: wgpu::ShaderSource::Glsl {
+            shader: include_str!("resources/shaders/shadow.vert").into(),
+            stage: wgpu::naga::ShaderStage::Vertex,
+            defines: HashMap::with_hasher(BuildHasherDefault::default()),
+        },
+    });
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Shadow Pipeline"),
+        layout: None,
+        vertex: wgpu::VertexState {
+            module: &vertex_shader,
+            entry_point: "main",
+            buffers: &[Vertex::descriptor(), InstanceData::descriptor()],
+        },
+        // Only depth is written
+        fragment: None,
+        // Nothing is culled in the main pass either, so both sides cast shadows
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: wgpu::TextureFormat::Depth32Float,
+            depth_write_enabled: true,
+            depth_compare: wgpu::CompareFunction::Less,
+            stencil: wgpu::StencilState::default(),
+            bias,
+        }),
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+    })
+}
+
+/// How point lights cast shadows, see [`crate::Context::set_point_shadows`].
+#[derive(Clone, Copy, Debug)]
+pub struct PointShadowSettings {
+    /// Width and height of each cube face in texels, at most the device's largest 2D texture.
+    pub resolution: u32,
+    /// How many point lights cast shadows, the first ones in the light list. Between 1 and
+    /// [`MAX_POINT_SHADOWS`].
+    pub lights: u32,
+    pub near: f32,
+    /// Nothing further than this from a light is shadowed by it.
+    pub far: f32,
+    /// In world units, subtracted from a fragment's distance to the light before comparing
+    /// it with the stored distance.
+    pub bias: f32,
+}
+
+impl Default for PointShadowSettings {
+    fn default() -> Self {
+        Self {
+            resolution: 512,
+            lights: 1,
+            near: 0.05,
+            far: 25.0,
+            bias: 0.05,
+        }
+    }
+}
+
+/// What the main fragment shader needs to look up the point light shadow maps.
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct PointShadowData {
+    /// Position of each shadow casting light, `w` holds the far plane.
+    pub lights: [glam::Vec4; MAX_POINT_SHADOWS],
+    /// Index of each shadow casting light in the light buffer.
+    pub light_indices: [i32; MAX_POINT_SHADOWS],
+    pub count: u32,
+    pub bias: f32,
+    pub _padding: [u32; 2],
+}
+
+unsafe impl bytemuck::Pod for PointShadowData {}
+unsafe impl bytemuck::Zeroable for PointShadowData {}
+
+impl PointShadowData {
+    pub fn disabled() -> Self {
+        Self {
+            lights: [glam::Vec4::ZERO; MAX_POINT_SHADOWS],
+            light_indices: [-1; MAX_POINT_SHADOWS],
+            count: 0,
+            bias: 0.0,
+            _padding: [0; 2],
+        }
+    }
+}
+
+/// Distance from point lights to the nearest surface in every direction, stored in one depth
+/// cube per light and rendered before the main pass.
+pub struct PointShadowMap {
+    settings: PointShadowSettings,
+    texture: Texture,
+    pipeline: wgpu::RenderPipeline,
+    /// Six per light, in cube face order.
+    faces: Vec<LayerTarget>,
+}
+
+impl PointShadowMap {
+    /// Like [`ShadowMap::new`], a lone cube gets a spare one where `backend` needs it.
+    pub fn new(
+        device: &wgpu::Device,
+        backend: wgpu::Backend,
+        settings: PointShadowSettings,
+    ) -> Self {
+        let settings = PointShadowSettings {
+            resolution: settings
+                .resolution
+                .clamp(1, device.limits().max_texture_dimension_2d),
+            lights: settings.lights.clamp(1, MAX_POINT_SHADOWS as u32),
+            ..settings
+        };
+
+        let texture = Texture::create_shadow_cube_map(
+            device,
+            backend,
+            settings.resolution,
+            settings.lights,
+            "point_shadow_map",
+        );
+        let pipeline = point_shadow_pipeline(device);
+
+        let faces = (0..settings.lights * cubemap::FACES)
+            .map(|layer| {
+                let view = texture.texture.create_view(&wgpu::TextureViewDescriptor {
+                    label: Some("Point Shadow Face View"),
+                    dimension: Some(wgpu::TextureViewDimension::D2),
+                    base_array_layer: layer,
+                    array_layer_count: Some(1),
+                    ..Default::default()
+                });
+
+                let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some("Point Shadow Buffer"),
+                    contents: bytemuck::cast_slice(&[FaceData {
+                        view_projection: glam::Mat4::IDENTITY,
+                        light_position: glam::Vec4::ONE,
+                    }]),
+                    usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                });
+
+                let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: Some("Point Shadow Bind Group"),
+                    layout: &pipeline.get_bind_group_layout(0),
+                    entries: &[wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: buffer.as_entire_binding(),
+                    }],
+                });
+
+                LayerTarget {
+                    view,
+                    buffer,
+                    bind_group,
+                }
+            })
+            .collect();
+
+        Self {
+            settings,
+            texture,
+            pipeline,
+            faces,
+        }
+    }
+
+    pub fn settings(&self) -> &PointShadowSettings {
+        &self.settings
+    }
+
+    pub fn texture(&self) -> &Texture {
+        &self.texture
+    }
+
+    /// The point lights that cast shadows, with their index in `lights`.
+    pub fn casters(&self, lights: &[Light]) -> Vec<(usize, glam::Vec3)> {
+        lights
+            .iter()
+            .enumerate()
+            .filter_map(|(index, light)| match light.kind {
+                LightKind::Point { position } => Some((index, position)),
+                _ => None,
+            })
+            .take(self.settings.lights as usize)
+            .collect()
+    }
+
+    /// View-projection of one face of the cube around `position`, oriented so the rendered
+    /// image lines up with how cube samplers pick texels.
+    pub fn face_view_projection(&self, position: glam::Vec3, face: u32) -> glam::Mat4 {
+        let (forward, up) = match face {
+            0 => (glam::Vec3::X, glam::Vec3::Y),
+            1 => (glam::Vec3::NEG_X, glam::Vec3::Y),
+            2 => (glam::Vec3::Y, glam::Vec3::NEG_Z),
+            3 => (glam::Vec3::NEG_Y, glam::Vec3::Z),
+            4 => (glam::Vec3::Z, glam::Vec3::Y),
+            _ => (glam::Vec3::NEG_Z, glam::Vec3::Y),
+        };
+
+        let view = glam::Mat4::look_at_rh(position, position + forward, up);
+        let projection = glam::Mat4::perspective_rh(
+            std::f32::consts::FRAC_PI_2,
+            1.0,
+            self.settings.near,
+            self.settings.far,
+        );
+
+        // Cube maps are addressed left-handed, so the right-handed view comes out mirrored
+        glam::Mat4::from_scale(glam::vec3(-1.0, 1.0, 1.0)) * projection * view
+    }
+
+    /// Uploads the faces of every shadow casting light, returning what the main pass reads.
+    pub fn update(&self, queue: &wgpu::Queue, lights: &[Light]) -> PointShadowData {
+        let mut data = PointShadowData {
+            bias: self.settings.bias,
+            ..PointShadowData::disabled()
+        };
+
+        for (slot, (index, position)) in self.casters(lights).into_iter().enumerate() {
+            let light_position = position.extend(self.settings.far);
+
+            for face in 0..cubemap::FACES {
+                let target = &self.faces[slot * cubemap::FACES as usize + face as usize];
+
+                queue.write_buffer(
+                    &target.buffer,
+                    0,
+                    bytemuck::cast_slice(&[FaceData {
+                        view_projection: self.face_view_projection(position, face),
+                        light_position,
+                    }]),
+                );
+            }
+
+            data.lights[slot] = light_position;
+            data.light_indices[slot] = index as i32;
+            data.count += 1;
+        }
+
+        data
+    }
+
+    /// Records a depth pass of every instance of every model for each face of the first
+    /// `light_count` cubes.
+    pub fn render(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        models: &[Model],
+        instance_buffer: &wgpu::Buffer,
+        instance_count: u32,
+        light_count: usize,
+    ) {
+        for face in self
+            .faces
+            .iter()
+            .take(light_count * cubemap::FACES as usize)
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Point Shadow Pass"),
+                color_attachments: &[],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &face.view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: wgpu::StoreOp::Store,
+                    }),
+                    stencil_ops: None,
+                }),
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+
+            render_pass.set_pipeline(&self.pipeline);
+            render_pass.set_bind_group(0, &face.bind_group, &[]);
+            render_pass.set_vertex_buffer(1, instance_buffer.slice(..));
+
+            for mesh in models
+                .iter()
+                .flat_map(|model| &model.meshes)
+                .filter(|mesh| mesh.num_elements > 0)
+            {
+                render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+                render_pass
+                    .set_index_buffer(mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+                render_pass.draw_indexed(0..mesh.num_elements, 0, 0..instance_count);
+            }
+        }
+    }
+}
+
+/// Uniform of a single cube face in the point shadow pass.
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+struct FaceData {
+    view_projection: glam::Mat4,
+    /// `w` holds the far plane.
+    light_position: glam::Vec4,
+}
+
+unsafe impl bytemuck::Pod for FaceData {}
+unsafe impl bytemuck::Zeroable for FaceData {}
+
+fn point_shadow_pipeline(device: &wgpu::Device) -> wgpu::RenderPipeline {
+    let vertex_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("Point Shadow Vertex Shader"),
+        source: wgpu::ShaderSource::Glsl {
+            shader: include_str!("resources/shaders/point_shadow.vert").into(),
+            stage: wgpu::naga::ShaderStage::Vertex,
+            defines: HashMap::with_hasher(BuildHasherDefault::default()),
+        },
+    });
+
+    let fragment_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("Point Shadow Fragment Shader"),
+        source: wgpu::ShaderSource::Glsl {
+            shader: include_str!("resources/shaders/point_shadow.frag").into(),
+            stage: wgpu::naga::ShaderStage::Fragment,
+            defines: HashMap::with_hasher(BuildHasherDefault::default()),
+        },
+    });
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Point Shadow Pipeline"),
+        layout: None,
+        vertex: wgpu::VertexState {
+            module: &vertex_shader,
+            entry_point: "main",
+            buffers: &[Vertex::descriptor(), InstanceData::descriptor()],
+        },
+        // Writes the linear distance as depth, there is no colour target
+        fragment: Some(wgpu::FragmentState {
+            module: &fragment_shader,
+            entry_point: "main",
+            targets: &[],
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: wgpu::TextureFormat::Depth32Float,
+            depth_write_enabled: true,
+            depth_compare: wgpu::CompareFunction::Less,
+            stencil: wgpu::StencilState::default(),
+            // Written depth is not biased by the rasteriser, the shader compares with a bias
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+    })
+}
@@ -0,0 +1,143 @@
+use std::{collections::HashMap, hash::BuildHasherDefault};
+
+use wgpu::util::DeviceExt;
+
+use crate::{camera::Camera, texture::Texture};
+
+/// Background drawn from a cubemap after the scene, on the far plane, so it only shows where
+/// no geometry was drawn.
+pub struct Skybox {
+    cubemap: Texture,
+    pipeline: wgpu::RenderPipeline,
+    buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+}
+
+impl Skybox {
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+        cubemap: Texture,
+        camera: &Camera,
+    ) -> Self {
+        let pipeline = skybox_pipeline(device, format, sample_count);
+
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Skybox Buffer"),
+            contents: bytemuck::cast_slice(&[Self::matrix(camera)]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Skybox Bind Group"),
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&cubemap.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&cubemap.sampler),
+                },
+            ],
+        });
+
+        Self {
+            cubemap,
+            pipeline,
+            buffer,
+            bind_group,
+        }
+    }
+
+    pub fn cubemap(&self) -> &Texture {
+        &self.cubemap
+    }
+
+    /// Gives the cubemap back, to build a skybox for a different target with it.
+    pub fn into_cubemap(self) -> Texture {
+        self.cubemap
+    }
+
+    /// Turns the screen position of a sky pixel back into a view direction.
+    fn matrix(camera: &Camera) -> glam::Mat4 {
+        camera.build_rotation_projection().inverse()
+    }
+
+    pub fn update(&self, queue: &wgpu::Queue, camera: &Camera) {
+        queue.write_buffer(
+            &self.buffer,
+            0,
+            bytemuck::cast_slice(&[Self::matrix(camera)]),
+        );
+    }
+
+    /// Draws into a pass whose depth buffer already holds the scene.
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+fn skybox_pipeline(
+    device: &wgpu::Device,
+    format: wgpu::TextureFormat,
+    sample_count: u32,
+) -> wgpu::RenderPipeline {
+    let vertex_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("Skybox Vertex Shader"),
+        source: wgpu::ShaderSource::Glsl {
+            shader: include_str!("resources/shaders/skybox.vert").into(),
+            stage: wgpu::naga::ShaderStage::Vertex,
+            defines: HashMap::with_hasher(BuildHasherDefault::default()),
+        },
+    });
+
+    let fragment_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("Skybox Fragment Shader"),
+        source: wgpu::ShaderSource::Glsl {
+            shader: include_str!("resources/shaders/skybox.frag").into(),
+            stage: wgpu::naga::ShaderStage::Fragment,
+            defines: HashMap::with_hasher(BuildHasherDefault::default()),
+        },
+    });
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Skybox Pipeline"),
+        // Derived from the shaders, the bind group layout is read back from the pipeline
+        layout: None,
+        vertex: wgpu::VertexState {
+            module: &vertex_shader,
+            entry_point: "main",
+            buffers: &[],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &fragment_shader,
+            entry_point: "main",
+            targets: &[Some(format.into())],
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        // The sky sits exactly on the far plane: it passes where the depth buffer is still
+        // cleared and never hides anything
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: wgpu::TextureFormat::Depth32Float,
+            depth_write_enabled: false,
+            depth_compare: wgpu::CompareFunction::LessEqual,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        // Drawn in the main pass, so it must match its attachments
+        multisample: wgpu::MultisampleState {
+            count: sample_count,
+            ..Default::default()
+        },
+        multiview: None,
+    })
+}
@@ -0,0 +1,567 @@
+use std::{collections::HashMap, hash::BuildHasherDefault};
+
+use rand::{Rng, SeedableRng};
+use wgpu::util::DeviceExt;
+
+use crate::{camera::Camera, culling::InstanceDraws, model::Model, shadow, texture::Texture};
+
+/// Most samples taken per pixel, the size of the kernel in [`SsaoData`].
+pub const MAX_SSAO_SAMPLES: usize = 64;
+
+/// How screen-space ambient occlusion darkens creases and contact points, see
+/// [`crate::Context::set_ssao`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SsaoSettings {
+    /// View space radius of the hemisphere searched for occluders around each point.
+    pub radius: f32,
+    /// Depth difference an occluder needs before it counts, so flat surfaces do not occlude
+    /// themselves.
+    pub bias: f32,
+    /// Exponent on the result, above 1 darkens the occluded areas further.
+    pub power: f32,
+    /// Samples per pixel, between 1 and [`MAX_SSAO_SAMPLES`].
+    pub samples: u32,
+    /// Pixels blurred on each side, 0 leaves the noise pattern in.
+    pub blur_radius: u32,
+}
+
+impl Default for SsaoSettings {
+    fn default() -> Self {
+        Self {
+            radius: 0.5,
+            bias: 0.025,
+            power: 1.5,
+            samples: 32,
+            blur_radius: 2,
+        }
+    }
+}
+
+/// What the occlusion and blur shaders need.
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+struct SsaoData {
+    projection: glam::Mat4,
+    inverse_projection: glam::Mat4,
+    kernel: [glam::Vec4; MAX_SSAO_SAMPLES],
+    radius: f32,
+    bias: f32,
+    power: f32,
+    sample_count: u32,
+    blur_radius: u32,
+    _padding: [u32; 3],
+}
+
+unsafe impl bytemuck::Pod for SsaoData {}
+unsafe impl bytemuck::Zeroable for SsaoData {}
+
+/// Ambient occlusion from a depth prepass of the scene, blurred and then read by the main
+/// pass at each fragment's pixel.
+pub(crate) struct Ssao {
+    settings: SsaoSettings,
+    kernel: [glam::Vec4; MAX_SSAO_SAMPLES],
+    layout: wgpu::BindGroupLayout,
+    noise: Texture,
+    sampler: wgpu::Sampler,
+    buffer: wgpu::Buffer,
+    /// The camera's view-projection, the prepass runs `shadow.vert` from its point of view.
+    prepass_buffer: wgpu::Buffer,
+    prepass_bind_group: wgpu::BindGroup,
+    prepass: wgpu::RenderPipeline,
+    occlusion: wgpu::RenderPipeline,
+    horizontal: wgpu::RenderPipeline,
+    vertical: wgpu::RenderPipeline,
+    targets: Targets,
+}
+
+/// The size dependent part of [`Ssao`], recreated on resize.
+struct Targets {
+    depth: Texture,
+    /// Raw occlusion, then blurred horizontally into `blurred` and back vertically.
+    occlusion: Texture,
+    blurred: Texture,
+    occlusion_bind_group: wgpu::BindGroup,
+    horizontal_bind_group: wgpu::BindGroup,
+    vertical_bind_group: wgpu::BindGroup,
+}
+
+impl Ssao {
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        config: &wgpu::SurfaceConfiguration,
+        camera: &Camera,
+        settings: SsaoSettings,
+    ) -> Self {
+        // A fixed seed, so the same settings always give the same image
+        let mut rng = rand::rngs::StdRng::seed_from_u64(0x55a0);
+        let samples = settings.samples.clamp(1, MAX_SSAO_SAMPLES as u32);
+
+        // Points in the +Z hemisphere, more of them close to the centre where occluders
+        // matter most
+        let mut kernel = [glam::Vec4::ZERO; MAX_SSAO_SAMPLES];
+        for (i, sample) in kernel.iter_mut().take(samples as usize).enumerate() {
+            let direction = glam::vec3(
+                rng.gen_range(-1.0..1.0),
+                rng.gen_range(-1.0..1.0),
+                rng.gen_range(0.0..1.0),
+            )
+            .normalize_or_zero();
+            let scale = i as f32 / samples as f32;
+
+            *sample = (direction * rng.gen::<f32>() * (0.1 + 0.9 * scale * scale)).extend(0.0);
+        }
+
+        // Random rotations around the normal, tiled over the screen
+        let noise = (0..16)
+            .flat_map(|_| {
+                let x = rng.gen_range(-127..=127i8);
+                let y = rng.gen_range(-127..=127i8);
+
+                [x, y, 0, 0].map(|channel| channel as u8)
+            })
+            .collect::<Vec<_>>();
+        let noise = noise_texture(device, queue, &noise);
+
+        // Everything is read with texelFetch, depth cannot be filtered anyway
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("SSAO Sampler"),
+            ..Default::default()
+        });
+        let layout = ssao_layout(device);
+
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("SSAO Buffer"),
+            contents: bytemuck::cast_slice(&[Self::data(&settings, samples, &kernel, camera)]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        // The prepass is a shadow pass from the camera's point of view, without the bias
+        let prepass = shadow::shadow_pipeline(device, wgpu::DepthBiasState::default());
+        let prepass_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("SSAO Prepass Buffer"),
+            contents: bytemuck::cast_slice(&[camera.build_view_projection()]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let prepass_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("SSAO Prepass Bind Group"),
+            layout: &prepass.get_bind_group_layout(0),
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: prepass_buffer.as_entire_binding(),
+            }],
+        });
+
+        let ssao_shader = include_str!("resources/shaders/ssao.frag");
+        let blur_shader = include_str!("resources/shaders/ssao_blur.frag");
+        let mut horizontal_defines = HashMap::with_hasher(BuildHasherDefault::default());
+        horizontal_defines.insert("HORIZONTAL".to_owned(), "1".to_owned());
+
+        let occlusion = ssao_pipeline(
+            device,
+            &layout,
+            "SSAO",
+            ssao_shader,
+            HashMap::with_hasher(BuildHasherDefault::default()),
+        );
+        let horizontal = ssao_pipeline(
+            device,
+            &layout,
+            "SSAO Blur",
+            blur_shader,
+            horizontal_defines,
+        );
+        let vertical = ssao_pipeline(
+            device,
+            &layout,
+            "SSAO Blur",
+            blur_shader,
+            HashMap::with_hasher(BuildHasherDefault::default()),
+        );
+
+        let targets = Targets::new(device, config, &layout, &noise, &sampler, &buffer);
+
+        Self {
+            settings,
+            kernel,
+            layout,
+            noise,
+            sampler,
+            buffer,
+            prepass_buffer,
+            prepass_bind_group,
+            prepass,
+            occlusion,
+            horizontal,
+            vertical,
+            targets,
+        }
+    }
+
+    fn data(
+        settings: &SsaoSettings,
+        samples: u32,
+        kernel: &[glam::Vec4; MAX_SSAO_SAMPLES],
+        camera: &Camera,
+    ) -> SsaoData {
+        let projection = camera.build_projection();
+
+        SsaoData {
+            projection,
+            inverse_projection: projection.inverse(),
+            kernel: *kernel,
+            radius: settings.radius,
+            bias: settings.bias,
+            power: settings.power,
+            sample_count: samples,
+            blur_radius: settings.blur_radius,
+            _padding: [0; 3],
+        }
+    }
+
+    pub fn settings(&self) -> &SsaoSettings {
+        &self.settings
+    }
+
+    /// The blurred occlusion, one texel per pixel of the surface.
+    pub fn texture(&self) -> &Texture {
+        &self.targets.occlusion
+    }
+
+    /// Follows the surface to a new size.
+    pub fn resize(&mut self, device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) {
+        self.targets = Targets::new(
+            device,
+            config,
+            &self.layout,
+            &self.noise,
+            &self.sampler,
+            &self.buffer,
+        );
+    }
+
+    pub fn update(&self, queue: &wgpu::Queue, camera: &Camera) {
+        let samples = self.settings.samples.clamp(1, MAX_SSAO_SAMPLES as u32);
+
+        queue.write_buffer(
+            &self.buffer,
+            0,
+            bytemuck::cast_slice(&[Self::data(&self.settings, samples, &self.kernel, camera)]),
+        );
+        queue.write_buffer(
+            &self.prepass_buffer,
+            0,
+            bytemuck::cast_slice(&[camera.build_view_projection()]),
+        );
+    }
+
+    /// Renders the scene's depth, then the occlusion it implies and blurs it.
+    pub fn render(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        models: &[Model],
+        instance_buffer: &wgpu::Buffer,
+        draws: InstanceDraws,
+    ) {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("SSAO Prepass"),
+            color_attachments: &[],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &self.targets.depth.view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+
+        // Empty buffers cannot be bound, an empty scene leaves the depth cleared and nothing
+        // occluded
+        if !draws.is_empty() {
+            render_pass.set_pipeline(&self.prepass);
+            render_pass.set_bind_group(0, &self.prepass_bind_group, &[]);
+            render_pass.set_vertex_buffer(1, instance_buffer.slice(..));
+
+            for (index, mesh) in models
+                .iter()
+                .flat_map(|model| &model.meshes)
+                .enumerate()
+                .filter(|(_, mesh)| mesh.num_elements > 0)
+            {
+                render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+                render_pass
+                    .set_index_buffer(mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+                draws.draw(&mut render_pass, index, mesh);
+            }
+        }
+
+        drop(render_pass);
+
+        let targets = &self.targets;
+        let passes = [
+            (
+                &self.occlusion,
+                &targets.occlusion_bind_group,
+                &targets.occlusion,
+            ),
+            (
+                &self.horizontal,
+                &targets.horizontal_bind_group,
+                &targets.blurred,
+            ),
+            (
+                &self.vertical,
+                &targets.vertical_bind_group,
+                &targets.occlusion,
+            ),
+        ];
+
+        // Without a blur the raw occlusion is used as it is
+        let passes = if self.settings.blur_radius == 0 {
+            &passes[..1]
+        } else {
+            &passes[..]
+        };
+
+        for (pipeline, bind_group, target) in passes {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("SSAO Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &target.view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::WHITE),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+
+            pass.set_pipeline(pipeline);
+            pass.set_bind_group(0, bind_group, &[]);
+            pass.draw(0..3, 0..1);
+        }
+    }
+}
+
+impl Targets {
+    fn new(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        layout: &wgpu::BindGroupLayout,
+        noise: &Texture,
+        sampler: &wgpu::Sampler,
+        buffer: &wgpu::Buffer,
+    ) -> Self {
+        let depth = Texture::create_depth_texture(device, config, 1, "ssao_depth");
+        let occlusion = occlusion_target(device, config, "ssao_occlusion");
+        let blurred = occlusion_target(device, config, "ssao_blurred");
+
+        let bind_group = |first: &wgpu::TextureView, second| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("SSAO Bind Group"),
+                layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(first),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::TextureView(second),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: wgpu::BindingResource::Sampler(sampler),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 3,
+                        resource: buffer.as_entire_binding(),
+                    },
+                ],
+            })
+        };
+
+        let occlusion_bind_group = bind_group(&depth.view, &noise.view);
+        let horizontal_bind_group = bind_group(&occlusion.view, &depth.view);
+        let vertical_bind_group = bind_group(&blurred.view, &depth.view);
+
+        Self {
+            depth,
+            occlusion,
+            blurred,
+            occlusion_bind_group,
+            horizontal_bind_group,
+            vertical_bind_group,
+        }
+    }
+}
+
+fn occlusion_target(
+    device: &wgpu::Device,
+    config: &wgpu::SurfaceConfiguration,
+    label: &str,
+) -> Texture {
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some(label),
+        size: wgpu::Extent3d {
+            width: config.width,
+            height: config.height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::R8Unorm,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        view_formats: &[],
+    });
+
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+    let sampler = device.create_sampler(&wgpu::SamplerDescriptor::default());
+
+    Texture {
+        texture,
+        view,
+        sampler,
+    }
+}
+
+/// 4x4 tile of random directions in the XY plane.
+fn noise_texture(device: &wgpu::Device, queue: &wgpu::Queue, data: &[u8]) -> Texture {
+    let size = wgpu::Extent3d {
+        width: 4,
+        height: 4,
+        depth_or_array_layers: 1,
+    };
+
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("ssao_noise"),
+        size,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::Rgba8Snorm,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        view_formats: &[],
+    });
+
+    queue.write_texture(
+        wgpu::ImageCopyTexture {
+            aspect: wgpu::TextureAspect::All,
+            texture: &texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+        },
+        data,
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: Some(4 * size.width),
+            rows_per_image: Some(size.height),
+        },
+        size,
+    );
+
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+    let sampler = device.create_sampler(&wgpu::SamplerDescriptor::default());
+
+    Texture {
+        texture,
+        view,
+        sampler,
+    }
+}
+
+/// Two textures, a sampler and the settings, shared by the occlusion pass and both blurs.
+fn ssao_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+    let texture = |binding| wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Texture {
+            multisampled: false,
+            view_dimension: wgpu::TextureViewDimension::D2,
+            sample_type: wgpu::TextureSampleType::Float { filterable: false },
+        },
+        count: None,
+    };
+
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("SSAO Layout"),
+        entries: &[
+            texture(0),
+            texture(1),
+            wgpu::BindGroupLayoutEntry {
+                binding: 2,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::NonFiltering),
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 3,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+        ],
+    })
+}
+
+fn ssao_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    label: &str,
+    shader: &str,
+    defines: wgpu::naga::FastHashMap<String, String>,
+) -> wgpu::RenderPipeline {
+    let vertex_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("Fullscreen Shader"),
+        source: wgpu::ShaderSource::Glsl {
+            shader: include_str!("resources/shaders/fullscreen.vert").into(),
+            stage: wgpu::naga::ShaderStage::Vertex,
+            defines: HashMap::with_hasher(BuildHasherDefault::default()),
+        },
+    });
+
+    let fragment_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some(label),
+        source: wgpu::ShaderSource::Glsl {
+            shader: shader.into(),
+            stage: wgpu::naga::ShaderStage::Fragment,
+            defines,
+        },
+    });
+
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some(label),
+        bind_group_layouts: &[layout],
+        push_constant_ranges: &[],
+    });
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some(label),
+        layout: Some(&pipeline_layout),
+        vertex: wgpu::VertexState {
+            module: &vertex_shader,
+            entry_point: "main",
+            buffers: &[],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &fragment_shader,
+            entry_point: "main",
+            targets: &[Some(wgpu::TextureFormat::R8Unorm.into())],
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+    })
+}
@@ -1,5 +1,101 @@
 use image::GenericImageView;
 
+use crate::{compressed, cubemap, mipmap};
+
+/// How an image is uploaded and sampled by [`Texture::from_image_with_options`].
+///
+/// The default is a clamped, trilinearly filtered sRGB colour texture with a full mip chain.
+#[derive(Clone, Copy, Debug)]
+pub struct TextureOptions {
+    pub address_mode_u: wgpu::AddressMode,
+    pub address_mode_v: wgpu::AddressMode,
+    pub mag_filter: wgpu::FilterMode,
+    pub min_filter: wgpu::FilterMode,
+    pub mipmap_filter: wgpu::FilterMode,
+    /// Maximum anisotropy, 1 disables anisotropic filtering. Anything above 1 requires every
+    /// filter to be `Linear`.
+    pub anisotropy_clamp: u16,
+    /// Colour textures are sRGB; normal maps and other data textures must be linear.
+    pub srgb: bool,
+    pub generate_mipmaps: bool,
+    /// Added to the usages the upload itself needs.
+    pub usage: wgpu::TextureUsages,
+}
+
+impl Default for TextureOptions {
+    fn default() -> Self {
+        Self {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            anisotropy_clamp: 1,
+            srgb: true,
+            generate_mipmaps: true,
+            usage: wgpu::TextureUsages::empty(),
+        }
+    }
+}
+
+impl TextureOptions {
+    /// Repeats in both directions, for tiling textures such as ground or walls.
+    pub fn tiling() -> Self {
+        Self {
+            address_mode_u: wgpu::AddressMode::Repeat,
+            address_mode_v: wgpu::AddressMode::Repeat,
+            ..Default::default()
+        }
+    }
+
+    /// Linear format, for normal maps and other non-colour data.
+    pub fn data() -> Self {
+        Self {
+            srgb: false,
+            ..Default::default()
+        }
+    }
+
+    pub fn format(&self) -> wgpu::TextureFormat {
+        if self.srgb {
+            wgpu::TextureFormat::Rgba8UnormSrgb
+        } else {
+            wgpu::TextureFormat::Rgba8Unorm
+        }
+    }
+
+    pub fn create_sampler(&self, device: &wgpu::Device) -> wgpu::Sampler {
+        device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: self.address_mode_u,
+            address_mode_v: self.address_mode_v,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: self.mag_filter,
+            min_filter: self.min_filter,
+            mipmap_filter: self.mipmap_filter,
+            anisotropy_clamp: self.anisotropy_clamp,
+            ..Default::default()
+        })
+    }
+
+    fn validate(&self) -> anyhow::Result<()> {
+        let all_linear = [self.mag_filter, self.min_filter, self.mipmap_filter]
+            .iter()
+            .all(|filter| *filter == wgpu::FilterMode::Linear);
+
+        anyhow::ensure!(
+            (1..=16).contains(&self.anisotropy_clamp),
+            "anisotropy clamp must be between 1 and 16, got {}",
+            self.anisotropy_clamp
+        );
+        anyhow::ensure!(
+            self.anisotropy_clamp == 1 || all_linear,
+            "anisotropic filtering requires linear mag, min and mipmap filters"
+        );
+
+        Ok(())
+    }
+}
+
 pub struct Texture {
     pub texture: wgpu::Texture,
     pub view: wgpu::TextureView,
@@ -7,39 +103,523 @@ pub struct Texture {
 }
 
 impl Texture {
+    /// Format the scene is rendered in before tonemapping, wide enough for colours above 1.
+    pub const HDR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
     pub fn from_file(
         device: &wgpu::Device,
         queue: &wgpu::Queue,
         path: &str,
         label: &str,
     ) -> anyhow::Result<Self> {
-        let img = image::io::Reader::open(path)?.decode()?;
-        Self::from_image(device, queue, &img, Some(label))
+        Self::from_file_with_options(device, queue, path, label, &TextureOptions::default())
+    }
+
+    pub fn from_file_with_options(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        path: &str,
+        label: &str,
+        options: &TextureOptions,
+    ) -> anyhow::Result<Self> {
+        let mipmaps = mipmap::MipmapGenerator::default();
+        Self::from_file_cached(device, queue, &mipmaps, path, label, options)
+    }
+
+    /// Like [`Texture::from_file_with_options`], blitting mip chains with the pipelines
+    /// `mipmaps` already built.
+    pub(crate) fn from_file_cached(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        mipmaps: &mipmap::MipmapGenerator,
+        path: &str,
+        label: &str,
+        options: &TextureOptions,
+    ) -> anyhow::Result<Self> {
+        let extension = std::path::Path::new(path)
+            .extension()
+            .map(|extension| extension.to_ascii_lowercase());
+
+        // Pre-compressed containers carry their own format and mip chain
+        match extension.as_ref().and_then(|extension| extension.to_str()) {
+            Some("ktx2") => {
+                let data = compressed::TextureData::from_ktx2(&std::fs::read(path)?)?;
+                Self::from_texture_data(device, queue, &data, Some(label), options)
+            }
+            Some("dds") => {
+                let data = compressed::TextureData::from_dds(&std::fs::read(path)?)?;
+                Self::from_texture_data(device, queue, &data, Some(label), options)
+            }
+            // High dynamic range images keep values above 1
+            Some("hdr" | "exr") => Self::from_hdr_image_cached(
+                device,
+                queue,
+                mipmaps,
+                &Self::read_hdr(path)?,
+                Some(label),
+                wgpu::TextureFormat::Rgba16Float,
+                options,
+            ),
+            _ => {
+                let img = image::io::Reader::open(path)?.decode()?;
+                Self::from_image_cached(device, queue, mipmaps, &img, Some(label), options)
+            }
+        }
+    }
+
+    /// Loads a Radiance `.hdr` or OpenEXR `.exr` file without clamping, see
+    /// [`Texture::from_hdr_image`].
+    pub fn from_hdr_file(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        path: &str,
+        label: &str,
+        format: wgpu::TextureFormat,
+        options: &TextureOptions,
+    ) -> anyhow::Result<Self> {
+        Self::from_hdr_image(
+            device,
+            queue,
+            &Self::read_hdr(path)?,
+            Some(label),
+            format,
+            options,
+        )
+    }
+
+    fn read_hdr(path: &str) -> anyhow::Result<image::Rgba32FImage> {
+        Ok(match image::ImageFormat::from_path(path)? {
+            // The generic Radiance decoder tone maps to 8 bits, read the raw floats instead
+            image::ImageFormat::Hdr => {
+                let reader = std::io::BufReader::new(std::fs::File::open(path)?);
+                let decoder = image::codecs::hdr::HdrDecoder::new(reader)?;
+                let metadata = decoder.metadata();
+                let pixels = decoder.read_image_hdr()?;
+
+                image::Rgba32FImage::from_fn(metadata.width, metadata.height, |x, y| {
+                    let [r, g, b] = pixels[(y * metadata.width + x) as usize].0;
+                    image::Rgba([r, g, b, 1.0])
+                })
+            }
+            _ => image::io::Reader::open(path)?.decode()?.into_rgba32f(),
+        })
+    }
+
+    /// Uploads linear float pixels to an `Rgba16Float` or `Rgba32Float` texture, keeping values
+    /// above 1 for environment maps and lightmaps.
+    ///
+    /// Float textures are always linear, `options.srgb` is ignored. `Rgba32Float` is only
+    /// filterable, and so can only use `Linear` filters or have mips generated, with
+    /// `Features::FLOAT32_FILTERABLE`.
+    pub fn from_hdr_image(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        img: &image::Rgba32FImage,
+        label: Option<&str>,
+        format: wgpu::TextureFormat,
+        options: &TextureOptions,
+    ) -> anyhow::Result<Self> {
+        let mipmaps = mipmap::MipmapGenerator::default();
+        Self::from_hdr_image_cached(device, queue, &mipmaps, img, label, format, options)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn from_hdr_image_cached(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        mipmaps: &mipmap::MipmapGenerator,
+        img: &image::Rgba32FImage,
+        label: Option<&str>,
+        format: wgpu::TextureFormat,
+        options: &TextureOptions,
+    ) -> anyhow::Result<Self> {
+        options.validate()?;
+
+        let pixels = img.as_raw();
+        let (bytes, bytes_per_pixel) = match format {
+            wgpu::TextureFormat::Rgba16Float => {
+                let halves = pixels
+                    .iter()
+                    .map(|value| half::f16::from_f32(*value))
+                    .collect::<Vec<_>>();
+                (bytemuck::cast_slice(&halves).to_vec(), 8)
+            }
+            wgpu::TextureFormat::Rgba32Float => {
+                let filtered = options.generate_mipmaps
+                    || [
+                        options.mag_filter,
+                        options.min_filter,
+                        options.mipmap_filter,
+                    ]
+                    .contains(&wgpu::FilterMode::Linear);
+                anyhow::ensure!(
+                    !filtered
+                        || device
+                            .features()
+                            .contains(wgpu::Features::FLOAT32_FILTERABLE),
+                    "Rgba32Float is not filterable on this device, use Rgba16Float or Nearest filters without mipmap generation"
+                );
+                (bytemuck::cast_slice(pixels).to_vec(), 16)
+            }
+            _ => anyhow::bail!("HDR images upload as Rgba16Float or Rgba32Float, not {format:?}"),
+        };
+
+        Ok(Self::upload(
+            device,
+            queue,
+            mipmaps,
+            &bytes,
+            img.dimensions(),
+            bytes_per_pixel,
+            format,
+            label,
+            options,
+        ))
+    }
+
+    /// Builds a cubemap from six square faces of the same size, in `+X, -X, +Y, -Y, +Z, -Z`
+    /// order.
+    pub fn cube_from_faces(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        faces: &[image::DynamicImage; 6],
+        label: Option<&str>,
+        options: &TextureOptions,
+    ) -> anyhow::Result<Self> {
+        options.validate()?;
+
+        let (size, _) = faces[0].dimensions();
+        for (index, face) in faces.iter().enumerate() {
+            anyhow::ensure!(
+                face.dimensions() == (size, size),
+                "cube face {index} is {:?}, expected {size}x{size}",
+                face.dimensions()
+            );
+        }
+
+        let texture = Self::create_cube(device, size, options.format(), label, options);
+
+        for (layer, face) in faces.iter().enumerate() {
+            queue.write_texture(
+                wgpu::ImageCopyTexture {
+                    aspect: wgpu::TextureAspect::All,
+                    texture: &texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d {
+                        x: 0,
+                        y: 0,
+                        z: layer as u32,
+                    },
+                },
+                &face.to_rgba8(),
+                wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(4 * size),
+                    rows_per_image: Some(size),
+                },
+                wgpu::Extent3d {
+                    width: size,
+                    height: size,
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
+
+        let mipmaps = mipmap::MipmapGenerator::default();
+        Ok(Self::finish_cube(device, queue, &mipmaps, texture, options))
+    }
+
+    /// Builds an `Rgba16Float` cubemap with `face_size` square faces from an equirectangular
+    /// (latitude/longitude) HDR image, projected on the GPU.
+    ///
+    /// The top row of `img` is straight up (+Y) and the centre column looks down +X.
+    pub fn cube_from_equirectangular(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        img: &image::Rgba32FImage,
+        face_size: u32,
+        label: Option<&str>,
+        options: &TextureOptions,
+    ) -> anyhow::Result<Self> {
+        options.validate()?;
+        anyhow::ensure!(face_size > 0, "cube faces must be at least 1x1");
+
+        let equirect = Self::from_hdr_image(
+            device,
+            queue,
+            img,
+            Some("equirectangular_source"),
+            wgpu::TextureFormat::Rgba16Float,
+            &TextureOptions {
+                generate_mipmaps: false,
+                ..Default::default()
+            },
+        )?;
+
+        let texture = Self::create_cube(
+            device,
+            face_size,
+            wgpu::TextureFormat::Rgba16Float,
+            label,
+            &TextureOptions {
+                // The faces are rendered into
+                usage: options.usage | wgpu::TextureUsages::RENDER_ATTACHMENT,
+                ..*options
+            },
+        );
+
+        cubemap::from_equirectangular(device, queue, &equirect.view, &texture);
+
+        let mipmaps = mipmap::MipmapGenerator::default();
+        Ok(Self::finish_cube(device, queue, &mipmaps, texture, options))
     }
 
+    fn create_cube(
+        device: &wgpu::Device,
+        size: u32,
+        format: wgpu::TextureFormat,
+        label: Option<&str>,
+        options: &TextureOptions,
+    ) -> wgpu::Texture {
+        let mut usage =
+            wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST | options.usage;
+
+        let mip_level_count = if options.generate_mipmaps {
+            usage |= wgpu::TextureUsages::RENDER_ATTACHMENT;
+            mipmap::level_count(size, size)
+        } else {
+            1
+        };
+
+        device.create_texture(&wgpu::TextureDescriptor {
+            label,
+            size: wgpu::Extent3d {
+                width: size,
+                height: size,
+                depth_or_array_layers: cubemap::FACES,
+            },
+            mip_level_count,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage,
+            view_formats: &[],
+        })
+    }
+
+    /// Fills the mip chain of every face once level 0 is uploaded and creates the cube view.
+    fn finish_cube(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        mipmaps: &mipmap::MipmapGenerator,
+        texture: wgpu::Texture,
+        options: &TextureOptions,
+    ) -> Self {
+        mipmaps.generate(device, queue, &texture);
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(wgpu::TextureViewDimension::Cube),
+            ..Default::default()
+        });
+        let sampler = options.create_sampler(device);
+
+        Self {
+            texture,
+            view,
+            sampler,
+        }
+    }
+
+    /// Uploads KTX2 or DDS data with its stored mip chain and array layers.
+    ///
+    /// BC data is decoded on the CPU when the device lacks BC support, see
+    /// [`compressed::TextureData::decompress`]; other formats the device cannot sample are an
+    /// error. Only the sampler settings of `options`
+    /// apply, the format and mips come from the data.
+    pub fn from_texture_data(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        data: &compressed::TextureData,
+        label: Option<&str>,
+        options: &TextureOptions,
+    ) -> anyhow::Result<Self> {
+        options.validate()?;
+
+        let decompressed;
+        let data = if device.features().contains(data.format.required_features()) {
+            data
+        } else {
+            log::warn!(
+                "{:?} is not supported by the device, decoding {label:?} on the CPU",
+                data.format
+            );
+
+            decompressed = data.decompress()?;
+            &decompressed
+        };
+
+        let size = data.level_size(0);
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label,
+            size: size.physical_size(data.format),
+            mip_level_count: data.levels.len() as u32,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: data.format,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_DST
+                | options.usage,
+            view_formats: &[],
+        });
+
+        for (level, bytes) in data.levels.iter().enumerate() {
+            let level = level as u32;
+            let (bytes_per_row, rows_per_image) = data.level_layout(level);
+
+            queue.write_texture(
+                wgpu::ImageCopyTexture {
+                    aspect: wgpu::TextureAspect::All,
+                    texture: &texture,
+                    mip_level: level,
+                    origin: wgpu::Origin3d::ZERO,
+                },
+                bytes,
+                wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(bytes_per_row),
+                    rows_per_image: Some(rows_per_image),
+                },
+                data.level_size(level).physical_size(data.format),
+            );
+        }
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(if data.layers > 1 {
+                wgpu::TextureViewDimension::D2Array
+            } else {
+                wgpu::TextureViewDimension::D2
+            }),
+            ..Default::default()
+        });
+        let sampler = options.create_sampler(device);
+
+        Ok(Self {
+            texture,
+            view,
+            sampler,
+        })
+    }
+
+    /// 1x1 opaque white texture, used when a texture asset is missing.
+    pub fn placeholder(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+        Self::from_colour(device, queue, [255, 255, 255, 255], "placeholder")
+    }
+
+    /// 1x1 texture of a single sRGB colour.
+    pub fn from_colour(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        colour: [u8; 4],
+        label: &str,
+    ) -> Self {
+        let img = image::RgbaImage::from_pixel(1, 1, image::Rgba(colour));
+        Self::from_image(
+            device,
+            queue,
+            &image::DynamicImage::ImageRgba8(img),
+            Some(label),
+        )
+        .expect("a 1x1 RGBA image is always a valid texture")
+    }
+
+    /// Uploads `img` as an sRGB texture with a full mip chain generated on the GPU, sampled
+    /// trilinearly.
     pub fn from_image(
         device: &wgpu::Device,
         queue: &wgpu::Queue,
         img: &image::DynamicImage,
         label: Option<&str>,
     ) -> anyhow::Result<Self> {
+        Self::from_image_with_options(device, queue, img, label, &TextureOptions::default())
+    }
+
+    pub fn from_image_with_options(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        img: &image::DynamicImage,
+        label: Option<&str>,
+        options: &TextureOptions,
+    ) -> anyhow::Result<Self> {
+        let mipmaps = mipmap::MipmapGenerator::default();
+        Self::from_image_cached(device, queue, &mipmaps, img, label, options)
+    }
+
+    /// Like [`Texture::from_image_with_options`], blitting mip chains with the pipelines
+    /// `mipmaps` already built.
+    pub(crate) fn from_image_cached(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        mipmaps: &mipmap::MipmapGenerator,
+        img: &image::DynamicImage,
+        label: Option<&str>,
+        options: &TextureOptions,
+    ) -> anyhow::Result<Self> {
+        options.validate()?;
+
         let rgba = img.to_rgba8();
-        let dimensions = img.dimensions();
 
+        Ok(Self::upload(
+            device,
+            queue,
+            mipmaps,
+            &rgba,
+            img.dimensions(),
+            4,
+            options.format(),
+            label,
+            options,
+        ))
+    }
+
+    /// Creates a single layer texture from tightly packed level 0 pixels and fills in the rest
+    /// of the mip chain if `options` ask for it.
+    #[allow(clippy::too_many_arguments)]
+    fn upload(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        mipmaps: &mipmap::MipmapGenerator,
+        bytes: &[u8],
+        dimensions: (u32, u32),
+        bytes_per_pixel: u32,
+        format: wgpu::TextureFormat,
+        label: Option<&str>,
+        options: &TextureOptions,
+    ) -> Self {
         let size = wgpu::Extent3d {
             width: dimensions.0,
             height: dimensions.1,
             depth_or_array_layers: 1,
         };
 
+        let mut usage =
+            wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST | options.usage;
+
+        let mip_level_count = if options.generate_mipmaps {
+            // Render attachment so the mip chain can be blitted
+            usage |= wgpu::TextureUsages::RENDER_ATTACHMENT;
+            mipmap::level_count(dimensions.0, dimensions.1)
+        } else {
+            1
+        };
+
         let texture = device.create_texture(&wgpu::TextureDescriptor {
             label,
             size,
-            mip_level_count: 1,
+            mip_level_count,
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Rgba8UnormSrgb,
-            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            format,
+            usage,
             view_formats: &[],
         });
 
@@ -50,36 +630,33 @@ impl Texture {
                 mip_level: 0,
                 origin: wgpu::Origin3d::ZERO,
             },
-            &rgba,
+            bytes,
             wgpu::ImageDataLayout {
                 offset: 0,
-                bytes_per_row: Some(4 * dimensions.0),
+                bytes_per_row: Some(bytes_per_pixel * dimensions.0),
                 rows_per_image: Some(dimensions.1),
             },
             size,
         );
 
+        mipmaps.generate(device, queue, &texture);
+
         let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
-        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
-            address_mode_u: wgpu::AddressMode::ClampToEdge,
-            address_mode_v: wgpu::AddressMode::ClampToEdge,
-            address_mode_w: wgpu::AddressMode::ClampToEdge,
-            mag_filter: wgpu::FilterMode::Linear,
-            min_filter: wgpu::FilterMode::Nearest,
-            mipmap_filter: wgpu::FilterMode::Nearest,
-            ..Default::default()
-        });
+        let sampler = options.create_sampler(device);
 
-        Ok(Self {
+        Self {
             texture,
             view,
             sampler,
-        })
+        }
     }
 
+    /// Depth attachment matching `config`, multisampled when `sample_count` is above 1 to go
+    /// with a target from [`Texture::create_msaa_target`].
     pub fn create_depth_texture(
         device: &wgpu::Device,
         config: &wgpu::SurfaceConfiguration,
+        sample_count: u32,
         label: &str,
     ) -> Self {
         let size = wgpu::Extent3d {
@@ -88,19 +665,99 @@ impl Texture {
             depth_or_array_layers: 1,
         };
 
+        Self::create_depth(
+            device,
+            size,
+            sample_count,
+            wgpu::TextureViewDimension::D2,
+            label,
+        )
+    }
+
+    /// Array of square depth textures that shadow passes render into, one layer at a time, and
+    /// the main pass samples through the comparison sampler. The view covers every layer.
+    pub fn create_shadow_map(
+        device: &wgpu::Device,
+        backend: wgpu::Backend,
+        resolution: u32,
+        layers: u32,
+        label: &str,
+    ) -> Self {
+        // The GL backend guesses the texture target from the layer count, a lone layer would
+        // become a plain 2D texture that can't be sampled as an array
+        let layers = if backend == wgpu::Backend::Gl {
+            layers.max(2)
+        } else {
+            layers
+        };
+        let size = wgpu::Extent3d {
+            width: resolution,
+            height: resolution,
+            depth_or_array_layers: layers,
+        };
+
+        Self::create_depth(device, size, 1, wgpu::TextureViewDimension::D2Array, label)
+    }
+
+    /// Array of depth cube maps for point light shadows, six layers per cube in the usual
+    /// `+X, -X, +Y, -Y, +Z, -Z` order. Sampled like [`Texture::create_shadow_map`].
+    pub fn create_shadow_cube_map(
+        device: &wgpu::Device,
+        backend: wgpu::Backend,
+        resolution: u32,
+        cubes: u32,
+        label: &str,
+    ) -> Self {
+        // Likewise six layers would become a single cube rather than a cube array on GL
+        let cubes = if backend == wgpu::Backend::Gl {
+            cubes.max(2)
+        } else {
+            cubes
+        };
+        let size = wgpu::Extent3d {
+            width: resolution,
+            height: resolution,
+            depth_or_array_layers: cubemap::FACES * cubes,
+        };
+
+        Self::create_depth(
+            device,
+            size,
+            1,
+            wgpu::TextureViewDimension::CubeArray,
+            label,
+        )
+    }
+
+    fn create_depth(
+        device: &wgpu::Device,
+        size: wgpu::Extent3d,
+        sample_count: u32,
+        dimension: wgpu::TextureViewDimension,
+        label: &str,
+    ) -> Self {
+        // Multisampled depth is never sampled, and binding it trips up the GL backend
+        let usage = if sample_count > 1 {
+            wgpu::TextureUsages::RENDER_ATTACHMENT
+        } else {
+            wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING
+        };
         let desc = wgpu::TextureDescriptor {
             label: Some(label),
             size,
             mip_level_count: 1,
-            sample_count: 1,
+            sample_count,
             dimension: wgpu::TextureDimension::D2,
             format: wgpu::TextureFormat::Depth32Float,
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            usage,
             view_formats: &[],
         };
 
         let texture = device.create_texture(&desc);
-        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(dimension),
+            ..Default::default()
+        });
 
         let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
             address_mode_u: wgpu::AddressMode::ClampToEdge,
@@ -124,4 +781,118 @@ impl Texture {
             sampler,
         }
     }
+
+    /// Colour texture matching `config` that can be rendered into and copied from, used in
+    /// place of a swapchain texture when rendering headless.
+    pub fn create_render_target(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        label: &str,
+    ) -> Self {
+        let size = wgpu::Extent3d {
+            width: config.width,
+            height: config.height,
+            depth_or_array_layers: 1,
+        };
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: config.format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::COPY_SRC
+                | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        Self {
+            texture,
+            view,
+            sampler,
+        }
+    }
+
+    /// Floating point colour target the size of `config` that the scene is lit into, then read
+    /// back by the tonemapping pass. Sampled one texel per pixel, so without filtering.
+    pub fn create_hdr_target(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        label: &str,
+    ) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d {
+                width: config.width,
+                height: config.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: Self::HDR_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            ..Default::default()
+        });
+
+        Self {
+            texture,
+            view,
+            sampler,
+        }
+    }
+
+    /// Multisampled [`Texture::HDR_FORMAT`] attachment the size of `config`, resolved into the
+    /// HDR target at the end of the pass. Only ever rendered to.
+    pub fn create_msaa_target(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        sample_count: u32,
+        label: &str,
+    ) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d {
+                width: config.width,
+                height: config.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count,
+            dimension: wgpu::TextureDimension::D2,
+            format: Self::HDR_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        });
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor::default());
+
+        Self {
+            texture,
+            view,
+            sampler,
+        }
+    }
 }
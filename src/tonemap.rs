@@ -0,0 +1,211 @@
+use std::{collections::HashMap, hash::BuildHasherDefault};
+
+use wgpu::util::DeviceExt;
+
+use crate::texture::Texture;
+
+/// Curve that maps scene colours, which can go well above 1, into the displayable range.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Tonemapper {
+    /// No curve at all, everything above 1 is clipped.
+    #[default]
+    Clamp,
+    /// `c / (1 + c)` per channel, never clips but flattens highlights.
+    Reinhard,
+    /// Fit of the ACES filmic curve, with a toe and a shoulder.
+    Aces,
+    /// AgX, which desaturates bright colours towards white instead of skewing their hue.
+    Agx,
+}
+
+/// How the HDR scene is brought into the surface format, see
+/// [`crate::Context::set_tonemapping`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TonemapSettings {
+    pub tonemapper: Tonemapper,
+    /// Scene colours are multiplied by this before the curve is applied.
+    pub exposure: f32,
+}
+
+impl Default for TonemapSettings {
+    fn default() -> Self {
+        Self {
+            tonemapper: Tonemapper::Clamp,
+            exposure: 1.0,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+struct TonemapData {
+    exposure: f32,
+    tonemapper: u32,
+    _padding: [u32; 2],
+}
+
+unsafe impl bytemuck::Pod for TonemapData {}
+unsafe impl bytemuck::Zeroable for TonemapData {}
+
+impl From<TonemapSettings> for TonemapData {
+    fn from(settings: TonemapSettings) -> Self {
+        Self {
+            exposure: settings.exposure,
+            tonemapper: settings.tonemapper as u32,
+            _padding: [0; 2],
+        }
+    }
+}
+
+/// Fullscreen pass reading the HDR target, or wherever post-processing left the image, and
+/// writing the tonemapped result into the surface.
+pub(crate) struct Tonemap {
+    pipeline: wgpu::RenderPipeline,
+    buffer: wgpu::Buffer,
+    /// One per target the image can end up in.
+    bind_groups: Vec<wgpu::BindGroup>,
+}
+
+impl Tonemap {
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        sources: &[&Texture],
+        settings: TonemapSettings,
+    ) -> Self {
+        let pipeline = tonemap_pipeline(device, format);
+
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Tonemap Buffer"),
+            contents: bytemuck::cast_slice(&[TonemapData::from(settings)]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let bind_groups = sources
+            .iter()
+            .map(|source| Self::create_bind_group(device, &pipeline, &buffer, source))
+            .collect();
+
+        Self {
+            pipeline,
+            buffer,
+            bind_groups,
+        }
+    }
+
+    fn create_bind_group(
+        device: &wgpu::Device,
+        pipeline: &wgpu::RenderPipeline,
+        buffer: &wgpu::Buffer,
+        source: &Texture,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Tonemap Bind Group"),
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&source.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&source.sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: buffer.as_entire_binding(),
+                },
+            ],
+        })
+    }
+
+    /// Reads from `sources` from now on, after the targets were recreated.
+    pub fn set_sources(&mut self, device: &wgpu::Device, sources: &[&Texture]) {
+        self.bind_groups = sources
+            .iter()
+            .map(|source| Self::create_bind_group(device, &self.pipeline, &self.buffer, source))
+            .collect();
+    }
+
+    pub fn update(&self, queue: &wgpu::Queue, settings: TonemapSettings) {
+        queue.write_buffer(
+            &self.buffer,
+            0,
+            bytemuck::cast_slice(&[TonemapData::from(settings)]),
+        );
+    }
+
+    /// Tonemaps the `source`th of the sources into `view`, overwriting every pixel.
+    pub fn render(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        source: usize,
+        view: &wgpu::TextureView,
+    ) {
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Tonemap Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &self.bind_groups[source], &[]);
+        pass.draw(0..3, 0..1);
+    }
+}
+
+/// Writes into `format`, encoding to sRGB in the shader when the format does not do it on write.
+fn tonemap_pipeline(device: &wgpu::Device, format: wgpu::TextureFormat) -> wgpu::RenderPipeline {
+    let vertex_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("Fullscreen Shader"),
+        source: wgpu::ShaderSource::Glsl {
+            shader: include_str!("resources/shaders/fullscreen.vert").into(),
+            stage: wgpu::naga::ShaderStage::Vertex,
+            defines: HashMap::with_hasher(BuildHasherDefault::default()),
+        },
+    });
+
+    let mut defines = HashMap::with_hasher(BuildHasherDefault::default());
+
+    if !format.is_srgb() {
+        defines.insert("ENCODE_SRGB".to_string(), "1".to_string());
+    }
+
+    let fragment_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("Tonemap Shader"),
+        source: wgpu::ShaderSource::Glsl {
+            shader: include_str!("resources/shaders/tonemap.frag").into(),
+            stage: wgpu::naga::ShaderStage::Fragment,
+            defines,
+        },
+    });
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Tonemap Pipeline"),
+        // Derived from the shaders, the bind group layout is read back from the pipeline
+        layout: None,
+        vertex: wgpu::VertexState {
+            module: &vertex_shader,
+            entry_point: "main",
+            buffers: &[],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &fragment_shader,
+            entry_point: "main",
+            targets: &[Some(format.into())],
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+    })
+}
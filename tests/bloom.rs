@@ -0,0 +1,113 @@
+mod common;
+
+use glam::{vec3, Vec3};
+use wgpu_test::{
+    bloom::BloomSettings,
+    light::Light,
+    tonemap::{TonemapSettings, Tonemapper},
+    Context,
+};
+
+/// The default instance grid, dimly lit apart from a hot spot under a very bright light.
+fn hot_spot(context: &mut Context) {
+    context.set_ambient(Vec3::splat(0.15));
+    context.set_lights(vec![Light::point(
+        vec3(0.0, 0.5, 1.0),
+        vec3(1.0, 0.8, 0.5),
+        3.0,
+    )]);
+}
+
+#[test]
+fn bloom_is_off_by_default() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+
+    hot_spot(&mut context);
+    let plain = common::brightness(&mut context);
+    assert!(context.bloom().is_none());
+
+    context.set_bloom(Some(BloomSettings::default()));
+    assert_eq!(context.bloom(), Some(&BloomSettings::default()));
+    assert!(common::brightness(&mut context) > plain);
+
+    context.set_bloom(None);
+    assert_eq!(common::brightness(&mut context), plain);
+}
+
+#[test]
+fn nothing_below_the_threshold_blooms() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+
+    // Unlit, nothing in the default scene is brighter than its texture
+    let plain = common::brightness(&mut context);
+
+    context.set_bloom(Some(BloomSettings {
+        knee: 0.0,
+        ..Default::default()
+    }));
+
+    assert_eq!(common::brightness(&mut context), plain);
+}
+
+#[test]
+fn intensity_scales_the_glow() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+
+    hot_spot(&mut context);
+    let plain = common::brightness(&mut context);
+
+    context.set_bloom(Some(BloomSettings {
+        intensity: 0.2,
+        ..Default::default()
+    }));
+    let faint = common::brightness(&mut context);
+
+    context.set_bloom(Some(BloomSettings {
+        intensity: 1.0,
+        ..Default::default()
+    }));
+    let strong = common::brightness(&mut context);
+
+    assert!(plain < faint && faint < strong, "{plain} {faint} {strong}");
+}
+
+#[test]
+fn resize_keeps_bloom() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+
+    hot_spot(&mut context);
+    context.set_bloom(Some(BloomSettings::default()));
+
+    // Odd sizes, and one too small for more than a single level
+    for (width, height) in [(77, 45), (3, 2)] {
+        context.resize(width, height);
+        context.update();
+
+        let frame = context.capture_frame().unwrap();
+        assert_eq!(frame.dimensions(), (width, height));
+    }
+}
+
+#[test]
+fn bloom_golden() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+
+    hot_spot(&mut context);
+    context.set_bloom(Some(BloomSettings::default()));
+    context.set_tonemapping(TonemapSettings {
+        tonemapper: Tonemapper::Aces,
+        exposure: 1.0,
+    });
+
+    common::assert_golden(&mut context, "bloom_grid");
+}
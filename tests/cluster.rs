@@ -0,0 +1,178 @@
+mod common;
+
+use glam::{vec3, Vec3};
+use wgpu_test::{
+    cluster::ClusterSettings,
+    light::{Attenuation, Light},
+    ContextOptions, RenderPath,
+};
+
+/// A grid of short ranged point lights just above the ground.
+fn many_lights(count: usize) -> Vec<Light> {
+    let side = (count as f32).sqrt().ceil() as usize;
+
+    (0..count)
+        .map(|i| {
+            let (x, z) = ((i % side) as f32, (i / side) as f32);
+            let position = vec3(x, 0.0, z) / side as f32 * 16.0 - vec3(8.0, 0.3, 8.0);
+            let colour = vec3(
+                0.5 + 0.5 * (x * 0.7).sin(),
+                0.5 + 0.5 * (z * 0.9).sin(),
+                0.5 + 0.5 * ((x + z) * 0.5).cos(),
+            );
+            Light {
+                attenuation: Attenuation {
+                    constant: 1.0,
+                    linear: 0.0,
+                    quadratic: 100.0,
+                },
+                ..Light::point(position, colour, 3.0)
+            }
+        })
+        .collect()
+}
+
+#[test]
+fn clustering_is_off_by_default() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+
+    assert!(context.clustered_lighting().is_none());
+
+    if context
+        .set_clustered_lighting(Some(ClusterSettings::default()))
+        .is_ok()
+    {
+        assert_eq!(
+            context.clustered_lighting(),
+            Some(&ClusterSettings::default())
+        );
+    }
+
+    context.set_clustered_lighting(None).unwrap();
+    assert!(context.clustered_lighting().is_none());
+}
+
+#[test]
+fn distant_lights_leave_the_frame_alone() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+
+    if context
+        .set_clustered_lighting(Some(ClusterSettings::default()))
+        .is_err()
+    {
+        return;
+    }
+
+    common::ground_and_cube(
+        &mut context,
+        common::Ground::SMALL,
+        common::two_point_lights(),
+    );
+    let plain = common::frame(&mut context);
+
+    let mut lights = context.lights().to_vec();
+    lights.extend((0..200).map(|i| Light::point(vec3(i as f32, 0.0, 500.0), Vec3::ONE, 1.0)));
+    context.set_lights(lights);
+
+    assert_eq!(common::frame(&mut context), plain);
+}
+
+#[test]
+fn resize_keeps_the_clusters() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+
+    if context
+        .set_clustered_lighting(Some(ClusterSettings::default()))
+        .is_err()
+    {
+        return;
+    }
+
+    common::ground_and_cube(
+        &mut context,
+        common::Ground::SMALL,
+        common::two_point_lights(),
+    );
+
+    for (width, height) in [(77, 45), (1, 1)] {
+        context.resize(width, height);
+        context.update();
+
+        let frame = context.capture_frame().unwrap();
+        assert_eq!(frame.dimensions(), (width, height));
+    }
+}
+
+// Lights reach every cluster they affect, so clustering only changes how they are found
+
+#[test]
+fn clustered_point_lights_match_forward() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+
+    if context
+        .set_clustered_lighting(Some(ClusterSettings::default()))
+        .is_err()
+    {
+        return;
+    }
+
+    common::ground_and_cube(
+        &mut context,
+        common::Ground::SMALL,
+        common::two_point_lights(),
+    );
+
+    common::assert_golden(&mut context, "light_point");
+}
+
+#[test]
+fn clustered_deferred_matches_forward() {
+    let Some(mut context) = common::context_with(&ContextOptions {
+        render_path: RenderPath::Deferred,
+    }) else {
+        return;
+    };
+
+    if context
+        .set_clustered_lighting(Some(ClusterSettings::default()))
+        .is_err()
+    {
+        return;
+    }
+
+    common::ground_and_cube(
+        &mut context,
+        common::Ground::SMALL,
+        common::two_point_lights(),
+    );
+
+    common::assert_golden(&mut context, "light_point");
+}
+
+#[test]
+fn many_lights_golden() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+
+    if context
+        .set_clustered_lighting(Some(ClusterSettings::default()))
+        .is_err()
+    {
+        return;
+    }
+
+    common::ground_and_cube(&mut context, common::Ground::SMALL, vec![]);
+    context.set_ambient(Vec3::splat(0.01));
+    context.set_lights(many_lights(1024));
+
+    common::assert_golden(&mut context, "clustered_lights");
+}
@@ -0,0 +1,290 @@
+//! Shared harness for the golden-image tests.
+//!
+//! Scenes are rendered headlessly and compared against the reference PNGs in `tests/golden`.
+//! Run with `UPDATE_GOLDEN=1` to (re)write the references after an intentional change.
+
+#![allow(dead_code)]
+
+use std::path::PathBuf;
+
+use glam::{vec2, vec3, vec4, Quat, Vec3};
+use image::{Rgba, RgbaImage};
+use wgpu_test::{
+    instance::Instance,
+    light::Light,
+    model::{Material, Mesh, Model, Vertex},
+    texture::Texture,
+    Context, ContextOptions,
+};
+
+pub const CUBE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/assets/cube.obj");
+
+pub const WIDTH: u32 = 128;
+pub const HEIGHT: u32 = 128;
+
+/// Largest per-channel difference for a pixel to still count as matching.
+pub const CHANNEL_TOLERANCE: u8 = 3;
+/// Fraction of pixels allowed to exceed `CHANNEL_TOLERANCE`, to absorb rasterisation
+/// differences between drivers along triangle edges.
+pub const MISMATCH_TOLERANCE: f64 = 0.005;
+
+/// Headless context with a deterministic texture. Panics when the machine has no adapter at
+/// all (not even a software one), unless `WGPU_TEST_ALLOW_NO_ADAPTER=1` is set, in which case
+/// it returns `None` and the test is skipped.
+pub fn context() -> Option<Context<'static>> {
+    context_with(&ContextOptions::default())
+}
+
+/// Like [`context`], created with `options`.
+pub fn context_with(options: &ContextOptions) -> Option<Context<'static>> {
+    let mut context =
+        match pollster::block_on(Context::new_headless_with_options(WIDTH, HEIGHT, options)) {
+            Ok(context) => context,
+            Err(e) if std::env::var_os("WGPU_TEST_ALLOW_NO_ADAPTER").is_some_and(|v| v == "1") => {
+                eprintln!("skipping golden test, no adapter available: {e}");
+                return None;
+            }
+            Err(e) => panic!(
+                "no adapter available ({e}), set WGPU_TEST_ALLOW_NO_ADAPTER=1 to skip GPU tests"
+            ),
+        };
+
+    // The LFS texture may not be checked out, so never depend on it
+    context
+        .set_texture(
+            &image::DynamicImage::ImageRgba8(checkerboard()),
+            "checkerboard",
+        )
+        .unwrap();
+
+    Some(context)
+}
+
+pub fn checkerboard() -> RgbaImage {
+    RgbaImage::from_fn(64, 64, |x, y| {
+        if (x / 8 + y / 8) % 2 == 0 {
+            Rgba([230, 180, 40, 255])
+        } else {
+            Rgba([40, 90, 200, 255])
+        }
+    })
+}
+
+/// Copies one mip level of `texture`, which needs `COPY_SRC` usage, back to the CPU with the
+/// row padding removed.
+pub fn read_level(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    texture: &wgpu_test::texture::Texture,
+    level: u32,
+) -> Vec<u8> {
+    let size = texture
+        .texture
+        .size()
+        .mip_level_size(level, wgpu::TextureDimension::D2);
+    let row_bytes = size.width * texture.texture.format().block_copy_size(None).unwrap();
+    let padded_row_bytes = row_bytes.next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Level Read Back"),
+        size: (padded_row_bytes * size.height) as u64,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+    encoder.copy_texture_to_buffer(
+        wgpu::ImageCopyTexture {
+            texture: &texture.texture,
+            mip_level: level,
+            origin: wgpu::Origin3d::ZERO,
+            aspect: wgpu::TextureAspect::All,
+        },
+        wgpu::ImageCopyBuffer {
+            buffer: &buffer,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_row_bytes),
+                rows_per_image: Some(size.height),
+            },
+        },
+        size,
+    );
+    queue.submit(std::iter::once(encoder.finish()));
+
+    let slice = buffer.slice(..);
+    slice.map_async(wgpu::MapMode::Read, |result| result.unwrap());
+    device.poll(wgpu::Maintain::Wait);
+
+    let mapped = slice.get_mapped_range();
+    mapped
+        .chunks(padded_row_bytes as usize)
+        .flat_map(|row| &row[..row_bytes as usize])
+        .copied()
+        .collect()
+}
+
+/// The ground of [`ground_and_cube`]. Like every model it is drawn once per instance, so its
+/// height is relative to the cube's centre.
+pub struct Ground {
+    pub half_size: f32,
+    pub height: f32,
+}
+
+impl Ground {
+    /// Under the bottom face of the cube, ending a few units behind it.
+    pub const SMALL: Self = Self {
+        half_size: 10.0,
+        height: -0.5,
+    };
+    /// Half a unit below the cube and reaching the horizon, for scenes that need the
+    /// distance or a gap under the cube.
+    pub const WIDE: Self = Self {
+        half_size: 50.0,
+        height: -1.0,
+    };
+}
+
+/// A light grey `ground` under the cube from `cube.obj`, turned by 30 degrees, lit by
+/// `lights` and a little ambient light and seen from the front left.
+pub fn ground_and_cube(context: &mut Context, ground: Ground, lights: Vec<Light>) {
+    let Ground { half_size, height } = ground;
+    let vertices = [
+        (-half_size, -half_size),
+        (half_size, -half_size),
+        (half_size, half_size),
+        (-half_size, half_size),
+    ]
+    .map(|(x, z)| Vertex {
+        position: vec3(x, height, z),
+        tex_coords: vec2(x, z),
+        normal: Vec3::Y,
+    });
+    let texture = Texture::from_colour(context.device(), context.queue(), [200; 4], "grey");
+    let material = Material::new(context.device(), context.material_layout(), "grey", texture);
+    let ground = Mesh::new(
+        context.device(),
+        "ground",
+        &vertices,
+        &[0, 2, 1, 0, 3, 2],
+        Some(0),
+    );
+
+    context.clear_models();
+    context.add_model(Model {
+        meshes: vec![ground],
+        materials: vec![material],
+    });
+    context.load_obj(CUBE).unwrap();
+    context.set_instances(vec![Instance {
+        position: Vec3::ZERO,
+        rotation: Quat::from_axis_angle(Vec3::Y, f32::to_radians(30.0)),
+        colour: vec4(1.0, 1.0, 1.0, 1.0),
+    }]);
+
+    context.set_ambient(Vec3::splat(0.05));
+    context.set_lights(lights);
+
+    let camera = context.camera_mut();
+    camera.eye = vec3(-2.5, 2.5, 3.5);
+    camera.target = vec3(0.0, -0.3, 0.0);
+}
+
+/// The two point lights of `light_point.png`, warm on the left and cool on the right.
+pub fn two_point_lights() -> Vec<Light> {
+    vec![
+        Light::point(vec3(-1.2, 0.2, 1.0), vec3(1.0, 0.6, 0.2), 2.0),
+        Light::point(vec3(1.5, 0.0, -0.5), vec3(0.2, 0.5, 1.0), 2.0),
+    ]
+}
+
+fn golden_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden")
+}
+
+fn output_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("golden")
+}
+
+/// Renders the current scene and reads it back.
+pub fn frame(context: &mut Context) -> RgbaImage {
+    context.update();
+    context.capture_frame().unwrap()
+}
+
+/// Renders the current scene and sums the colour channels of every pixel.
+pub fn brightness(context: &mut Context) -> u64 {
+    frame(context)
+        .pixels()
+        .flat_map(|pixel| &pixel.0[..3])
+        .map(|&channel| channel as u64)
+        .sum()
+}
+
+/// Renders the current scene and compares it against `tests/golden/<name>.png`.
+///
+/// On failure the actual frame and a diff image (mismatching pixels in red over a dimmed copy
+/// of the reference) are written next to each other under the cargo target directory.
+pub fn assert_golden(context: &mut Context, name: &str) {
+    context.update();
+    let actual = context.capture_frame().unwrap();
+    let reference_path = golden_dir().join(format!("{name}.png"));
+
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::create_dir_all(golden_dir()).unwrap();
+        actual.save(&reference_path).unwrap();
+        return;
+    }
+
+    let reference = match image::open(&reference_path) {
+        Ok(reference) => reference.to_rgba8(),
+        Err(e) => panic!(
+            "missing reference {} ({e}), run with UPDATE_GOLDEN=1 to create it",
+            reference_path.display()
+        ),
+    };
+
+    assert_eq!(
+        reference.dimensions(),
+        actual.dimensions(),
+        "{name}: frame size differs from the reference"
+    );
+
+    let mut diff = RgbaImage::new(actual.width(), actual.height());
+    let mut mismatched = 0;
+
+    for (x, y, expected) in reference.enumerate_pixels() {
+        let got = actual.get_pixel(x, y);
+        let matches = expected
+            .0
+            .iter()
+            .zip(got.0)
+            .all(|(&e, g)| e.abs_diff(g) <= CHANNEL_TOLERANCE);
+
+        if matches {
+            let luma = (expected[0] as u32 + expected[1] as u32 + expected[2] as u32) / 12;
+            diff.put_pixel(x, y, Rgba([luma as u8, luma as u8, luma as u8, 255]));
+        } else {
+            mismatched += 1;
+            diff.put_pixel(x, y, Rgba([255, 0, 0, 255]));
+        }
+    }
+
+    let total = (actual.width() * actual.height()) as f64;
+
+    if mismatched as f64 / total > MISMATCH_TOLERANCE {
+        let dir = output_dir();
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let actual_path = dir.join(format!("{name}.actual.png"));
+        let diff_path = dir.join(format!("{name}.diff.png"));
+        actual.save(&actual_path).unwrap();
+        diff.save(&diff_path).unwrap();
+
+        panic!(
+            "{name}: {mismatched} of {total} pixels differ from the reference, see {} and {}",
+            actual_path.display(),
+            diff_path.display()
+        );
+    }
+}
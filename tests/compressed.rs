@@ -0,0 +1,400 @@
+mod common;
+
+use glam::{vec2, vec3, vec4, Quat, Vec3};
+use wgpu_test::{
+    compressed::TextureData,
+    instance::Instance,
+    model::{Material, Mesh, Model, Vertex},
+    texture::{Texture, TextureOptions},
+};
+
+const KTX2_IDENTIFIER: [u8; 12] = [
+    0xAB, 0x4B, 0x54, 0x58, 0x20, 0x32, 0x30, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A,
+];
+const VK_FORMAT_BC1_RGBA_SRGB_BLOCK: u32 = 134;
+
+/// Solid BC1 block, both endpoints set to the same RGB565 colour.
+fn bc1_block(colour: u16) -> [u8; 8] {
+    let [lo, hi] = colour.to_le_bytes();
+    [lo, hi, lo, hi, 0, 0, 0, 0]
+}
+
+/// 16x16 BC1 KTX2 file with a full mip chain, each level a different solid colour.
+fn bc1_mips_ktx2() -> Vec<u8> {
+    const COLOURS: [u16; 5] = [0xF800, 0x07E0, 0x001F, 0xFFE0, 0xFFFF];
+
+    let levels = COLOURS
+        .iter()
+        .enumerate()
+        .map(|(level, &colour)| {
+            let blocks = (16usize >> level).div_ceil(4).pow(2);
+            bc1_block(colour).repeat(blocks)
+        })
+        .collect::<Vec<_>>();
+
+    let header_length = 80 + 24 * levels.len();
+    let dfd = 4u32.to_le_bytes();
+
+    let mut bytes = KTX2_IDENTIFIER.to_vec();
+    for value in [VK_FORMAT_BC1_RGBA_SRGB_BLOCK, 1, 16, 16, 0, 0, 1, 5, 0] {
+        bytes.extend(u32::to_le_bytes(value));
+    }
+    // Descriptor offset/length, no key/value data, no supercompression data
+    for value in [header_length as u32, dfd.len() as u32, 0, 0] {
+        bytes.extend(u32::to_le_bytes(value));
+    }
+    bytes.extend([0; 16]);
+
+    let mut offset = (header_length + dfd.len()) as u64;
+    for level in &levels {
+        let length = level.len() as u64;
+        for value in [offset, length, length] {
+            bytes.extend(u64::to_le_bytes(value));
+        }
+        offset += length;
+    }
+
+    bytes.extend(dfd);
+    bytes.extend(levels.concat());
+    bytes
+}
+
+/// Packs `(value, bits)` fields into a 16 byte block, least significant bit first like BC6H
+/// and BC7 read them.
+fn pack_block(fields: &[(u32, u32)]) -> Vec<u8> {
+    let (block, used) = fields
+        .iter()
+        .fold((0u128, 0), |(block, used), &(value, bits)| {
+            (block | (value as u128) << used, used + bits)
+        });
+    assert_eq!(used, 128);
+
+    block.to_le_bytes().to_vec()
+}
+
+/// The four texels of the top row of a decoded 4x4 RGBA8 SNORM level.
+fn snorm_row(data: &TextureData) -> Vec<[i8; 4]> {
+    let texels = bytemuck::cast_slice::<u8, [i8; 4]>(&data.levels[0]);
+    texels[..4].to_vec()
+}
+
+fn ground_with(context: &mut wgpu_test::Context, texture: Texture) {
+    let vertices = [(-8.0, -30.0), (8.0, -30.0), (8.0, 4.0), (-8.0, 4.0)].map(|(x, z)| Vertex {
+        position: vec3(x, 0.0, z),
+        tex_coords: vec2(x, z) * 0.5,
+        normal: Vec3::Y,
+    });
+    let material = Material::new(
+        context.device(),
+        context.material_layout(),
+        "ground",
+        texture,
+    );
+    let mesh = Mesh::new(
+        context.device(),
+        "ground",
+        &vertices,
+        &[0, 2, 1, 0, 3, 2],
+        Some(0),
+    );
+
+    context.clear_models();
+    context.add_model(Model {
+        meshes: vec![mesh],
+        materials: vec![material],
+    });
+    context.set_instances(vec![Instance {
+        position: Vec3::ZERO,
+        rotation: Quat::IDENTITY,
+        colour: vec4(1.0, 1.0, 1.0, 1.0),
+    }]);
+
+    let camera = context.camera_mut();
+    camera.eye = vec3(0.0, 0.4, 3.5);
+    camera.target = vec3(0.0, 0.0, -4.0);
+}
+
+#[test]
+fn decodes_bc1_punch_through_alpha() {
+    // Blue and red endpoints with c0 < c1: texel 0 is the midpoint, texel 1 is transparent
+    // and texel 2 is the first endpoint
+    let block = [0x1F, 0x00, 0x00, 0xF8, 0b0000_1110, 0, 0, 0];
+    let data = TextureData {
+        format: wgpu::TextureFormat::Bc1RgbaUnorm,
+        width: 4,
+        height: 4,
+        layers: 1,
+        levels: vec![block.to_vec()],
+    };
+
+    let decoded = data.decompress().unwrap();
+
+    assert_eq!(decoded.format, wgpu::TextureFormat::Rgba8Unorm);
+    assert_eq!(decoded.levels[0][0..4], [127, 0, 127, 255]);
+    assert_eq!(decoded.levels[0][4..8], [0, 0, 0, 0]);
+    assert_eq!(decoded.levels[0][8..12], [0, 0, 255, 255]);
+}
+
+#[test]
+fn decodes_bc3_interpolated_alpha_and_colour() {
+    // Alpha 200..100 and colour red..blue, every texel uses index 2
+    let alpha = [
+        200,
+        100,
+        0b1001_0010,
+        0b0010_0100,
+        0b0100_1001,
+        0b1001_0010,
+        0b0010_0100,
+        0b0100_1001,
+    ];
+    let colour = [0x00, 0xF8, 0x1F, 0x00, 0xAA, 0xAA, 0xAA, 0xAA];
+    let data = TextureData {
+        format: wgpu::TextureFormat::Bc3RgbaUnormSrgb,
+        width: 2,
+        height: 2,
+        layers: 1,
+        levels: vec![[alpha, colour].concat()],
+    };
+
+    let decoded = data.decompress().unwrap();
+
+    assert_eq!(decoded.format, wgpu::TextureFormat::Rgba8UnormSrgb);
+    // Cropped to the 2x2 level
+    assert_eq!(decoded.levels[0].len(), 2 * 2 * 4);
+    assert!(decoded.levels[0]
+        .chunks_exact(4)
+        .all(|texel| texel == [170, 0, 85, 185]));
+}
+
+#[test]
+fn decodes_bc4_signed_red() {
+    // Endpoints 70 and -70 with six values between, texels use indices 0, 1, 2 and 7
+    let block = [70, -70i8 as u8, 0x88, 0x0E, 0, 0, 0, 0];
+    let data = TextureData {
+        format: wgpu::TextureFormat::Bc4RSnorm,
+        width: 4,
+        height: 4,
+        layers: 1,
+        levels: vec![block.to_vec()],
+    };
+
+    let decoded = data.decompress().unwrap();
+
+    assert_eq!(decoded.format, wgpu::TextureFormat::Rgba8Snorm);
+    assert_eq!(
+        snorm_row(&decoded),
+        [
+            [70, 0, 0, 127],
+            [-70, 0, 0, 127],
+            [50, 0, 0, 127],
+            [-50, 0, 0, 127]
+        ]
+    );
+}
+
+#[test]
+fn decodes_bc5_signed_red_green() {
+    // Green has endpoints -50 and 50, so only four values between plus -1 and 1, texels use
+    // indices 6, 7, 2 and 0
+    let red = [70, -70i8 as u8, 0x88, 0x0E, 0, 0, 0, 0];
+    let green = [-50i8 as u8, 50, 0xBE, 0, 0, 0, 0, 0];
+    let data = TextureData {
+        format: wgpu::TextureFormat::Bc5RgSnorm,
+        width: 4,
+        height: 4,
+        layers: 1,
+        levels: vec![[red, green].concat()],
+    };
+
+    let decoded = data.decompress().unwrap();
+
+    assert_eq!(decoded.format, wgpu::TextureFormat::Rgba8Snorm);
+    assert_eq!(
+        snorm_row(&decoded),
+        [
+            [70, -127, 0, 127],
+            [-70, 127, 0, 127],
+            [50, -30, 0, 127],
+            [-50, -50, 0, 127]
+        ]
+    );
+}
+
+#[test]
+fn decodes_bc6h_to_half_floats() {
+    // Mode 11 stores both endpoints whole in 10 bits, texel 0 uses the first one and texel 1
+    // the second
+    let block = |[r0, g0, b0]: [u32; 3], [r1, g1, b1]: [u32; 3]| {
+        let mut fields = vec![(0b00011, 5)];
+        fields.extend([r0, g0, b0, r1, g1, b1].map(|value| (value, 10)));
+        fields.extend([(0, 3), (15, 4)]);
+        fields.extend([(0, 4); 14]);
+        pack_block(&fields)
+    };
+    let texels = |format, block| {
+        let data = TextureData {
+            format,
+            width: 4,
+            height: 4,
+            layers: 1,
+            levels: vec![block],
+        };
+        let decoded = data.decompress().unwrap();
+
+        assert_eq!(decoded.format, wgpu::TextureFormat::Rgba16Float);
+        bytemuck::cast_slice::<u8, half::f16>(&decoded.levels[0][..16])
+            .iter()
+            .map(|value| value.to_f32())
+            .collect::<Vec<_>>()
+    };
+
+    // The largest endpoint is the largest finite half
+    let unsigned = texels(
+        wgpu::TextureFormat::Bc6hRgbUfloat,
+        block([0, 0, 0], [1023, 1023, 0]),
+    );
+    assert_eq!(unsigned, [0.0, 0.0, 0.0, 1.0, 65504.0, 65504.0, 0.0, 1.0]);
+
+    // -511 and 511 as 10 bit two's complement
+    let signed = texels(
+        wgpu::TextureFormat::Bc6hRgbFloat,
+        block([0x201, 0, 0], [0x1FF, 0, 0]),
+    );
+    assert_eq!(signed, [-65504.0, 0.0, 0.0, 1.0, 65504.0, 0.0, 0.0, 1.0]);
+}
+
+#[test]
+fn decodes_bc7_separate_alpha_indices() {
+    // Mode 5: red to blue in 7 bits, alpha 255 to 0 in 8 bits, each with its own 2 bit
+    // indices. Texels 0-2 use colour indices 0, 3, 1 and alpha indices 0, 3, 2.
+    let mut fields = vec![(1 << 5, 6), (0, 2)];
+    fields.extend([(127, 7), (0, 7), (0, 7), (0, 7), (0, 7), (127, 7)]);
+    fields.extend([(255, 8), (0, 8)]);
+    fields.extend([(0, 1), (3, 2), (1, 2)]);
+    fields.extend([(0, 2); 13]);
+    fields.extend([(0, 1), (3, 2), (2, 2)]);
+    fields.extend([(0, 2); 13]);
+    let data = TextureData {
+        format: wgpu::TextureFormat::Bc7RgbaUnormSrgb,
+        width: 4,
+        height: 4,
+        layers: 1,
+        levels: vec![pack_block(&fields)],
+    };
+
+    let decoded = data.decompress().unwrap();
+
+    assert_eq!(decoded.format, wgpu::TextureFormat::Rgba8UnormSrgb);
+    assert_eq!(decoded.levels[0][0..4], [255, 0, 0, 255]);
+    assert_eq!(decoded.levels[0][4..8], [0, 0, 255, 0]);
+    assert_eq!(decoded.levels[0][8..12], [171, 0, 84, 84]);
+}
+
+#[test]
+fn reads_ktx2_mip_chain() {
+    let data = TextureData::from_ktx2(&bc1_mips_ktx2()).unwrap();
+
+    assert_eq!(data.format, wgpu::TextureFormat::Bc1RgbaUnormSrgb);
+    assert_eq!((data.width, data.height, data.layers), (16, 16, 1));
+    assert_eq!(data.levels.len(), 5);
+    assert_eq!(data.levels[0].len(), 16 * 8);
+    assert_eq!(data.levels[4].len(), 8);
+}
+
+#[test]
+fn reads_dds_array_layers() {
+    let mut dds = ddsfile::Dds::new_dxgi(ddsfile::NewDxgiParams {
+        height: 8,
+        width: 8,
+        depth: None,
+        format: ddsfile::DxgiFormat::BC1_UNorm,
+        mipmap_levels: Some(2),
+        array_layers: Some(3),
+        caps2: None,
+        is_cubemap: false,
+        resource_dimension: ddsfile::D3D10ResourceDimension::Texture2D,
+        alpha_mode: ddsfile::AlphaMode::Straight,
+    })
+    .unwrap();
+
+    // Each layer stores its own mip chain: four level 0 blocks then one level 1 block
+    dds.data = (0..3u16)
+        .flat_map(|layer| {
+            let block = bc1_block(layer);
+            [block.repeat(4), block.to_vec()].concat()
+        })
+        .collect();
+
+    let mut bytes = Vec::new();
+    dds.write(&mut bytes).unwrap();
+
+    let data = TextureData::from_dds(&bytes).unwrap();
+
+    assert_eq!(data.format, wgpu::TextureFormat::Bc1RgbaUnorm);
+    assert_eq!(data.layers, 3);
+    assert_eq!(data.levels.len(), 2);
+    // Levels come first, so level 1 holds the single block of every layer in order
+    assert_eq!(
+        data.levels[1],
+        [bc1_block(0), bc1_block(1), bc1_block(2)].concat()
+    );
+}
+
+#[test]
+fn rejects_truncated_ktx2() {
+    let mut bytes = bc1_mips_ktx2();
+    bytes.truncate(bytes.len() - 8);
+
+    assert!(TextureData::from_ktx2(&bytes).is_err());
+}
+
+#[test]
+fn ktx2_stored_mips_golden() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+
+    let data = TextureData::from_ktx2(&bc1_mips_ktx2()).unwrap();
+    let texture = Texture::from_texture_data(
+        context.device(),
+        context.queue(),
+        &data,
+        Some("bc1_mips"),
+        &TextureOptions::tiling(),
+    )
+    .unwrap();
+
+    assert_eq!(texture.texture.mip_level_count(), 5);
+
+    ground_with(&mut context, texture);
+    common::assert_golden(&mut context, "ktx2_bc1_mips");
+}
+
+#[test]
+fn cpu_decoded_mips_match_gpu_golden() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+
+    let data = TextureData::from_ktx2(&bc1_mips_ktx2())
+        .unwrap()
+        .decompress()
+        .unwrap();
+    let texture = Texture::from_texture_data(
+        context.device(),
+        context.queue(),
+        &data,
+        Some("bc1_mips_decoded"),
+        &TextureOptions::tiling(),
+    )
+    .unwrap();
+
+    assert_eq!(
+        texture.texture.format(),
+        wgpu::TextureFormat::Rgba8UnormSrgb
+    );
+
+    ground_with(&mut context, texture);
+    common::assert_golden(&mut context, "ktx2_bc1_mips");
+}
@@ -0,0 +1,248 @@
+mod common;
+
+use glam::{vec3, vec4, Mat4, Quat, Vec3};
+use wgpu_test::{
+    camera::Camera,
+    culling::{Aabb, CullingStats, Frustum},
+    instance::Instance,
+    ssao::SsaoSettings,
+};
+
+fn camera() -> Camera {
+    Camera {
+        eye: vec3(0.0, 0.0, 5.0),
+        target: Vec3::ZERO,
+        up: Vec3::Y,
+        aspect: 1.0,
+        fov_y: 45.0,
+        z_near: 0.1,
+        z_far: 100.0,
+    }
+}
+
+fn unit_box(centre: Vec3) -> Aabb {
+    Aabb {
+        min: centre - 0.5,
+        max: centre + 0.5,
+    }
+}
+
+/// A row of instances along X, one unit apart.
+fn row(count: usize) -> Vec<Instance> {
+    (0..count)
+        .map(|i| Instance {
+            position: vec3(i as f32 - count as f32 * 0.5, 0.0, 0.0),
+            rotation: Quat::IDENTITY,
+            colour: vec4(1.0, 1.0, 1.0, 1.0),
+        })
+        .collect()
+}
+
+#[test]
+fn aabb_from_points() {
+    let aabb = Aabb::from_points([vec3(1.0, -2.0, 0.5), vec3(-1.0, 3.0, 0.0)]);
+    assert_eq!(aabb.min, vec3(-1.0, -2.0, 0.0));
+    assert_eq!(aabb.max, vec3(1.0, 3.0, 0.5));
+
+    assert!(Aabb::from_points([]).is_empty());
+    assert_eq!(Aabb::EMPTY.union(aabb), aabb);
+}
+
+#[test]
+fn transformed_aabb_contains_the_rotated_box() {
+    let aabb = unit_box(Vec3::ZERO);
+    let transform =
+        Mat4::from_translation(vec3(2.0, 0.0, 0.0)) * Mat4::from_rotation_y(f32::to_radians(45.0));
+    let transformed = aabb.transformed(transform);
+
+    let half_diagonal = f32::sqrt(0.5);
+    assert!((transformed.min - vec3(2.0 - half_diagonal, -0.5, -half_diagonal)).length() < 1e-5);
+    assert!((transformed.max - vec3(2.0 + half_diagonal, 0.5, half_diagonal)).length() < 1e-5);
+}
+
+#[test]
+fn frustum_keeps_what_the_camera_sees() {
+    let frustum = camera().frustum();
+
+    assert!(frustum.intersects(&unit_box(Vec3::ZERO)));
+    // Straddling the left plane
+    assert!(frustum.intersects(&unit_box(vec3(-2.3, 0.0, 0.0))));
+    // Behind the camera, beyond the far plane, far to the side and below
+    assert!(!frustum.intersects(&unit_box(vec3(0.0, 0.0, 10.0))));
+    assert!(!frustum.intersects(&unit_box(vec3(0.0, 0.0, -200.0))));
+    assert!(!frustum.intersects(&unit_box(vec3(10.0, 0.0, 0.0))));
+    assert!(!frustum.intersects(&unit_box(vec3(0.0, -10.0, 0.0))));
+
+    assert!(!frustum.intersects(&Aabb::EMPTY));
+}
+
+#[test]
+fn frustum_planes_face_inwards() {
+    let frustum = Frustum::from_view_projection(camera().build_view_projection());
+
+    for plane in frustum.planes {
+        assert!(plane.truncate().dot(Vec3::ZERO) + plane.w > 0.0, "{plane}");
+    }
+}
+
+#[test]
+fn culling_counts_instances() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+
+    assert!(context.frustum_culling());
+
+    context.set_instances(row(40));
+    context.update();
+    let stats = context.culling_stats();
+    assert_eq!(stats.drawn + stats.culled, 40);
+    assert!(stats.drawn > 0 && stats.culled > 0, "{stats:?}");
+
+    context.set_frustum_culling(false);
+    context.update();
+    assert_eq!(
+        context.culling_stats(),
+        CullingStats {
+            drawn: 40,
+            culled: 0
+        }
+    );
+}
+
+#[test]
+fn culling_leaves_the_frame_alone() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+
+    context.set_instances(row(40));
+    context.update();
+    let culled = context.capture_frame().unwrap();
+
+    context.set_frustum_culling(false);
+    context.update();
+    let everything = context.capture_frame().unwrap();
+
+    assert_eq!(culled, everything);
+}
+
+#[test]
+fn looking_away_culls_everything() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+
+    context.set_instances(vec![]);
+    context.update();
+    let empty = context.capture_frame().unwrap();
+
+    context.set_instances(row(40));
+    let camera = context.camera_mut();
+    camera.target = camera.eye + (camera.eye - camera.target);
+    context.update();
+
+    assert_eq!(
+        context.culling_stats(),
+        CullingStats {
+            drawn: 0,
+            culled: 40
+        }
+    );
+    // Nothing in view draws just the clear, like an empty scene
+    assert_eq!(context.capture_frame().unwrap(), empty);
+}
+
+#[test]
+fn unchanged_frames_reuse_the_visible_instances() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+
+    context.set_instances(row(40));
+    context.update();
+    let uploads = context.visible_instance_uploads();
+    let stats = context.culling_stats();
+
+    context.update();
+    assert_eq!(context.visible_instance_uploads(), uploads);
+    assert_eq!(context.culling_stats(), stats);
+
+    context.camera_mut().eye.x += 1.0;
+    context.update();
+    assert_eq!(context.visible_instance_uploads(), uploads + 1);
+
+    let handle = context.instance_handles()[0];
+    context.instance_mut(handle).unwrap().position.y += 1.0;
+    context.update();
+    assert_eq!(context.visible_instance_uploads(), uploads + 2);
+}
+
+// GPU culling keeps the same instances, so it must draw exactly what the CPU does
+
+#[test]
+fn gpu_culling_matches_cpu() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+
+    context.set_instances(row(600));
+    context.set_ssao(Some(SsaoSettings::default()));
+    context.update();
+    let cpu = context.capture_frame().unwrap();
+
+    if context.set_gpu_culling(true).is_err() {
+        return;
+    }
+    assert!(context.gpu_culling());
+
+    context.update();
+    assert_eq!(context.culling_stats(), CullingStats::default());
+    assert_eq!(context.capture_frame().unwrap(), cpu);
+
+    context.set_gpu_culling(false).unwrap();
+    context.update();
+    assert_eq!(
+        context.culling_stats().drawn + context.culling_stats().culled,
+        600
+    );
+}
+
+#[test]
+fn gpu_culling_follows_the_scene() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+
+    if context.set_gpu_culling(true).is_err() {
+        return;
+    }
+
+    for instances in [vec![], row(3)] {
+        context.set_instances(instances);
+        context.update();
+        let gpu = context.capture_frame().unwrap();
+
+        context.set_frustum_culling(false);
+        context.update();
+        assert_eq!(context.capture_frame().unwrap(), gpu);
+        context.set_frustum_culling(true);
+    }
+
+    context.clear_models();
+    context.update();
+    context.capture_frame().unwrap();
+}
+
+#[test]
+fn gpu_culled_grid_matches_golden() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+
+    if context.set_gpu_culling(true).is_err() {
+        return;
+    }
+
+    common::assert_golden(&mut context, "pentagon_grid");
+}
@@ -0,0 +1,88 @@
+mod common;
+
+use wgpu_test::{Context, ContextOptions, RenderPath};
+
+fn deferred() -> Option<Context<'static>> {
+    common::context_with(&ContextOptions {
+        render_path: RenderPath::Deferred,
+    })
+}
+
+#[test]
+fn forward_is_the_default() {
+    let Some(context) = common::context() else {
+        return;
+    };
+
+    assert_eq!(context.render_path(), RenderPath::Forward);
+}
+
+#[test]
+fn deferred_has_no_msaa() {
+    let Some(mut context) = deferred() else {
+        return;
+    };
+
+    assert_eq!(context.render_path(), RenderPath::Deferred);
+    assert_eq!(context.supported_sample_counts(), vec![1]);
+    assert!(context.set_sample_count(4).is_err());
+    assert!(context.set_sample_count(1).is_ok());
+}
+
+#[test]
+fn resize_keeps_the_gbuffer() {
+    let Some(mut context) = deferred() else {
+        return;
+    };
+
+    common::ground_and_cube(
+        &mut context,
+        common::Ground::SMALL,
+        common::two_point_lights(),
+    );
+
+    for (width, height) in [(77, 45), (1, 1)] {
+        context.resize(width, height);
+        context.update();
+
+        let frame = context.capture_frame().unwrap();
+        assert_eq!(frame.dimensions(), (width, height));
+    }
+}
+
+// The deferred path lights exactly like the forward one, so it is held to the same references
+
+#[test]
+fn deferred_grid_matches_forward() {
+    let Some(mut context) = deferred() else {
+        return;
+    };
+
+    common::assert_golden(&mut context, "pentagon_grid");
+}
+
+#[test]
+fn deferred_empty_scene_matches_forward() {
+    let Some(mut context) = deferred() else {
+        return;
+    };
+
+    context.set_instances(vec![]);
+
+    common::assert_golden(&mut context, "empty_scene");
+}
+
+#[test]
+fn deferred_point_lights_match_forward() {
+    let Some(mut context) = deferred() else {
+        return;
+    };
+
+    common::ground_and_cube(
+        &mut context,
+        common::Ground::SMALL,
+        common::two_point_lights(),
+    );
+
+    common::assert_golden(&mut context, "light_point");
+}
@@ -0,0 +1,103 @@
+mod common;
+
+use glam::{vec3, vec4, Quat, Vec3};
+use wgpu_test::{instance::Instance, light::Light, model::ShadingModel, scene::Scene};
+
+const QUADS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/assets/quads.gltf");
+const MIRRORED: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/assets/mirrored.gltf");
+
+#[test]
+fn imports_hierarchy_materials_and_cameras() {
+    let Some(context) = common::context() else {
+        return;
+    };
+
+    let scene = Scene::load_gltf(
+        context.device(),
+        context.queue(),
+        context.material_layout(),
+        QUADS,
+        1.0,
+    )
+    .unwrap();
+
+    let names = scene.nodes.iter().map(|node| node.name.as_deref().unwrap());
+    assert_eq!(
+        names.collect::<Vec<_>>(),
+        ["root", "checker", "tinted", "stripes", "camera"]
+    );
+    assert_eq!(scene.nodes[0].children, [1, 2, 3]);
+    assert_eq!(scene.nodes[3].parent, Some(0));
+    assert_eq!(scene.nodes[4].parent, None);
+
+    // Child transforms are composed with their parent's
+    let stripes = scene.nodes[3].world.transform_point3(Vec3::ZERO);
+    assert!(stripes.abs_diff_eq(vec3(1.1, 0.0, -1.0), 1e-6));
+
+    assert_eq!(scene.model.meshes.len(), 3);
+    assert_eq!(scene.model.materials.len(), 3);
+    assert_eq!(scene.model.meshes[1].material, Some(1));
+
+    // Factors are kept on the material rather than baked into its texture
+    let tinted = &scene.model.materials[1];
+    assert_eq!(tinted.shading, ShadingModel::MetallicRoughness);
+    assert_eq!(tinted.factors.base_colour, vec4(0.8, 0.05, 0.05, 1.0));
+    assert_eq!(tinted.factors.metallic, 1.0);
+
+    let camera = scene.cameras[0];
+    assert!(camera.eye.abs_diff_eq(vec3(0.0, 0.3, 2.5), 1e-6));
+    assert!((camera.fov_y - f32::to_degrees(0.8)).abs() < 1e-4);
+}
+
+#[test]
+fn quads_golden() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+
+    context.clear_models();
+    context.load_gltf(QUADS).unwrap();
+    context.set_instances(vec![Instance {
+        position: Vec3::ZERO,
+        rotation: Quat::IDENTITY,
+        colour: vec4(1.0, 1.0, 1.0, 1.0),
+    }]);
+
+    common::assert_golden(&mut context, "gltf_quads");
+}
+
+#[test]
+fn mirrored_nodes_keep_their_front_faces() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+
+    // The same quad twice, the right one mirrored along X
+    context.clear_models();
+    context.load_gltf(MIRRORED).unwrap();
+    context.set_instances(vec![Instance {
+        position: Vec3::ZERO,
+        rotation: Quat::IDENTITY,
+        colour: vec4(1.0, 1.0, 1.0, 1.0),
+    }]);
+    context.set_ambient(Vec3::splat(0.05));
+    context.set_lights(vec![Light::point(vec3(0.0, 0.0, 1.0), Vec3::ONE, 1.0)]);
+
+    let camera = context.camera_mut();
+    camera.eye = vec3(0.0, 0.0, 3.0);
+    camera.target = Vec3::ZERO;
+
+    context.update();
+    let frame = context.capture_frame().unwrap();
+    let half = |right: bool| {
+        frame
+            .enumerate_pixels()
+            .filter(|(x, _, _)| (*x >= frame.width() / 2) == right)
+            .map(|(_, _, pixel)| pixel[0] as i64)
+            .sum::<i64>()
+    };
+
+    // A mirrored winding would make the lit side a back face, whose normal is flipped away
+    let (left, right) = (half(false), half(true));
+    assert!((left - right).abs() * 100 < left, "{left} {right}");
+}
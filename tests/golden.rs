@@ -0,0 +1,81 @@
+mod common;
+
+use glam::{vec3, vec4, Quat, Vec3};
+use wgpu_test::instance::Instance;
+
+#[test]
+fn pentagon_grid() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+
+    common::assert_golden(&mut context, "pentagon_grid");
+}
+
+#[test]
+fn single_instance() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+
+    context.set_instances(vec![Instance {
+        position: Vec3::ZERO,
+        rotation: Quat::IDENTITY,
+        colour: vec4(1.0, 1.0, 1.0, 1.0),
+    }]);
+
+    common::assert_golden(&mut context, "single_instance");
+}
+
+#[test]
+fn single_instance_rotated() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+
+    context.set_instances(vec![Instance {
+        position: vec3(0.25, 0.0, -0.5),
+        rotation: Quat::from_axis_angle(Vec3::Y, f32::to_radians(60.0)),
+        colour: vec4(1.0, 1.0, 1.0, 1.0),
+    }]);
+
+    common::assert_golden(&mut context, "single_instance_rotated");
+}
+
+#[test]
+fn camera_above_grid() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+
+    let camera = context.camera_mut();
+    camera.eye = vec3(0.0, 8.0, 6.0);
+    camera.target = vec3(0.0, 0.0, -1.0);
+
+    common::assert_golden(&mut context, "camera_above_grid");
+}
+
+#[test]
+fn camera_side_wide_fov() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+
+    let camera = context.camera_mut();
+    camera.eye = vec3(-7.0, 1.5, 0.0);
+    camera.target = Vec3::ZERO;
+    camera.fov_y = 70.0;
+
+    common::assert_golden(&mut context, "camera_side_wide_fov");
+}
+
+#[test]
+fn empty_scene_is_clear_colour() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+
+    context.set_instances(vec![]);
+
+    common::assert_golden(&mut context, "empty_scene");
+}
@@ -0,0 +1,201 @@
+mod common;
+
+use std::path::PathBuf;
+
+use image::{Rgb, Rgba, Rgba32FImage};
+use wgpu_test::texture::{Texture, TextureOptions};
+
+fn output_path(name: &str) -> PathBuf {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("hdr");
+    std::fs::create_dir_all(&dir).unwrap();
+    dir.join(name)
+}
+
+/// Texture options for read back, without filtering so the stored values come out as is.
+fn readable() -> TextureOptions {
+    TextureOptions {
+        mag_filter: wgpu::FilterMode::Nearest,
+        min_filter: wgpu::FilterMode::Nearest,
+        mipmap_filter: wgpu::FilterMode::Nearest,
+        generate_mipmaps: false,
+        usage: wgpu::TextureUsages::COPY_SRC,
+        ..Default::default()
+    }
+}
+
+/// A device with only the default features, so `Rgba32Float` cannot be filtered whatever the
+/// adapter supports. `None` without an adapter.
+fn device_without_float32_filtering() -> Option<(wgpu::Device, wgpu::Queue)> {
+    let instance = wgpu::Instance::new(Default::default());
+    let adapter = pollster::block_on(instance.request_adapter(&Default::default()))?;
+
+    pollster::block_on(adapter.request_device(
+        &wgpu::DeviceDescriptor {
+            label: None,
+            required_features: wgpu::Features::empty(),
+            required_limits: wgpu::Limits::downlevel_defaults(),
+        },
+        None,
+    ))
+    .ok()
+}
+
+fn halves_to_f32(bytes: &[u8]) -> Vec<f32> {
+    bytemuck::cast_slice::<u8, half::f16>(bytes)
+        .iter()
+        .map(|value| value.to_f32())
+        .collect()
+}
+
+#[test]
+fn radiance_hdr_keeps_values_above_one() {
+    let Some(context) = common::context() else {
+        return;
+    };
+
+    // Powers of two survive the shared exponent encoding exactly
+    let path = output_path("bright.hdr");
+    let pixels = [
+        Rgb([8.0, 0.5, 0.0]),
+        Rgb([1.0, 2.0, 4.0]),
+        Rgb([16.0, 16.0, 16.0]),
+        Rgb([0.0, 0.0, 0.0]),
+    ];
+    image::codecs::hdr::HdrEncoder::new(std::fs::File::create(&path).unwrap())
+        .encode(&pixels, 4, 1)
+        .unwrap();
+
+    let texture = Texture::from_file_with_options(
+        context.device(),
+        context.queue(),
+        path.to_str().unwrap(),
+        "bright",
+        &readable(),
+    )
+    .unwrap();
+
+    assert_eq!(texture.texture.format(), wgpu::TextureFormat::Rgba16Float);
+
+    let texels = halves_to_f32(&common::read_level(
+        context.device(),
+        context.queue(),
+        &texture,
+        0,
+    ));
+    assert_eq!(texels[0..4], [8.0, 0.5, 0.0, 1.0]);
+    assert_eq!(texels[4..8], [1.0, 2.0, 4.0, 1.0]);
+    assert_eq!(texels[8..12], [16.0, 16.0, 16.0, 1.0]);
+}
+
+#[test]
+fn openexr_loads_as_full_float() {
+    let Some(context) = common::context() else {
+        return;
+    };
+
+    let path = output_path("lightmap.exr");
+    let pixels = [[3.25, 0.1, 1e-3, 1.0], [100.0, 0.0, 7.5, 0.5]];
+    let img = Rgba32FImage::from_fn(4, 1, |x, _| Rgba(pixels[x as usize % 2]));
+    image::DynamicImage::ImageRgba32F(img).save(&path).unwrap();
+
+    let texture = Texture::from_hdr_file(
+        context.device(),
+        context.queue(),
+        path.to_str().unwrap(),
+        "lightmap",
+        wgpu::TextureFormat::Rgba32Float,
+        &readable(),
+    )
+    .unwrap();
+
+    assert_eq!(texture.texture.format(), wgpu::TextureFormat::Rgba32Float);
+
+    let bytes = common::read_level(context.device(), context.queue(), &texture, 0);
+    let texels = bytemuck::cast_slice::<u8, f32>(&bytes);
+    assert_eq!(texels[0..4], pixels[0]);
+    assert_eq!(texels[4..8], pixels[1]);
+}
+
+#[test]
+fn mip_chain_averages_unclamped_values() {
+    let Some(context) = common::context() else {
+        return;
+    };
+
+    let img = Rgba32FImage::from_fn(4, 1, |x, _| {
+        if x % 2 == 0 {
+            Rgba([0.0, 2.0, 0.0, 1.0])
+        } else {
+            Rgba([8.0, 2.0, 0.0, 1.0])
+        }
+    });
+    let texture = Texture::from_hdr_image(
+        context.device(),
+        context.queue(),
+        &img,
+        Some("mipped"),
+        wgpu::TextureFormat::Rgba16Float,
+        &TextureOptions {
+            usage: wgpu::TextureUsages::COPY_SRC,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    assert_eq!(texture.texture.mip_level_count(), 3);
+
+    let texels = halves_to_f32(&common::read_level(
+        context.device(),
+        context.queue(),
+        &texture,
+        1,
+    ));
+    assert_eq!(texels[0..4], [4.0, 2.0, 0.0, 1.0]);
+}
+
+#[test]
+fn rejects_non_float_formats() {
+    let Some(context) = common::context() else {
+        return;
+    };
+
+    let result = Texture::from_hdr_image(
+        context.device(),
+        context.queue(),
+        &Rgba32FImage::new(1, 1),
+        None,
+        wgpu::TextureFormat::Rgba8Unorm,
+        &TextureOptions::default(),
+    );
+
+    assert!(result.is_err());
+}
+
+#[test]
+fn unfilterable_full_float_rejects_linear_filters() {
+    let Some((device, queue)) = device_without_float32_filtering() else {
+        return;
+    };
+
+    let img = Rgba32FImage::new(4, 4);
+    let upload = |options: &TextureOptions| {
+        Texture::from_hdr_image(
+            &device,
+            &queue,
+            &img,
+            None,
+            wgpu::TextureFormat::Rgba32Float,
+            options,
+        )
+    };
+
+    // Linear filters alone need filtering, even without a mip chain
+    let linear = TextureOptions {
+        generate_mipmaps: false,
+        ..Default::default()
+    };
+    assert!(upload(&linear).is_err());
+
+    let texture = upload(&readable()).unwrap();
+    assert_eq!(texture.texture.format(), wgpu::TextureFormat::Rgba32Float);
+}
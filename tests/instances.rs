@@ -0,0 +1,145 @@
+mod common;
+
+use glam::{vec3, vec4, Quat, Vec3};
+use wgpu_test::instance::Instance;
+
+fn instance(x: f32) -> Instance {
+    Instance {
+        position: vec3(x, 0.0, 0.0),
+        rotation: Quat::IDENTITY,
+        colour: vec4(1.0, 1.0, 1.0, 1.0),
+    }
+}
+
+/// A row of instances along X, centred in front of the default camera.
+fn row(count: usize) -> Vec<Instance> {
+    (0..count)
+        .map(|i| instance((i as f32 - count as f32 * 0.5) * 0.3))
+        .collect()
+}
+
+#[test]
+fn handles_find_their_instances() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+
+    context.set_instances(vec![]);
+    let handles = (0..5)
+        .map(|i| context.spawn_instance(instance(i as f32)))
+        .collect::<Vec<_>>();
+
+    assert_eq!(context.instances().len(), 5);
+    assert_eq!(context.instance_handles(), handles);
+
+    // The last instance moves into the despawned one's place
+    assert_eq!(context.despawn_instance(handles[1]), Some(instance(1.0)));
+    assert_eq!(context.despawn_instance(handles[1]), None);
+    assert_eq!(context.instance(handles[1]), None);
+
+    for (i, &handle) in handles.iter().enumerate().filter(|&(i, _)| i != 1) {
+        assert_eq!(context.instance(handle), Some(&instance(i as f32)));
+    }
+
+    context.instance_mut(handles[4]).unwrap().position.y = 2.0;
+    assert_eq!(context.instances()[1].position, vec3(4.0, 2.0, 0.0));
+}
+
+#[test]
+fn stale_handles_stay_dead() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+
+    let old = context.instance_handles().to_vec();
+    context.set_instances(vec![instance(0.0)]);
+
+    for handle in old {
+        assert!(context.instance(handle).is_none());
+    }
+
+    let first = context.instance_handles()[0];
+    context.despawn_instance(first).unwrap();
+    // Reuses the freed slot under a new handle
+    let second = context.spawn_instance(instance(1.0));
+
+    assert_ne!(first, second);
+    assert!(context.instance_mut(first).is_none());
+    assert_eq!(context.instance(second), Some(&instance(1.0)));
+}
+
+#[test]
+fn spawned_instances_draw_like_set_ones() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+
+    // Enough to regrow the buffers a few times
+    let instances = row(100);
+    context.set_instances(instances.clone());
+    let expected = common::frame(&mut context);
+
+    context.set_instances(vec![]);
+    for &instance in &instances {
+        context.spawn_instance(instance);
+    }
+
+    assert_eq!(common::frame(&mut context), expected);
+}
+
+#[test]
+fn changes_are_uploaded_on_update() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+
+    let mut instances = row(20);
+    context.set_instances(instances.clone());
+    let before = common::frame(&mut context);
+
+    let handles = context.instance_handles().to_vec();
+    context.instance_mut(handles[3]).unwrap().position.y += 0.5;
+    context.instance_mut(handles[12]).unwrap().rotation = Quat::from_rotation_z(1.0);
+    context.despawn_instance(handles[7]).unwrap();
+    context.spawn_instance(instance(0.1));
+    let moved = common::frame(&mut context);
+    assert_ne!(moved, before);
+
+    instances[3].position.y += 0.5;
+    instances[12].rotation = Quat::from_rotation_z(1.0);
+    instances.swap_remove(7);
+    instances.push(instance(0.1));
+    context.set_instances(instances);
+
+    assert_eq!(common::frame(&mut context), moved);
+}
+
+#[test]
+fn dynamic_instances_with_gpu_culling() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+
+    if context.set_gpu_culling(true).is_err() {
+        return;
+    }
+
+    context.set_instances(vec![]);
+    let empty = common::frame(&mut context);
+
+    let handles = row(40)
+        .into_iter()
+        .map(|instance| context.spawn_instance(instance))
+        .collect::<Vec<_>>();
+    context.instance_mut(handles[0]).unwrap().position = Vec3::ZERO;
+    let gpu = common::frame(&mut context);
+    assert_ne!(gpu, empty);
+
+    context.set_frustum_culling(false);
+    assert_eq!(common::frame(&mut context), gpu);
+
+    for handle in handles {
+        context.despawn_instance(handle).unwrap();
+    }
+    assert_eq!(common::frame(&mut context), empty);
+}
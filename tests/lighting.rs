@@ -0,0 +1,117 @@
+mod common;
+
+use glam::{vec3, Vec3};
+use wgpu_test::light::{Light, LightData};
+
+#[test]
+fn spot_cones_are_stored_as_cosines() {
+    let light = Light::spot(Vec3::Y, Vec3::NEG_Y * 2.0, 20.0, 30.0, Vec3::ONE, 3.0);
+    let data = light.to_raw();
+
+    assert_eq!(data.position.w, LightData::SPOT);
+    assert_eq!(data.direction.truncate(), Vec3::NEG_Y);
+    assert!((data.direction.w - 30f32.to_radians().cos()).abs() < 1e-6);
+    assert!((data.attenuation.w - 20f32.to_radians().cos()).abs() < 1e-6);
+    assert_eq!(data.colour.truncate(), Vec3::splat(3.0));
+}
+
+#[test]
+fn directional_lights_do_not_attenuate() {
+    let mut light = Light::directional(vec3(0.0, -3.0, 4.0), Vec3::ONE, 1.0);
+    light.attenuation.quadratic = 10.0;
+
+    let data = light.to_raw();
+
+    assert_eq!(data.position.w, LightData::DIRECTIONAL);
+    assert_eq!(data.direction.truncate(), vec3(0.0, -0.6, 0.8));
+    assert_eq!(data.attenuation.truncate(), vec3(1.0, 0.0, 0.0));
+}
+
+#[test]
+fn more_lights_grow_the_light_buffer() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+
+    common::ground_and_cube(
+        &mut context,
+        common::Ground::SMALL,
+        vec![Light::point(vec3(0.0, 2.0, 0.0), Vec3::ONE, 1.0)],
+    );
+    let one = common::brightness(&mut context);
+
+    let ring = (0..32)
+        .map(|i| {
+            let angle = i as f32 / 32.0 * std::f32::consts::TAU;
+            Light::point(
+                vec3(angle.cos() * 3.0, 1.0, angle.sin() * 3.0),
+                Vec3::ONE,
+                0.5,
+            )
+        })
+        .collect();
+    context.set_lights(ring);
+    let many = common::brightness(&mut context);
+
+    assert_eq!(context.lights().len(), 32);
+    assert!(
+        many > one,
+        "32 lights ({many}) are not brighter than one ({one})"
+    );
+}
+
+#[test]
+fn directional_light_golden() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+
+    common::ground_and_cube(
+        &mut context,
+        common::Ground::SMALL,
+        vec![Light::directional(
+            vec3(0.6, -1.0, -0.4),
+            vec3(1.0, 0.95, 0.85),
+            1.0,
+        )],
+    );
+
+    common::assert_golden(&mut context, "light_directional");
+}
+
+#[test]
+fn point_lights_golden() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+
+    common::ground_and_cube(
+        &mut context,
+        common::Ground::SMALL,
+        common::two_point_lights(),
+    );
+
+    common::assert_golden(&mut context, "light_point");
+}
+
+#[test]
+fn spot_light_golden() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+
+    common::ground_and_cube(
+        &mut context,
+        common::Ground::SMALL,
+        vec![Light::spot(
+            vec3(-1.0, 3.0, 1.5),
+            vec3(0.3, -1.0, -0.4),
+            15.0,
+            25.0,
+            Vec3::ONE,
+            4.0,
+        )],
+    );
+
+    common::assert_golden(&mut context, "light_spot");
+}
@@ -0,0 +1,126 @@
+mod common;
+
+use std::collections::HashSet;
+
+use glam::{vec3, vec4, Quat, Vec3};
+use image::{DynamicImage, Rgba, RgbaImage};
+use wgpu_test::{
+    instance::Instance,
+    texture::{Texture, TextureOptions},
+    Context,
+};
+
+/// The pentagon in a single flat colour, so its edges are the only source of in-between
+/// colours.
+fn flat_pentagon(context: &mut Context) {
+    context
+        .set_texture(
+            &DynamicImage::ImageRgba8(RgbaImage::from_pixel(4, 4, Rgba([240, 200, 40, 255]))),
+            "flat",
+        )
+        .unwrap();
+    context.set_instances(vec![Instance {
+        position: Vec3::ZERO,
+        rotation: Quat::from_axis_angle(Vec3::Z, 0.3),
+        colour: vec4(1.0, 1.0, 1.0, 1.0),
+    }]);
+}
+
+fn distinct_colours(context: &mut Context) -> usize {
+    context.update();
+    let frame = context.capture_frame().unwrap();
+    frame.pixels().collect::<HashSet<_>>().len()
+}
+
+#[test]
+fn unsupported_sample_counts_are_rejected() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+
+    assert!(context.supported_sample_counts().contains(&1));
+
+    assert!(context.set_sample_count(3).is_err());
+    assert!(context.set_sample_count(0).is_err());
+    assert_eq!(context.sample_count(), 1);
+}
+
+#[test]
+fn multisampling_smooths_edges() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+    if !context.supported_sample_counts().contains(&4) {
+        return;
+    }
+
+    flat_pentagon(&mut context);
+    let aliased = distinct_colours(&mut context);
+
+    context.set_sample_count(4).unwrap();
+    let smoothed = distinct_colours(&mut context);
+
+    assert_eq!(context.sample_count(), 4);
+    assert!(
+        smoothed > aliased,
+        "4x MSAA gave {smoothed} colours, no MSAA {aliased}"
+    );
+
+    // And back off again
+    context.set_sample_count(1).unwrap();
+    assert_eq!(distinct_colours(&mut context), aliased);
+}
+
+#[test]
+fn resize_keeps_multisampling() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+    if !context.supported_sample_counts().contains(&4) {
+        return;
+    }
+
+    context.set_sample_count(4).unwrap();
+    context.resize(64, 48);
+    context.update();
+
+    let frame = context.capture_frame().unwrap();
+    assert_eq!(frame.dimensions(), (64, 48));
+}
+
+#[test]
+fn msaa_golden() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+    if !context.supported_sample_counts().contains(&4) {
+        return;
+    }
+
+    // Set before switching, so the skybox has to follow the new sample count
+    let faces = [
+        [220, 60, 60],
+        [60, 220, 220],
+        [60, 220, 60],
+        [220, 60, 220],
+        [60, 60, 220],
+        [220, 220, 60],
+    ]
+    .map(|[r, g, b]| DynamicImage::ImageRgba8(RgbaImage::from_pixel(8, 8, Rgba([r, g, b, 255]))));
+    let cubemap = Texture::cube_from_faces(
+        context.device(),
+        context.queue(),
+        &faces,
+        Some("faces"),
+        &TextureOptions::default(),
+    )
+    .unwrap();
+    context.set_skybox(cubemap);
+    context.set_sample_count(4).unwrap();
+
+    let camera = context.camera_mut();
+    camera.eye = vec3(0.0, 3.0, 6.0);
+    camera.target = vec3(0.0, 0.0, -1.0);
+
+    common::assert_golden(&mut context, "msaa_4x");
+}
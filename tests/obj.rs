@@ -0,0 +1,76 @@
+mod common;
+
+use glam::{vec3, vec4, Quat, Vec3};
+use wgpu_test::instance::Instance;
+
+const CUBE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/assets/cube.obj");
+
+#[test]
+fn loads_groups_and_materials() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+
+    let index = context.load_obj(CUBE).unwrap();
+    let model = &context.models()[index];
+
+    let names = model.meshes.iter().map(|mesh| mesh.name.as_str());
+    assert_eq!(names.collect::<Vec<_>>(), ["sides", "caps"]);
+
+    let materials = model
+        .materials
+        .iter()
+        .map(|material| material.name.as_str());
+    assert_eq!(materials.collect::<Vec<_>>(), ["red", "blue"]);
+
+    // Quads are triangulated into two triangles each
+    assert_eq!(model.meshes[0].num_elements, 4 * 6);
+    assert_eq!(model.meshes[1].num_elements, 2 * 6);
+    assert_eq!(model.meshes[0].material, Some(1));
+    assert_eq!(model.meshes[1].material, Some(0));
+}
+
+#[test]
+fn diffuse_colours_stay_linear() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+
+    let index = context.load_obj(CUBE).unwrap();
+
+    // `Kd` is linear already, an sRGB texture would decode it a second time
+    for material in &context.models()[index].materials {
+        let format = material.textures.base_colour.texture.format();
+        assert_eq!(format, wgpu::TextureFormat::Rgba8Unorm, "{}", material.name);
+    }
+}
+
+#[test]
+fn missing_file_is_an_error() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+
+    assert!(context.load_obj("tests/assets/does_not_exist.obj").is_err());
+    assert_eq!(context.models().len(), 1);
+}
+
+#[test]
+fn cube_golden() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+
+    context.clear_models();
+    context.load_obj(CUBE).unwrap();
+    context.set_instances(vec![Instance {
+        position: Vec3::ZERO,
+        rotation: Quat::from_axis_angle(Vec3::Y, f32::to_radians(30.0)),
+        colour: vec4(1.0, 1.0, 1.0, 1.0),
+    }]);
+
+    let camera = context.camera_mut();
+    camera.eye = vec3(-1.5, 1.2, 2.0);
+
+    common::assert_golden(&mut context, "obj_cube");
+}
@@ -0,0 +1,208 @@
+mod common;
+
+use glam::{vec2, vec3, vec4, Quat, Vec3};
+use image::{DynamicImage, Rgba, RgbaImage};
+use wgpu_test::{
+    instance::Instance,
+    light::Light,
+    model::{Material, MaterialFactors, MaterialTextures, Mesh, Model, ShadingModel, Vertex},
+    texture::{Texture, TextureOptions},
+    Context,
+};
+
+/// UV sphere of radius `radius` around `centre`, with U around the equator and V from pole to
+/// pole.
+fn sphere(context: &Context, centre: Vec3, radius: f32, material: usize) -> Mesh {
+    const RINGS: u32 = 16;
+    const SEGMENTS: u32 = 24;
+
+    let mut vertices = Vec::new();
+    for ring in 0..=RINGS {
+        let v = ring as f32 / RINGS as f32;
+        let polar = v * std::f32::consts::PI;
+
+        for segment in 0..=SEGMENTS {
+            let u = segment as f32 / SEGMENTS as f32;
+            let azimuth = u * std::f32::consts::TAU;
+            let normal = vec3(
+                polar.sin() * azimuth.cos(),
+                polar.cos(),
+                -polar.sin() * azimuth.sin(),
+            );
+
+            vertices.push(Vertex {
+                position: centre + normal * radius,
+                tex_coords: vec2(u, v),
+                normal,
+            });
+        }
+    }
+
+    let mut indices = Vec::new();
+    for ring in 0..RINGS {
+        for segment in 0..SEGMENTS {
+            let a = ring * (SEGMENTS + 1) + segment;
+            let b = a + SEGMENTS + 1;
+            indices.extend_from_slice(&[a, b, a + 1, a + 1, b, b + 1]);
+        }
+    }
+
+    Mesh::new(
+        context.device(),
+        "sphere",
+        &vertices,
+        &indices,
+        Some(material),
+    )
+}
+
+fn white(context: &Context) -> Texture {
+    Texture::from_colour(context.device(), context.queue(), [255; 4], "white")
+}
+
+fn show(context: &mut Context, model: Model, lights: Vec<Light>) {
+    context.clear_models();
+    context.add_model(model);
+    context.set_instances(vec![Instance {
+        position: Vec3::ZERO,
+        rotation: Quat::IDENTITY,
+        colour: vec4(1.0, 1.0, 1.0, 1.0),
+    }]);
+    context.set_ambient(Vec3::splat(0.03));
+    context.set_lights(lights);
+}
+
+#[test]
+fn blinn_phong_materials_have_no_maps() {
+    let Some(context) = common::context() else {
+        return;
+    };
+
+    let material = Material::new(
+        context.device(),
+        context.material_layout(),
+        "plain",
+        white(&context),
+    );
+
+    assert_eq!(material.shading, ShadingModel::BlinnPhong);
+    assert_eq!(material.factors, MaterialFactors::default());
+    assert!(material.textures.normal.is_none());
+}
+
+#[test]
+fn metallic_roughness_grid_golden() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+
+    // Metallic increases to the right, roughness downwards
+    let mut meshes = Vec::new();
+    let mut materials = Vec::new();
+    for row in 0..3 {
+        for column in 0..3 {
+            let factors = MaterialFactors {
+                base_colour: vec4(0.9, 0.6, 0.3, 1.0),
+                metallic: column as f32 / 2.0,
+                roughness: 0.2 + row as f32 * 0.35,
+                ..Default::default()
+            };
+
+            materials.push(Material::pbr(
+                context.device(),
+                context.material_layout(),
+                "sphere",
+                MaterialTextures::new(white(&context)),
+                factors,
+            ));
+            meshes.push(sphere(
+                &context,
+                vec3(column as f32 - 1.0, 1.0 - row as f32, 0.0),
+                0.45,
+                materials.len() - 1,
+            ));
+        }
+    }
+
+    show(
+        &mut context,
+        Model { meshes, materials },
+        vec![
+            Light::directional(vec3(-0.5, -0.6, -1.0), Vec3::ONE, 2.0),
+            Light::point(vec3(2.0, 2.0, 2.0), vec3(0.6, 0.7, 1.0), 6.0),
+        ],
+    );
+
+    let camera = context.camera_mut();
+    camera.eye = vec3(0.0, 0.0, 4.2);
+    camera.target = Vec3::ZERO;
+
+    common::assert_golden(&mut context, "pbr_spheres");
+}
+
+#[test]
+fn normal_map_golden() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+
+    // Horizontal ridges: the normal tilts up on the top half of each stripe and down on the
+    // bottom half
+    let bumps = RgbaImage::from_fn(64, 64, |_, y| {
+        let tilt = if y % 16 < 8 { 0.6 } else { -0.6 };
+        let normal = vec3(0.0, tilt, 1.0).normalize() * 0.5 + 0.5;
+        let [r, g, b] = (normal * 255.0).round().to_array().map(|c| c as u8);
+        Rgba([r, g, b, 255])
+    });
+    let normal = Texture::from_image_with_options(
+        context.device(),
+        context.queue(),
+        &DynamicImage::ImageRgba8(bumps),
+        Some("bumps"),
+        &TextureOptions::data(),
+    )
+    .unwrap();
+
+    let mut textures = MaterialTextures::new(white(&context));
+    textures.normal = Some(normal);
+
+    let material = Material::pbr(
+        context.device(),
+        context.material_layout(),
+        "bumpy",
+        textures,
+        MaterialFactors {
+            roughness: 0.6,
+            ..Default::default()
+        },
+    );
+
+    // Upright quad facing +Z, lit from above so the upward facing halves are brighter
+    let vertices = [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)].map(|(x, y)| Vertex {
+        position: vec3(x, y, 0.0),
+        tex_coords: vec2((x + 1.0) / 2.0, (1.0 - y) / 2.0),
+        normal: Vec3::Z,
+    });
+    let quad = Mesh::new(
+        context.device(),
+        "quad",
+        &vertices,
+        &[0, 1, 2, 0, 2, 3],
+        Some(0),
+    );
+
+    show(
+        &mut context,
+        Model {
+            meshes: vec![quad],
+            materials: vec![material],
+        },
+        vec![Light::directional(vec3(0.0, -1.0, -0.6), Vec3::ONE, 1.5)],
+    );
+
+    let camera = context.camera_mut();
+    camera.eye = vec3(0.0, 0.0, 2.6);
+    camera.target = Vec3::ZERO;
+
+    common::assert_golden(&mut context, "pbr_normal_map");
+}
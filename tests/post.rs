@@ -0,0 +1,223 @@
+mod common;
+
+use std::panic::{self, AssertUnwindSafe};
+
+use glam::{vec4, Vec4};
+use wgpu_test::{post::PostEffect, Context};
+
+/// Multiplies the image with the colour in its parameters.
+const TINT: &str = "#version 460
+
+layout(location = 0) in vec2 texCoords;
+
+layout(set = 0, binding = 0) uniform texture2D t_input;
+layout(set = 0, binding = 1) uniform sampler s_input;
+
+layout(set = 1, binding = 0) uniform Parameters {
+    vec4 tint;
+};
+
+layout(location = 0) out vec4 outColor;
+
+void main() {
+    outColor = texture(sampler2D(t_input, s_input), texCoords) * tint;
+}
+";
+
+/// Turns the image into its negative, parameters are unused.
+const INVERT: &str = "#version 460
+
+layout(location = 0) in vec2 texCoords;
+
+layout(set = 0, binding = 0) uniform texture2D t_input;
+layout(set = 0, binding = 1) uniform sampler s_input;
+
+layout(set = 1, binding = 0) uniform Parameters {
+    vec4 unused;
+};
+
+layout(location = 0) out vec4 outColor;
+
+void main() {
+    vec4 colour = texture(sampler2D(t_input, s_input), texCoords);
+
+    outColor = vec4(1.0 - colour.rgb, colour.a);
+}
+";
+
+/// Darkens towards the corners by `strength`.
+const VIGNETTE: &str = "#version 460
+
+layout(location = 0) in vec2 texCoords;
+
+layout(set = 0, binding = 0) uniform texture2D t_input;
+layout(set = 0, binding = 1) uniform sampler s_input;
+
+layout(set = 1, binding = 0) uniform Parameters {
+    float strength;
+};
+
+layout(location = 0) out vec4 outColor;
+
+void main() {
+    vec4 colour = texture(sampler2D(t_input, s_input), texCoords);
+    float falloff = 1.0 - strength * dot(texCoords - 0.5, texCoords - 0.5) * 2.0;
+
+    outColor = vec4(colour.rgb * falloff, colour.a);
+}
+";
+
+fn tint(context: &Context, colour: Vec4) -> PostEffect {
+    PostEffect::new(
+        context.device(),
+        context.post_layout(),
+        "tint",
+        TINT,
+        &colour,
+    )
+}
+
+fn invert(context: &Context) -> PostEffect {
+    PostEffect::new(
+        context.device(),
+        context.post_layout(),
+        "invert",
+        INVERT,
+        &Vec4::ZERO,
+    )
+}
+
+/// sRGB encoded red of the frame's top left pixel. With no instances the whole frame is the
+/// clear colour, whose red is 0.1 before post-processing.
+fn red(context: &mut Context) -> u8 {
+    context.update();
+    context.capture_frame().unwrap().get_pixel(0, 0)[0]
+}
+
+fn srgb(linear: f32) -> u8 {
+    let encoded = if linear <= 0.0031308 {
+        linear * 12.92
+    } else {
+        1.055 * linear.powf(1.0 / 2.4) - 0.055
+    };
+
+    (encoded * 255.0).round() as u8
+}
+
+fn assert_red(context: &mut Context, linear: f32) {
+    let red = red(context);
+
+    assert!(
+        red.abs_diff(srgb(linear)) <= 1,
+        "red is {red}, expected {}",
+        srgb(linear)
+    );
+}
+
+#[test]
+fn effects_run_in_order() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+
+    context.set_instances(vec![]);
+    assert!(context.post_effects().is_empty());
+    assert_red(&mut context, 0.1);
+
+    // One effect leaves the image in the spare target, two bring it back
+    let tint = tint(&context, vec4(0.5, 1.0, 1.0, 1.0));
+    context.add_post_effect(tint);
+    assert_red(&mut context, 0.05);
+
+    let invert = invert(&context);
+    assert_eq!(context.add_post_effect(invert), 1);
+    assert_red(&mut context, 0.95);
+
+    context.post_effects_mut().swap(0, 1);
+    assert_eq!(context.post_effects()[0].name(), "invert");
+    assert_red(&mut context, 0.45);
+}
+
+#[test]
+fn disabled_effects_are_skipped() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+
+    context.set_instances(vec![]);
+    let tint = tint(&context, vec4(0.5, 1.0, 1.0, 1.0));
+    let invert = invert(&context);
+    context.add_post_effect(tint);
+    context.add_post_effect(invert);
+
+    context.post_effects_mut()[1].enabled = false;
+    assert_red(&mut context, 0.05);
+
+    context.post_effects_mut()[0].enabled = false;
+    assert_red(&mut context, 0.1);
+
+    context.post_effects_mut()[1].enabled = true;
+    assert_red(&mut context, 0.9);
+}
+
+#[test]
+fn parameters_apply_on_update() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+
+    context.set_instances(vec![]);
+    let tint = tint(&context, Vec4::ONE);
+    context.add_post_effect(tint);
+    assert_red(&mut context, 0.1);
+
+    context.post_effects_mut()[0].set_parameters(&vec4(4.0, 1.0, 1.0, 1.0));
+    assert_red(&mut context, 0.4);
+}
+
+#[test]
+fn parameters_keep_their_size() {
+    let Some(context) = common::context() else {
+        return;
+    };
+
+    let mut tint = tint(&context, Vec4::ONE);
+    let resized = panic::catch_unwind(AssertUnwindSafe(|| tint.set_parameters(&1.0f32)));
+
+    assert!(resized.is_err());
+}
+
+#[test]
+fn resize_keeps_the_stack() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+
+    context.set_instances(vec![]);
+    let invert = invert(&context);
+    context.add_post_effect(invert);
+    context.resize(64, 48);
+
+    assert_red(&mut context, 0.9);
+    assert_eq!(context.capture_frame().unwrap().dimensions(), (64, 48));
+}
+
+#[test]
+fn post_stack_golden() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+
+    let tint = tint(&context, vec4(1.0, 0.8, 0.6, 1.0));
+    let vignette = PostEffect::new(
+        context.device(),
+        context.post_layout(),
+        "vignette",
+        VIGNETTE,
+        &1.5f32,
+    );
+    context.add_post_effect(tint);
+    context.add_post_effect(vignette);
+
+    common::assert_golden(&mut context, "post_stack");
+}
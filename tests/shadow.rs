@@ -0,0 +1,386 @@
+mod common;
+
+use glam::{vec3, vec4, Quat, Vec3};
+use wgpu_test::{
+    camera::Camera,
+    instance::Instance,
+    light::Light,
+    shadow::{PointShadowMap, PointShadowSettings, ShadowMap, ShadowSettings},
+    Context,
+};
+
+/// The shared cube floating above the wide ground, under a low sun.
+fn shadow_scene(context: &mut Context) {
+    common::ground_and_cube(
+        context,
+        common::Ground::WIDE,
+        vec![Light::directional(vec3(1.0, -1.5, -0.5), Vec3::ONE, 1.0)],
+    );
+    context.set_instances(vec![Instance {
+        position: vec3(0.0, 0.6, 0.0),
+        rotation: Quat::from_axis_angle(Vec3::Y, f32::to_radians(30.0)),
+        colour: vec4(1.0, 1.0, 1.0, 1.0),
+    }]);
+    context.set_ambient(Vec3::splat(0.1));
+
+    let camera = context.camera_mut();
+    camera.eye = vec3(-3.0, 3.0, 4.0);
+    camera.target = vec3(0.5, -0.8, 0.0);
+}
+
+#[test]
+fn splits_end_at_the_shadow_distance() {
+    let Some(context) = common::context() else {
+        return;
+    };
+
+    let camera = *context.camera();
+    let settings = ShadowSettings {
+        distance: 40.0,
+        ..Default::default()
+    };
+
+    let splits = ShadowMap::new(
+        context.device(),
+        context.adapter().get_info().backend,
+        settings,
+    )
+    .splits(&camera);
+    assert_eq!(splits.len(), 4);
+    assert!(
+        splits.windows(2).all(|pair| pair[0] < pair[1]),
+        "{splits:?}"
+    );
+    assert!((splits[3] - 40.0).abs() < 1e-4, "{splits:?}");
+
+    // Evenly spaced without the logarithmic part
+    let even = ShadowMap::new(
+        context.device(),
+        context.adapter().get_info().backend,
+        ShadowSettings {
+            split_lambda: 0.0,
+            cascades: 2,
+            ..settings
+        },
+    )
+    .splits(&camera);
+    let middle = camera.z_near + (40.0 - camera.z_near) / 2.0;
+    assert!((even[0] - middle).abs() < 1e-4, "{even:?}");
+
+    // Never past the far plane
+    let far = ShadowMap::new(
+        context.device(),
+        context.adapter().get_info().backend,
+        ShadowSettings::default(),
+    )
+    .splits(&Camera {
+        z_far: 20.0,
+        ..camera
+    });
+    assert!((far[3] - 20.0).abs() < 1e-4, "{far:?}");
+}
+
+#[test]
+fn cascades_cover_their_slice() {
+    let Some(context) = common::context() else {
+        return;
+    };
+
+    let camera = *context.camera();
+    let shadow_map = ShadowMap::new(
+        context.device(),
+        context.adapter().get_info().backend,
+        ShadowSettings::default(),
+    );
+    let forward = camera.forward().normalize();
+
+    let mut near = camera.z_near;
+    for cascade in shadow_map.cascades(&camera, vec3(1.0, -2.0, 0.5)) {
+        let middle = camera.eye + forward * (near + cascade.split) / 2.0;
+        let ndc = cascade.view_projection.project_point3(middle);
+
+        assert!(ndc.abs().max_element() < 1.0, "{ndc}");
+        near = cascade.split;
+    }
+}
+
+#[test]
+fn cascades_snap_to_whole_texels() {
+    let Some(context) = common::context() else {
+        return;
+    };
+
+    let settings = ShadowSettings::default();
+    let shadow_map = ShadowMap::new(
+        context.device(),
+        context.adapter().get_info().backend,
+        settings,
+    );
+    let direction = vec3(1.0, -2.0, 0.5);
+    let camera = *context.camera();
+    let moved = Camera {
+        eye: camera.eye + vec3(0.013, 0.0, 0.007),
+        target: camera.target + vec3(0.013, 0.0, 0.007),
+        ..camera
+    };
+
+    // A fixed point lands on the same spot within a texel however the camera slides
+    let texel = |camera: &Camera| {
+        let cascade = shadow_map.cascades(camera, direction)[0];
+        let ndc = cascade.view_projection.project_point3(Vec3::ZERO);
+        (ndc.truncate() * 0.5 + 0.5) * settings.resolution as f32
+    };
+    let offset = texel(&moved) - texel(&camera);
+
+    assert!(
+        offset.abs_diff_eq(offset.round(), 1e-2),
+        "moved by {offset} texels"
+    );
+}
+
+#[test]
+fn shadow_resolution_is_clamped_to_the_device() {
+    let Some(context) = common::context() else {
+        return;
+    };
+
+    let shadow_map = ShadowMap::new(
+        context.device(),
+        context.adapter().get_info().backend,
+        ShadowSettings {
+            resolution: u32::MAX,
+            ..Default::default()
+        },
+    );
+
+    assert_eq!(
+        shadow_map.settings().resolution,
+        context.device().limits().max_texture_dimension_2d
+    );
+}
+
+#[test]
+fn shadows_are_off_by_default() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+
+    shadow_scene(&mut context);
+    let unshadowed = common::brightness(&mut context);
+
+    assert!(context.shadows().is_none());
+
+    context.set_shadows(Some(ShadowSettings::default()));
+    let shadowed = common::brightness(&mut context);
+
+    assert!(
+        shadowed < unshadowed,
+        "shadows ({shadowed}) did not darken the scene ({unshadowed})"
+    );
+
+    context.set_shadows(None);
+    assert_eq!(common::brightness(&mut context), unshadowed);
+}
+
+#[test]
+fn lights_without_a_directional_light_cast_no_shadow() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+
+    shadow_scene(&mut context);
+    context.set_lights(vec![Light::point(vec3(0.0, 3.0, 0.0), Vec3::ONE, 2.0)]);
+    let unshadowed = common::brightness(&mut context);
+
+    context.set_shadows(Some(ShadowSettings::default()));
+
+    assert_eq!(common::brightness(&mut context), unshadowed);
+}
+
+#[test]
+fn directional_shadow_golden() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+
+    shadow_scene(&mut context);
+    context.set_shadows(Some(ShadowSettings {
+        distance: 15.0,
+        ..Default::default()
+    }));
+
+    common::assert_golden(&mut context, "shadow_directional");
+}
+
+#[test]
+fn hard_shadow_golden() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+
+    shadow_scene(&mut context);
+    context.set_shadows(Some(ShadowSettings {
+        resolution: 256,
+        cascades: 1,
+        distance: 15.0,
+        pcf_radius: 0,
+        ..Default::default()
+    }));
+
+    common::assert_golden(&mut context, "shadow_hard");
+}
+
+#[test]
+fn cascaded_field_golden() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+
+    shadow_scene(&mut context);
+
+    // A field of cubes reaching far into the distance, each cascade shadows part of it
+    let instances = (0..16)
+        .flat_map(|z| {
+            (0..6).map(move |x| Instance {
+                position: vec3(x as f32 * 3.0 - 7.5, 0.0, -(z as f32) * 3.0),
+                rotation: Quat::from_axis_angle(Vec3::Y, (x * 16 + z) as f32 * 0.4),
+                colour: vec4(1.0, 1.0, 1.0, 1.0),
+            })
+        })
+        .collect();
+    context.set_instances(instances);
+    context.set_shadows(Some(ShadowSettings {
+        resolution: 512,
+        distance: 45.0,
+        ..Default::default()
+    }));
+
+    let camera = context.camera_mut();
+    camera.eye = vec3(0.0, 3.0, 6.0);
+    camera.target = vec3(0.0, 0.0, -10.0);
+
+    common::assert_golden(&mut context, "shadow_cascades");
+}
+
+#[test]
+fn point_shadow_faces_follow_cube_sampling() {
+    let Some(context) = common::context() else {
+        return;
+    };
+
+    let shadow_map = PointShadowMap::new(
+        context.device(),
+        context.adapter().get_info().backend,
+        PointShadowSettings::default(),
+    );
+    let light = vec3(1.0, 2.0, 3.0);
+
+    // Face, a direction that lands on it, and where cube samplers read that direction from in
+    // normalised device coordinates (X right, Y up)
+    let cases = [
+        (0, vec3(1.0, 0.5, -0.5), [0.5, 0.5]),
+        (1, vec3(-1.0, 0.5, -0.5), [-0.5, 0.5]),
+        (2, vec3(0.5, 1.0, -0.5), [0.5, 0.5]),
+        (3, vec3(0.5, -1.0, 0.5), [0.5, 0.5]),
+        (4, vec3(0.5, 0.5, 1.0), [0.5, 0.5]),
+        (5, vec3(0.5, 0.5, -1.0), [-0.5, 0.5]),
+    ];
+
+    for (face, direction, expected) in cases {
+        let ndc = shadow_map
+            .face_view_projection(light, face)
+            .project_point3(light + direction * 2.0);
+
+        assert!(
+            ndc.truncate().abs_diff_eq(expected.into(), 1e-5),
+            "face {face}: {ndc}"
+        );
+    }
+}
+
+#[test]
+fn point_shadow_resolution_is_clamped_to_the_device() {
+    let Some(context) = common::context() else {
+        return;
+    };
+
+    let shadow_map = PointShadowMap::new(
+        context.device(),
+        context.adapter().get_info().backend,
+        PointShadowSettings {
+            resolution: u32::MAX,
+            ..Default::default()
+        },
+    );
+
+    assert_eq!(
+        shadow_map.settings().resolution,
+        context.device().limits().max_texture_dimension_2d
+    );
+}
+
+#[test]
+fn point_shadows_come_from_the_first_point_lights() {
+    let Some(context) = common::context() else {
+        return;
+    };
+
+    let shadow_map = PointShadowMap::new(
+        context.device(),
+        context.adapter().get_info().backend,
+        PointShadowSettings {
+            lights: 2,
+            ..Default::default()
+        },
+    );
+    let lights = [
+        Light::directional(Vec3::NEG_Y, Vec3::ONE, 1.0),
+        Light::point(Vec3::X, Vec3::ONE, 1.0),
+        Light::spot(Vec3::Y, Vec3::NEG_Y, 10.0, 20.0, Vec3::ONE, 1.0),
+        Light::point(Vec3::Y, Vec3::ONE, 1.0),
+        Light::point(Vec3::Z, Vec3::ONE, 1.0),
+    ];
+
+    assert_eq!(shadow_map.casters(&lights), [(1, Vec3::X), (3, Vec3::Y)]);
+}
+
+#[test]
+fn point_shadows_darken_the_scene() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+
+    shadow_scene(&mut context);
+    context.set_lights(vec![Light::point(vec3(-1.0, 3.0, 1.0), Vec3::ONE, 3.0)]);
+    let unshadowed = common::brightness(&mut context);
+
+    assert!(context.point_shadows().is_none());
+
+    context.set_point_shadows(Some(PointShadowSettings::default()));
+    let shadowed = common::brightness(&mut context);
+
+    assert!(
+        shadowed < unshadowed,
+        "point shadows ({shadowed}) did not darken the scene ({unshadowed})"
+    );
+}
+
+#[test]
+fn point_shadow_golden() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+
+    shadow_scene(&mut context);
+    // Two lights on either side, each casting its own shadow away from it
+    context.set_lights(vec![
+        Light::point(vec3(-2.0, 2.5, 1.0), vec3(1.0, 0.8, 0.6), 3.0),
+        Light::point(vec3(2.5, 2.0, -1.0), vec3(0.6, 0.8, 1.0), 3.0),
+    ]);
+    context.set_point_shadows(Some(PointShadowSettings {
+        lights: 2,
+        ..Default::default()
+    }));
+
+    common::assert_golden(&mut context, "shadow_point");
+}
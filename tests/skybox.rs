@@ -0,0 +1,164 @@
+mod common;
+
+use glam::{vec3, Vec3};
+use image::{DynamicImage, Rgba, Rgba32FImage, RgbaImage};
+use wgpu_test::texture::{Texture, TextureOptions};
+
+/// One solid colour per face, `+X, -X, +Y, -Y, +Z, -Z`.
+fn coloured_faces() -> [DynamicImage; 6] {
+    [
+        [220, 60, 60],
+        [60, 220, 220],
+        [60, 220, 60],
+        [220, 60, 220],
+        [60, 60, 220],
+        [220, 220, 60],
+    ]
+    .map(|[r, g, b]| DynamicImage::ImageRgba8(RgbaImage::from_pixel(16, 16, Rgba([r, g, b, 255]))))
+}
+
+/// Sky gradient from white at the zenith to dark blue at the horizon, with a brown ground
+/// and a red band marking the +X direction.
+fn equirect() -> Rgba32FImage {
+    Rgba32FImage::from_fn(64, 32, |x, y| {
+        let latitude = 1.0 - (y as f32 + 0.5) / 16.0;
+
+        if (30..34).contains(&x) {
+            Rgba([1.0, 0.05, 0.05, 1.0])
+        } else if latitude < 0.0 {
+            Rgba([0.2, 0.1, 0.05, 1.0])
+        } else {
+            Rgba([
+                0.05 + 0.9 * latitude,
+                0.1 + 0.85 * latitude,
+                0.4 + 0.6 * latitude,
+                1.0,
+            ])
+        }
+    })
+}
+
+#[test]
+fn faces_must_be_square_and_equal() {
+    let Some(context) = common::context() else {
+        return;
+    };
+
+    let mut faces = coloured_faces();
+    faces[3] = DynamicImage::ImageRgba8(RgbaImage::new(16, 8));
+
+    let result = Texture::cube_from_faces(
+        context.device(),
+        context.queue(),
+        &faces,
+        None,
+        &TextureOptions::default(),
+    );
+
+    assert!(result.is_err());
+}
+
+#[test]
+fn equirectangular_builds_float_cube() {
+    let Some(context) = common::context() else {
+        return;
+    };
+
+    let cubemap = Texture::cube_from_equirectangular(
+        context.device(),
+        context.queue(),
+        &equirect(),
+        32,
+        Some("sky"),
+        &TextureOptions::default(),
+    )
+    .unwrap();
+
+    assert_eq!(cubemap.texture.format(), wgpu::TextureFormat::Rgba16Float);
+    assert_eq!(cubemap.texture.depth_or_array_layers(), 6);
+    assert_eq!(cubemap.texture.width(), 32);
+    assert_eq!(cubemap.texture.mip_level_count(), 6);
+}
+
+#[test]
+fn sky_ignores_camera_translation() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+
+    let cubemap = Texture::cube_from_faces(
+        context.device(),
+        context.queue(),
+        &coloured_faces(),
+        Some("faces"),
+        &TextureOptions::default(),
+    )
+    .unwrap();
+    context.set_skybox(cubemap);
+    context.set_instances(vec![]);
+
+    let frame = |context: &mut wgpu_test::Context, eye: Vec3| {
+        let camera = context.camera_mut();
+        camera.eye = eye;
+        camera.target = eye + vec3(1.0, 0.5, -1.0);
+        context.update();
+        context.capture_frame().unwrap()
+    };
+
+    let near = frame(&mut context, Vec3::ZERO);
+    let far = frame(&mut context, vec3(40.0, -8.0, 12.0));
+
+    assert!(near == far, "moving the camera changed the sky");
+}
+
+#[test]
+fn skybox_faces_golden() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+
+    let cubemap = Texture::cube_from_faces(
+        context.device(),
+        context.queue(),
+        &coloured_faces(),
+        Some("faces"),
+        &TextureOptions::default(),
+    )
+    .unwrap();
+    context.set_skybox(cubemap);
+
+    // Looking into the corner between +X, +Y and -Z, over the pentagon grid
+    let camera = context.camera_mut();
+    camera.eye = vec3(-2.0, 1.5, 3.0);
+    camera.target = vec3(1.0, 2.0, -1.0);
+    camera.fov_y = 90.0;
+
+    common::assert_golden(&mut context, "skybox_faces");
+}
+
+#[test]
+fn skybox_equirectangular_golden() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+
+    let cubemap = Texture::cube_from_equirectangular(
+        context.device(),
+        context.queue(),
+        &equirect(),
+        32,
+        Some("sky"),
+        &TextureOptions::default(),
+    )
+    .unwrap();
+    context.set_skybox(cubemap);
+    context.set_instances(vec![]);
+
+    // Facing +X, so the red band is in the middle with the horizon across the frame
+    let camera = context.camera_mut();
+    camera.eye = Vec3::ZERO;
+    camera.target = vec3(1.0, 0.2, 0.0);
+    camera.fov_y = 90.0;
+
+    common::assert_golden(&mut context, "skybox_equirect");
+}
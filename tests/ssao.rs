@@ -0,0 +1,103 @@
+mod common;
+
+use glam::{vec3, vec4, Quat, Vec3};
+use wgpu_test::{instance::Instance, ssao::SsaoSettings, Context};
+
+/// The shared cube above the wide ground, lit by ambient light alone.
+fn contact_scene(context: &mut Context) {
+    common::ground_and_cube(context, common::Ground::WIDE, vec![]);
+    context.set_instances(vec![Instance {
+        position: vec3(0.0, -0.5, 0.0),
+        rotation: Quat::from_axis_angle(Vec3::Y, f32::to_radians(30.0)),
+        colour: vec4(1.0, 1.0, 1.0, 1.0),
+    }]);
+    context.set_ambient(Vec3::ONE);
+
+    let camera = context.camera_mut();
+    camera.eye = vec3(-2.0, 1.5, 3.0);
+    camera.target = vec3(0.0, -0.6, 0.0);
+}
+
+#[test]
+fn ssao_is_off_by_default() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+
+    contact_scene(&mut context);
+    let plain = common::brightness(&mut context);
+    assert!(context.ssao().is_none());
+
+    context.set_ssao(Some(SsaoSettings::default()));
+    assert_eq!(context.ssao(), Some(&SsaoSettings::default()));
+    assert!(common::brightness(&mut context) < plain);
+
+    context.set_ssao(None);
+    assert_eq!(common::brightness(&mut context), plain);
+}
+
+#[test]
+fn empty_scenes_are_not_occluded() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+
+    context.set_instances(vec![]);
+    let plain = common::brightness(&mut context);
+
+    context.set_ssao(Some(SsaoSettings::default()));
+    assert_eq!(common::brightness(&mut context), plain);
+}
+
+#[test]
+fn power_deepens_the_occlusion() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+
+    contact_scene(&mut context);
+
+    context.set_ssao(Some(SsaoSettings {
+        power: 1.0,
+        ..Default::default()
+    }));
+    let soft = common::brightness(&mut context);
+
+    context.set_ssao(Some(SsaoSettings {
+        power: 3.0,
+        ..Default::default()
+    }));
+    let deep = common::brightness(&mut context);
+
+    assert!(deep < soft, "{deep} {soft}");
+}
+
+#[test]
+fn resize_keeps_ssao() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+
+    contact_scene(&mut context);
+    context.set_ssao(Some(SsaoSettings::default()));
+
+    for (width, height) in [(77, 45), (1, 1)] {
+        context.resize(width, height);
+        context.update();
+
+        let frame = context.capture_frame().unwrap();
+        assert_eq!(frame.dimensions(), (width, height));
+    }
+}
+
+#[test]
+fn ssao_golden() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+
+    contact_scene(&mut context);
+    context.set_ssao(Some(SsaoSettings::default()));
+
+    common::assert_golden(&mut context, "ssao_contact");
+}
@@ -0,0 +1,171 @@
+mod common;
+
+use glam::{vec2, vec3, vec4, Quat, Vec3};
+use wgpu_test::{
+    instance::Instance,
+    model::{Material, Mesh, Model, Vertex},
+    texture::{Texture, TextureOptions},
+};
+
+#[test]
+fn from_image_allocates_full_mip_chain() {
+    let Some(context) = common::context() else {
+        return;
+    };
+
+    let img = image::DynamicImage::ImageRgba8(image::RgbaImage::new(64, 20));
+    let texture = Texture::from_image(context.device(), context.queue(), &img, None).unwrap();
+
+    // 64, 32, 16, 8, 4, 2, 1
+    assert_eq!(texture.texture.mip_level_count(), 7);
+
+    let texture = Texture::placeholder(context.device(), context.queue());
+    assert_eq!(texture.texture.mip_level_count(), 1);
+}
+
+#[test]
+fn data_textures_are_linear() {
+    let Some(context) = common::context() else {
+        return;
+    };
+
+    let img = image::DynamicImage::ImageRgba8(image::RgbaImage::new(4, 4));
+    let options = TextureOptions {
+        generate_mipmaps: false,
+        ..TextureOptions::data()
+    };
+    let texture =
+        Texture::from_image_with_options(context.device(), context.queue(), &img, None, &options)
+            .unwrap();
+
+    assert_eq!(texture.texture.format(), wgpu::TextureFormat::Rgba8Unorm);
+    assert_eq!(texture.texture.mip_level_count(), 1);
+    assert!(!texture
+        .texture
+        .usage()
+        .contains(wgpu::TextureUsages::RENDER_ATTACHMENT));
+}
+
+#[test]
+fn anisotropy_requires_linear_filtering() {
+    let Some(context) = common::context() else {
+        return;
+    };
+
+    let img = image::DynamicImage::ImageRgba8(image::RgbaImage::new(4, 4));
+    let options = TextureOptions {
+        anisotropy_clamp: 16,
+        mipmap_filter: wgpu::FilterMode::Nearest,
+        ..TextureOptions::tiling()
+    };
+
+    assert!(Texture::from_image_with_options(
+        context.device(),
+        context.queue(),
+        &img,
+        None,
+        &options
+    )
+    .is_err());
+}
+
+#[test]
+fn tiling_texture_golden() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+
+    let options = TextureOptions {
+        anisotropy_clamp: 8,
+        ..TextureOptions::tiling()
+    };
+    let img = image::DynamicImage::ImageRgba8(common::checkerboard());
+    let texture =
+        Texture::from_image_with_options(context.device(), context.queue(), &img, None, &options)
+            .unwrap();
+
+    let vertices = [(-4.0, -4.0), (4.0, -4.0), (4.0, 4.0), (-4.0, 4.0)].map(|(x, z)| Vertex {
+        position: vec3(x, 0.0, z),
+        // Eight repeats across the ground plane
+        tex_coords: vec2(x + 4.0, z + 4.0),
+        normal: Vec3::Y,
+    });
+    let material = Material::new(
+        context.device(),
+        context.material_layout(),
+        "ground",
+        texture,
+    );
+    let mesh = Mesh::new(
+        context.device(),
+        "ground",
+        &vertices,
+        &[0, 2, 1, 0, 3, 2],
+        Some(0),
+    );
+
+    context.clear_models();
+    context.add_model(Model {
+        meshes: vec![mesh],
+        materials: vec![material],
+    });
+    context.set_instances(vec![Instance {
+        position: Vec3::ZERO,
+        rotation: Quat::IDENTITY,
+        colour: vec4(1.0, 1.0, 1.0, 1.0),
+    }]);
+
+    let camera = context.camera_mut();
+    camera.eye = vec3(0.0, 0.6, 3.5);
+
+    common::assert_golden(&mut context, "tiling_ground");
+}
+
+#[test]
+fn generated_levels_average_the_level_above() {
+    let Some(context) = common::context() else {
+        return;
+    };
+
+    // Every 2x2 block averages to (100, 50, 200), the whole image to (100, 100, 200)
+    let img = image::RgbaImage::from_fn(4, 4, |x, y| {
+        let green = if y < 2 { 50 } else { 150 };
+        if (x + y) % 2 == 0 {
+            image::Rgba([40, green, 180, 255])
+        } else {
+            image::Rgba([160, green, 220, 255])
+        }
+    });
+    let options = TextureOptions {
+        usage: wgpu::TextureUsages::COPY_SRC,
+        ..TextureOptions::data()
+    };
+    let texture = Texture::from_image_with_options(
+        context.device(),
+        context.queue(),
+        &image::DynamicImage::ImageRgba8(img),
+        None,
+        &options,
+    )
+    .unwrap();
+
+    let close = |texel: &[u8], expected: [u8; 4]| {
+        texel
+            .iter()
+            .zip(expected)
+            .all(|(&got, e)| got.abs_diff(e) <= 1)
+    };
+
+    let level = common::read_level(context.device(), context.queue(), &texture, 1);
+    assert_eq!(level.len(), 2 * 2 * 4);
+    for (index, texel) in level.chunks(4).enumerate() {
+        let green = if index < 2 { 50 } else { 150 };
+        assert!(
+            close(texel, [100, green, 200, 255]),
+            "level 1 texel {index} is {texel:?}"
+        );
+    }
+
+    let level = common::read_level(context.device(), context.queue(), &texture, 2);
+    assert!(close(&level, [100, 100, 200, 255]), "level 2 is {level:?}");
+}
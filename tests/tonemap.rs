@@ -0,0 +1,127 @@
+mod common;
+
+use glam::{vec3, Vec3};
+use image::RgbaImage;
+use wgpu_test::{
+    light::Light,
+    tonemap::{TonemapSettings, Tonemapper},
+    Context,
+};
+
+/// The shared ground and cube under a sun far too bright for the display.
+fn bright_scene(context: &mut Context) {
+    common::ground_and_cube(
+        context,
+        common::Ground::SMALL,
+        vec![Light::directional(
+            vec3(1.0, -2.0, -1.5),
+            vec3(1.0, 0.9, 0.8),
+            6.0,
+        )],
+    );
+    context.set_ambient(Vec3::splat(0.1));
+}
+
+fn clipped(frame: &RgbaImage) -> usize {
+    frame.pixels().filter(|pixel| pixel[0] == 255).count()
+}
+
+#[test]
+fn tonemapping_defaults_to_clamping() {
+    let Some(context) = common::context() else {
+        return;
+    };
+
+    assert_eq!(context.tonemapping(), TonemapSettings::default());
+    assert_eq!(context.tonemapping().tonemapper, Tonemapper::Clamp);
+    assert_eq!(context.tonemapping().exposure, 1.0);
+}
+
+#[test]
+fn exposure_recovers_clipped_highlights() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+
+    bright_scene(&mut context);
+    let clamped = common::frame(&mut context);
+    assert!(clipped(&clamped) > 1000, "{} clipped", clipped(&clamped));
+
+    // Values above 1 survive until tonemapping, so scaling them down brings the detail back
+    context.set_tonemapping(TonemapSettings {
+        exposure: 0.1,
+        ..Default::default()
+    });
+    let exposed = common::frame(&mut context);
+
+    assert_eq!(clipped(&exposed), 0);
+    assert!(exposed.pixels().map(|pixel| pixel[0]).max().unwrap() > 128);
+}
+
+#[test]
+fn curves_never_clip() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+
+    bright_scene(&mut context);
+
+    for tonemapper in [Tonemapper::Reinhard, Tonemapper::Aces, Tonemapper::Agx] {
+        context.set_tonemapping(TonemapSettings {
+            tonemapper,
+            ..Default::default()
+        });
+        let frame = common::frame(&mut context);
+
+        assert!(
+            clipped(&frame) < 100,
+            "{tonemapper:?}: {} clipped",
+            clipped(&frame)
+        );
+    }
+}
+
+#[test]
+fn reinhard_golden() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+
+    bright_scene(&mut context);
+    context.set_tonemapping(TonemapSettings {
+        tonemapper: Tonemapper::Reinhard,
+        ..Default::default()
+    });
+
+    common::assert_golden(&mut context, "tonemap_reinhard");
+}
+
+#[test]
+fn aces_golden() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+
+    bright_scene(&mut context);
+    context.set_tonemapping(TonemapSettings {
+        tonemapper: Tonemapper::Aces,
+        exposure: 0.6,
+    });
+
+    common::assert_golden(&mut context, "tonemap_aces");
+}
+
+#[test]
+fn agx_golden() {
+    let Some(mut context) = common::context() else {
+        return;
+    };
+
+    bright_scene(&mut context);
+    context.set_tonemapping(TonemapSettings {
+        tonemapper: Tonemapper::Agx,
+        ..Default::default()
+    });
+
+    common::assert_golden(&mut context, "tonemap_agx");
+}